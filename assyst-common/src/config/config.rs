@@ -12,6 +12,8 @@ pub struct AssystConfig {
     pub logging_webhooks: LoggingWebhooks,
     pub dev: DevAttributes,
     pub entitlements: Entitlements,
+    #[serde(default)]
+    pub javascript: Javascript,
 }
 
 #[derive(Deserialize)]
@@ -35,6 +37,36 @@ pub struct Urls {
     pub cobalt_api: Vec<CobaltApiInstance>,
 }
 
+/// Where JavaScript from tags is executed.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum JavascriptBackend {
+    /// The eval service at `urls.eval`.
+    #[default]
+    Remote,
+    /// The embedded dash VM, in a sandboxed child process.
+    Local,
+    /// The eval service, or the dash VM if the eval service fails.
+    Fallback,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct Javascript {
+    pub backend: JavascriptBackend,
+    pub memory_limit_mb: u64,
+    pub time_limit_ms: u64,
+}
+impl Default for Javascript {
+    fn default() -> Self {
+        Self {
+            backend: JavascriptBackend::Remote,
+            memory_limit_mb: 128,
+            time_limit_ms: 2000,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Authentication {
     pub discord_token: String,
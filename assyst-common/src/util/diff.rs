@@ -0,0 +1,48 @@
+/// Inputs with more lines than this (combined) are not diffed line by line, as the diff takes
+/// quadratic time and memory
+const MAX_DIFF_LINES: usize = 2000;
+
+/// Generates a line based diff in the format of a `diff` codeblock, where removed lines are
+/// prefixed with `-`, added lines with `+` and unchanged lines with a space
+pub fn line_diff(old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    if old.len() + new.len() > MAX_DIFF_LINES {
+        return old
+            .iter()
+            .map(|line| format!("-{line}\n"))
+            .chain(new.iter().map(|line| format!("+{line}\n")))
+            .collect();
+    }
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out += &format!(" {}\n", old[i]);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out += &format!("+{}\n", new[j]);
+            j += 1;
+        } else {
+            out += &format!("-{}\n", old[i]);
+            i += 1;
+        }
+    }
+
+    out
+}
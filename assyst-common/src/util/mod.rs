@@ -11,6 +11,7 @@ use tracing_subscriber::fmt::time::UtcTime;
 use tracing_subscriber::EnvFilter;
 use twilight_model::channel::message::Mention;
 
+pub mod diff;
 pub mod discord;
 pub mod filetype;
 pub mod process;
@@ -21,6 +21,7 @@ use crate::persistent_cache_handler::PersistentCacheHandler;
 use crate::replies::Replies;
 use crate::rest::patreon::Patron;
 use crate::rest::rest_cache_handler::RestCacheHandler;
+use crate::tag_uses::TagUses;
 use crate::task::Task;
 
 pub type ThreadSafeAssyst = Arc<Assyst>;
@@ -64,6 +65,8 @@ pub struct Assyst {
     pub entitlements: Arc<Mutex<HashMap<i64, ActiveGuildPremiumEntitlement>>>,
     /// Component contexts, mapping a custom ID (e.g., a button) to a context.
     pub component_contexts: ComponentCtxts,
+    /// Tag uses that were not written to the database yet.
+    pub tag_uses: TagUses,
 }
 impl Assyst {
     pub async fn new() -> anyhow::Result<Assyst> {
@@ -98,6 +101,7 @@ impl Assyst {
             command_ratelimits: CommandRatelimits::new(),
             entitlements,
             component_contexts: ComponentCtxts::new(),
+            tag_uses: TagUses::new(),
         })
     }
 
@@ -0,0 +1,85 @@
+//! Runs the JavaScript read from stdin in the dash VM and prints the result to stdout.
+//!
+//! This is used by the local JavaScript backend of tags (see `js_sandbox.rs`), which runs it as a
+//! child process with a memory limit, so that a misbehaving script only ever takes down this
+//! process and not the bot. The CPU time limit in milliseconds is passed as the only argument.
+#![feature(alloc_error_hook)]
+
+use std::alloc::Layout;
+use std::io::Read;
+use std::process::ExitCode;
+
+use dash_rt::format_value;
+use dash_vm::eval::EvalError;
+use dash_vm::value::Root;
+use dash_vm::Vm;
+
+/// Exit code when an allocation fails, which is how exceeding the memory limit shows up. Must
+/// match `OUT_OF_MEMORY_EXIT_CODE` in `js_sandbox.rs`.
+const OUT_OF_MEMORY_EXIT_CODE: i32 = 3;
+
+fn out_of_memory(_: Layout) {
+    // SAFETY: _exit does not allocate and does not run any destructors, so it is fine to call
+    // when allocating failed
+    unsafe { libc::_exit(OUT_OF_MEMORY_EXIT_CODE) }
+}
+
+/// Interrupts the process with SIGPROF, which terminates it, once it used `milliseconds` of CPU
+/// time.
+fn set_cpu_time_limit(milliseconds: u64) -> std::io::Result<()> {
+    // a zero timer would disable the limit instead
+    let milliseconds = milliseconds.max(1);
+    let limit = libc::itimerval {
+        it_interval: libc::timeval { tv_sec: 0, tv_usec: 0 },
+        it_value: libc::timeval {
+            tv_sec: (milliseconds / 1000) as libc::time_t,
+            tv_usec: (milliseconds % 1000 * 1000) as libc::suseconds_t,
+        },
+    };
+
+    // SAFETY: `limit` is a valid itimerval, and the old value is not requested
+    if unsafe { libc::setitimer(libc::ITIMER_PROF, &limit, std::ptr::null_mut()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    std::alloc::set_alloc_error_hook(out_of_memory);
+
+    let Some(cpu_time_limit) = std::env::args().nth(1).and_then(|arg| arg.parse().ok()) else {
+        print!("Missing CPU time limit");
+        return ExitCode::FAILURE;
+    };
+    if let Err(e) = set_cpu_time_limit(cpu_time_limit) {
+        print!("Failed to set CPU time limit: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut code = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut code) {
+        print!("Failed to read script: {e}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut vm = Vm::new(Default::default());
+    let result = vm.eval(&code, Default::default());
+    let mut scope = vm.scope();
+
+    let (output, success) = match result {
+        Ok(result) => match format_value(result.root(&mut scope), &mut scope) {
+            Ok(f) => (f.to_string(), true),
+            Err(e) => (format!("{e:?}"), false),
+        },
+        Err(EvalError::Exception(unrooted)) => match format_value(unrooted.root(&mut scope), &mut scope) {
+            Ok(f) => (format!("Uncaught {f}"), false),
+            Err(e) => (format!("Uncaught {e:?}"), false),
+        },
+        Err(EvalError::Middle(middle)) => (format!("SyntaxError: {middle:?}"), false),
+    };
+
+    print!("{output}");
+
+    if success { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
@@ -35,6 +35,11 @@ pub trait ParseArgument: Sized {
     fn usage(name: &str) -> String {
         format!("<{name}>")
     }
+    /// The flags this argument accepts and their descriptions, which `help` lists. Implemented by
+    /// `#[derive(Flags)]`.
+    fn flag_descriptions() -> Vec<(&'static str, &'static str)> {
+        Vec::new()
+    }
 }
 
 impl ParseArgument for i64 {
@@ -176,6 +181,10 @@ impl<T: ParseArgument> ParseArgument for Option<T> {
         // this is hacky maybe
         format!("[{}]", &as_required[1..as_required.len() - 1])
     }
+
+    fn flag_descriptions() -> Vec<(&'static str, &'static str)> {
+        T::flag_descriptions()
+    }
 }
 
 impl ParseArgument for Vec<Word> {
@@ -263,7 +272,7 @@ pub struct Time {
 impl ParseArgument for Time {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        let millis = parse_to_millis(word)?;
+        let millis = parse_to_millis(&word)?;
 
         Ok(Time { millis })
     }
@@ -297,7 +306,7 @@ pub struct Word(pub String);
 
 impl ParseArgument for Word {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        Ok(Self(ctxt.next_word(label)?.to_owned()))
+        Ok(Self(ctxt.next_word(label)?.into_owned()))
     }
 
     async fn parse_command_option(
@@ -327,7 +336,7 @@ pub struct WordAutocomplete(pub String);
 
 impl ParseArgument for WordAutocomplete {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
-        Ok(Self(ctxt.next_word(label)?.to_owned()))
+        Ok(Self(ctxt.next_word(label)?.into_owned()))
     }
 
     async fn parse_command_option(
@@ -397,7 +406,7 @@ pub struct User(pub TwlUser);
 impl ParseArgument for User {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let next = ctxt.next_word(label)?;
-        let id = user_mention_to_id(next);
+        let id = user_mention_to_id(&next);
 
         let user = ctxt
             .cx
@@ -457,7 +466,7 @@ pub struct Channel(pub TwlChannel);
 impl ParseArgument for Channel {
     async fn parse_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let next = ctxt.next_word(label)?;
-        let id = channel_mention_to_id(next);
+        let id = channel_mention_to_id(&next);
 
         let channel = ctxt
             .cx
@@ -505,7 +514,9 @@ impl ParseArgument for Channel {
     }
 }
 
-/// The rest of a message as an argument. This should be the last argument if used.
+/// The rest of a message as an argument, excluding flags. This should be the last argument if
+/// used. The text is kept as it was written, unless it is a single quoted word like `"some text"`,
+/// whose quotes are removed (see [`crate::command::tokenizer::split_flags`]).
 #[derive(Debug)]
 pub struct Rest(pub String);
 
@@ -623,7 +634,7 @@ impl ImageUrl {
     async fn from_mention_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
 
-        let user_id = id_from_mention(word).ok_or(TagParseError::NoMention)?;
+        let user_id = id_from_mention(&word).ok_or(TagParseError::NoMention)?;
 
         if user_id == 0 {
             return Err(TagParseError::NoMention);
@@ -678,8 +689,8 @@ impl ImageUrl {
     ) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
 
-        if regex::URL.is_match(word) {
-            Ok(Self(word.to_owned()))
+        if regex::URL.is_match(&word) {
+            Ok(Self(word.into_owned()))
         } else {
             Err(TagParseError::NoUrl)
         }
@@ -839,7 +850,7 @@ impl ImageUrl {
 
     async fn from_emoji_raw_message(ctxt: &mut RawMessageParseCtxt<'_>, label: Label) -> Result<Self, TagParseError> {
         let word = ctxt.next_word(label)?;
-        Self::emoji(&mut ctxt.cx, word).await
+        Self::emoji(&mut ctxt.cx, &word).await
     }
 
     async fn from_emoji_command_option(
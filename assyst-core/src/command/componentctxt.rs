@@ -12,6 +12,7 @@ use twilight_model::id::marker::{GuildMarker, InteractionMarker, UserMarker};
 use twilight_model::id::Id;
 use twilight_util::builder::InteractionResponseDataBuilder;
 
+use super::misc::tag::transfer::TagTransferComponentMetadata;
 use super::misc::tag::TagPaginatorComponentMetadata;
 use crate::assyst::ThreadSafeAssyst;
 
@@ -32,8 +33,8 @@ pub struct ComponentInteractionData {
 
 /// A component context is a context in which a component interaction is handled under.\
 /// It contains basic information required to action on the button.\
-/// Because components are responded to via interactions, minimal metadata (e.g., from `CommandData`)
-/// is required.
+/// Because components are responded to via interactions, minimal metadata (e.g., from
+/// `CommandData`) is required.
 #[derive(Clone)]
 pub struct ComponentCtxt {
     pub assyst: ThreadSafeAssyst,
@@ -52,6 +53,7 @@ impl ComponentCtxt {
         // further interactions
         let res = match &mut self.data {
             ComponentMetadata::TagList(tl) => tl.component_callback(component_data).await,
+            ComponentMetadata::TagTransfer(tt) => tt.component_callback(component_data).await,
         };
 
         if let Err(e) = res {
@@ -112,6 +114,30 @@ pub async fn respond_update_text(
     Ok(())
 }
 
+/// Updates the text of the message and removes all of its components, e.g. once a confirmation was
+/// answered
+pub async fn respond_update_text_without_components(
+    assyst: ThreadSafeAssyst,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: &str,
+    text: &str,
+) -> anyhow::Result<()> {
+    let b = InteractionResponseDataBuilder::new();
+    let b = b.allowed_mentions(AllowedMentions::default());
+    let b = b.content(text);
+    let b = b.components(Vec::new());
+    let r = b.build();
+    let r = InteractionResponse {
+        kind: InteractionResponseType::UpdateMessage,
+        data: Some(r),
+    };
+    assyst
+        .interaction_client()
+        .create_response(interaction_id, interaction_token, &r)
+        .await?;
+    Ok(())
+}
+
 pub async fn respond_modal(
     assyst: ThreadSafeAssyst,
     interaction_id: Id<InteractionMarker>,
@@ -139,6 +165,7 @@ pub async fn respond_modal(
 #[derive(Clone)]
 pub enum ComponentMetadata {
     TagList(TagPaginatorComponentMetadata),
+    TagTransfer(TagTransferComponentMetadata),
 }
 
 pub fn button_emoji_new(custom_id: &str, emoji: EmojiReactionType, style: ButtonStyle) -> Button {
@@ -1,66 +1,10 @@
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
 
-use anyhow::{bail, Context};
-
-#[macro_export]
-macro_rules! int_arg_u64 {
-    ($ctxt:expr, $s:expr, $d:expr) => {{
-        let inner = $ctxt
-            .option_by_name($s)
-            .map(|o| o.value.clone())
-            .unwrap_or(twilight_model::application::interaction::application_command::CommandOptionValue::Integer($d));
-
-        let inner =
-            if let twilight_model::application::interaction::application_command::CommandOptionValue::Integer(option) =
-                inner
-            {
-                option as u64
-            } else {
-                panic!("download {} wrong arg type", $s);
-            };
-
-        inner
-    }};
-}
-
-#[macro_export]
-macro_rules! int_arg_u64_opt {
-    ($ctxt:expr, $s:expr) => {{
-        let inner = $ctxt.option_by_name($s).map(|o| o.value.clone());
-
-        let inner = if let Ok(
-            twilight_model::application::interaction::application_command::CommandOptionValue::Integer(option),
-        ) = inner
-        {
-            Some(option as u64)
-        } else {
-            None
-        };
+use anyhow::{bail, ensure, Context};
 
-        inner
-    }};
-}
-
-#[macro_export]
-macro_rules! int_arg_bool {
-    ($ctxt:expr, $s:expr, $d:expr) => {{
-        let inner = $ctxt
-            .option_by_name($s)
-            .map(|o| o.value.clone())
-            .unwrap_or(twilight_model::application::interaction::application_command::CommandOptionValue::Boolean($d));
-
-        let inner =
-            if let twilight_model::application::interaction::application_command::CommandOptionValue::Boolean(option) =
-                inner
-            {
-                option
-            } else {
-                panic!("download {} wrong arg type", $s);
-            };
-
-        inner
-    }};
-}
+use crate::command::tokenizer::RawMessageArgsIter;
 
 pub enum FlagType {
     WithValue,
@@ -69,19 +13,26 @@ pub enum FlagType {
 
 type ValidFlags = HashMap<&'static str, FlagType>;
 
+/// Decodes flags from the rest of a raw message. Usually implemented with `#[derive(Flags)]`, which
+/// also implements `ParseArgument` for the flags.
 pub trait FlagDecode {
     fn from_str(input: &str) -> anyhow::Result<Self>
     where
         Self: Sized;
 }
 
+/// Decodes the flags of a raw message. Flag values can be quoted to include whitespace, and quoted
+/// or escaped values starting with `--` are not treated as flags (see
+/// [`crate::command::tokenizer`]).
 pub fn flags_from_str(input: &str, valid_flags: ValidFlags) -> anyhow::Result<HashMap<String, Option<String>>> {
-    let args = input.split_ascii_whitespace();
+    let args = RawMessageArgsIter::new(input);
     let mut current_flag: Option<String> = None;
     let mut entries: HashMap<String, Option<String>> = HashMap::new();
 
-    for arg in args {
-        if (arg.starts_with("--") && arg.len() > 2) || (arg.starts_with("—") && arg.len() > 1) {
+    for token in args {
+        let arg = token.text.as_ref();
+
+        if token.is_flag() {
             let arglen = if arg.starts_with("--") { 2 } else { 1 };
 
             // prev flag present but no value, write to hashmap
@@ -130,3 +81,56 @@ pub fn flags_from_str(input: &str, valid_flags: ValidFlags) -> anyhow::Result<Ha
 
     Ok(entries)
 }
+
+/// Parses the value of a flag decoded by [`flags_from_str`], if it was provided.
+pub fn flag_value<T>(decoded: &HashMap<String, Option<String>>, name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    decoded
+        .get(name)
+        .and_then(Option::as_deref)
+        .map(str::parse)
+        .transpose()
+        .with_context(|| format!("Provided {name} is invalid"))
+}
+
+/// Checks that the value of a flag, if it was provided, is within the inclusive bounds.
+pub fn check_flag_range<T: PartialOrd + Display>(
+    name: &str,
+    value: Option<&T>,
+    min: Option<T>,
+    max: Option<T>,
+) -> anyhow::Result<()> {
+    let Some(value) = value else { return Ok(()) };
+
+    match (min, max) {
+        (Some(min), Some(max)) => ensure!(
+            *value >= min && *value <= max,
+            "Flag {name} must be between {min} and {max}"
+        ),
+        (Some(min), None) => ensure!(*value >= min, "Flag {name} must be at least {min}"),
+        (None, Some(max)) => ensure!(*value <= max, "Flag {name} must be at most {max}"),
+        (None, None) => {},
+    }
+
+    Ok(())
+}
+
+/// Checks that the value of a flag, if it was provided, is one of the choices.
+pub fn check_flag_choice<T: PartialEq<C>, C: Display>(
+    name: &str,
+    value: Option<&T>,
+    choices: &[C],
+) -> anyhow::Result<()> {
+    if let Some(value) = value {
+        ensure!(
+            choices.iter().any(|choice| value == choice),
+            "Flag {name} must be one of: {}",
+            choices.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    Ok(())
+}
@@ -1,22 +1,18 @@
-use std::collections::HashMap;
 use std::time::Duration;
 
 use anyhow::{bail, Context};
 use assyst_common::err;
 use assyst_database::model::colour_role::ColourRole;
-use assyst_proc_macro::command;
+use assyst_proc_macro::{command, Flags};
 use assyst_string_fmt::Markdown;
 use twilight_model::id::marker::{GuildMarker, RoleMarker};
 use twilight_model::id::Id;
-use twilight_util::builder::command::BooleanBuilder;
 
 use crate::assyst::ThreadSafeAssyst;
-use crate::command::arguments::{ParseArgument, Word, WordAutocomplete};
+use crate::command::arguments::{Word, WordAutocomplete};
 use crate::command::autocomplete::AutocompleteData;
-use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
 use crate::command::{Availability, Category, CommandCtxt};
-use crate::{define_commandgroup, int_arg_bool};
+use crate::define_commandgroup;
 
 const DEFAULT_COLOURS: &[(&str, u32)] = &[
     ("gold", 0xf1c40f),
@@ -236,10 +232,7 @@ pub async fn remove(
     cooldown = Duration::from_secs(20),
     category = Category::Fun,
     usage = "--i-am-sure",
-    examples = ["", "--i-am-sure"],
-    flag_descriptions = [
-        ("i-am-sure", "Confirm this operation"),
-    ]
+    examples = ["", "--i-am-sure"]
 )]
 pub async fn remove_all(ctxt: CommandCtxt<'_>, flags: ColourRemoveAllFlags) -> anyhow::Result<()> {
     if let Some(id) = ctxt.data.guild_id.map(twilight_model::id::Id::get) {
@@ -417,50 +410,8 @@ define_commandgroup! {
     default: default
 }
 
-#[derive(Default)]
+#[derive(Default, Flags)]
 pub struct ColourRemoveAllFlags {
+    /// confirm you are sure
     pub i_am_sure: bool,
 }
-impl FlagDecode for ColourRemoveAllFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("i-am-sure", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
-        let result = Self {
-            i_am_sure: raw_decode.contains_key("i-am-sure"),
-        };
-
-        Ok(result)
-    }
-}
-impl ParseArgument for ColourRemoveAllFlags {
-    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
-        vec![
-            BooleanBuilder::new("i-am-sure", "confirm you are sure")
-                .required(false)
-                .build(),
-        ]
-    }
-
-    async fn parse_raw_message(
-        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
-        label: crate::command::Label,
-    ) -> Result<Self, crate::command::errors::TagParseError> {
-        let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
-        Ok(parsed)
-    }
-
-    async fn parse_command_option(
-        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
-        _: crate::command::Label,
-    ) -> Result<Self, TagParseError> {
-        let sure = int_arg_bool!(ctxt, "i-am-sure", false);
-
-        Ok(Self { i_am_sure: sure })
-    }
-}
@@ -1,89 +1,25 @@
-use std::collections::HashMap;
 use std::time::Duration;
 
 use anyhow::{bail, Context};
 use assyst_common::util::{normalize_emojis, normalize_mentions, table};
-use assyst_proc_macro::command;
+use assyst_proc_macro::{command, Flags};
 use assyst_string_fmt::Markdown;
-use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder};
 
-use crate::command::arguments::{ParseArgument, Rest, Word};
-use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::arguments::{Rest, Word};
 use crate::command::{Availability, Category, CommandCtxt};
 use crate::rest::bad_translation::{
     bad_translate as bad_translate_default, bad_translate_with_count, get_languages, translate_single, TranslateResult,
     Translation,
 };
-use crate::{int_arg_bool, int_arg_u64_opt};
 
-#[derive(Default)]
+#[derive(Default, Flags)]
 pub struct BadTranslateFlags {
+    /// show language chain
     pub chain: bool,
+    /// amount of translations
+    #[flag(min = 1, max = 10)]
     pub count: Option<u64>,
 }
-impl FlagDecode for BadTranslateFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("chain", FlagType::NoValue);
-        valid_flags.insert("count", FlagType::WithValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
-
-        let count = raw_decode
-            .get("count")
-            .and_then(|x| x.clone().map(|y| y.parse::<u64>()));
-
-        let count = if let Some(inner) = count {
-            Some(inner.context("Failed to parse translation count")?)
-        } else {
-            None
-        };
-
-        let result = Self {
-            chain: raw_decode.contains_key("chain"),
-            count,
-        };
-
-        Ok(result)
-    }
-}
-impl ParseArgument for BadTranslateFlags {
-    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
-        vec![
-            IntegerBuilder::new("count", "amount of translations")
-                .required(false)
-                .min_value(1)
-                .max_value(10)
-                .build(),
-            BooleanBuilder::new("chain", "show language chain")
-                .required(false)
-                .build(),
-        ]
-    }
-
-    async fn parse_raw_message(
-        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
-        label: crate::command::Label,
-    ) -> Result<Self, crate::command::errors::TagParseError> {
-        let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
-        Ok(parsed)
-    }
-
-    async fn parse_command_option(
-        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
-        _: crate::command::Label,
-    ) -> Result<Self, TagParseError> {
-        let count = int_arg_u64_opt!(ctxt, "count");
-        let chain = int_arg_bool!(ctxt, "chain", false);
-
-        Ok(Self { chain, count })
-    }
-}
 
 #[command(
     name = "badtranslate",
@@ -94,10 +30,6 @@ impl ParseArgument for BadTranslateFlags {
     category = Category::Fun,
     usage = "[text|\"languages\"]",
     examples = ["hello i love assyst", "languages"],
-    flag_descriptions = [
-        ("chain", "Show language chain"),
-        ("count", "Set the amount of translations to perform")
-    ],
     send_processing = true,
     context_menu_message_command = "Bad Translate"
 )]
@@ -241,8 +241,8 @@ pub async fn execute_subcommand_raw_message(
         .next_word(None)
         .map_err(|_| ExecutionError::Parse(TagParseError::SubcommandArgsExhausted("unknown".to_owned())))?;
 
-    let command = find_subcommand(subcommand, commands).ok_or(ExecutionError::Parse(
-        TagParseError::InvalidSubcommand(subcommand.to_owned()),
+    let command = find_subcommand(&subcommand, commands).ok_or(ExecutionError::Parse(
+        TagParseError::InvalidSubcommand(subcommand.into_owned()),
     ))?;
 
     command.execute_raw_message(ctxt).await
@@ -181,10 +181,11 @@ pub async fn frames(ctxt: CommandCtxt<'_>, source: Image) -> anyhow::Result<()>
 
     let response: MessageBuilder = MessageBuilder {
         content: None,
-        attachment: Some(Attachment {
+        attachments: vec![Attachment {
             name: "frames.zip".to_owned().into_boxed_str(),
             data: result,
-        }),
+        }],
+        embeds: Vec::new(),
         component_ctxt: None,
         components: None,
     };
@@ -1,5 +1,5 @@
 use assyst_common::util::filetype::{get_sig, Type};
-use twilight_model::channel::message::Component;
+use twilight_model::channel::message::{Component, Embed};
 
 use super::arguments::Image;
 use super::componentctxt::ComponentCtxtRegister;
@@ -22,7 +22,8 @@ impl From<Image> for Attachment {
 
 pub struct MessageBuilder {
     pub content: Option<String>,
-    pub attachment: Option<Attachment>,
+    pub attachments: Vec<Attachment>,
+    pub embeds: Vec<Embed>,
     pub components: Option<Vec<Component>>,
     pub component_ctxt: Option<ComponentCtxtRegister>,
 }
@@ -31,7 +32,8 @@ impl From<&str> for MessageBuilder {
     fn from(value: &str) -> Self {
         Self {
             content: Some(value.into()),
-            attachment: None,
+            attachments: Vec::new(),
+            embeds: Vec::new(),
             components: None,
             component_ctxt: None,
         }
@@ -41,7 +43,8 @@ impl From<String> for MessageBuilder {
     fn from(value: String) -> Self {
         Self {
             content: Some(value),
-            attachment: None,
+            attachments: Vec::new(),
+            embeds: Vec::new(),
             components: None,
             component_ctxt: None,
         }
@@ -52,7 +55,8 @@ impl From<Attachment> for MessageBuilder {
     fn from(value: Attachment) -> Self {
         Self {
             content: None,
-            attachment: Some(value),
+            attachments: vec![value],
+            embeds: Vec::new(),
             components: None,
             component_ctxt: None,
         }
@@ -63,7 +67,8 @@ impl From<(Attachment, String)> for MessageBuilder {
     fn from(value: (Attachment, String)) -> Self {
         Self {
             content: Some(value.1),
-            attachment: Some(value.0),
+            attachments: vec![value.0],
+            embeds: Vec::new(),
             components: None,
             component_ctxt: None,
         }
@@ -74,7 +79,8 @@ impl From<Image> for MessageBuilder {
     fn from(value: Image) -> Self {
         Self {
             content: None,
-            attachment: Some(value.into()),
+            attachments: vec![value.into()],
+            embeds: Vec::new(),
             components: None,
             component_ctxt: None,
         }
@@ -83,7 +89,8 @@ impl From<Image> for MessageBuilder {
 impl From<(Image, &str)> for MessageBuilder {
     fn from((image, text): (Image, &str)) -> Self {
         Self {
-            attachment: Some(image.into()),
+            attachments: vec![image.into()],
+            embeds: Vec::new(),
             content: Some(text.into()),
             components: None,
             component_ctxt: None,
@@ -93,7 +100,8 @@ impl From<(Image, &str)> for MessageBuilder {
 impl From<Vec<u8>> for MessageBuilder {
     fn from(value: Vec<u8>) -> Self {
         Self {
-            attachment: Some(Image(value).into()),
+            attachments: vec![Image(value).into()],
+            embeds: Vec::new(),
             content: None,
             components: None,
             component_ctxt: None,
@@ -103,7 +111,8 @@ impl From<Vec<u8>> for MessageBuilder {
 impl From<(Vec<u8>, &str)> for MessageBuilder {
     fn from((value, text): (Vec<u8>, &str)) -> Self {
         Self {
-            attachment: Some(Image(value).into()),
+            attachments: vec![Image(value).into()],
+            embeds: Vec::new(),
             content: Some(text.into()),
             components: None,
             component_ctxt: None,
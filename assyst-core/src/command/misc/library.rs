@@ -0,0 +1,395 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_common::util::discord::format_discord_timestamp;
+use assyst_common::util::unix_timestamp;
+use assyst_database::model::published_tag::PublishedTag;
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_editor::TagEditor;
+use assyst_database::model::tag_install::TagInstall;
+use assyst_database::model::tag_revision::TagRevisionKind;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+
+use crate::command::arguments::{RestNoFlags, Word, WordAutocomplete};
+use crate::command::misc::tag::{ensure_not_alias, ensure_valid_tag_name, user_revision};
+use crate::command::{Availability, Category, CommandCtxt};
+use crate::define_commandgroup;
+
+const DEFAULT_LIST_COUNT: i64 = 10;
+const MAX_DESCRIPTION_LENGTH: usize = 200;
+/// Maximum length of the content shown by `library info`
+const MAX_PREVIEW_LENGTH: usize = 1000;
+
+/// Library tag ids are stored as `i32`, so larger ids can not exist
+fn library_tag_id(id: u64) -> anyhow::Result<i32> {
+    i32::try_from(id).ok().context("That library tag does not exist.")
+}
+
+fn format_published_tags(tags: &[PublishedTag]) -> String {
+    tags.iter()
+        .map(|tag| {
+            format!(
+                "{} **{}** - {} (by <@{}>, version {}, {} installs)\n",
+                tag.id.to_string().codestring(),
+                tag.name,
+                tag.description,
+                tag.author,
+                tag.version,
+                tag.installs
+            )
+        })
+        .collect()
+}
+
+#[command(
+    description = "publish a tag that you own to the tag library, or update the published version",
+    cooldown = Duration::from_secs(10),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[tag name] [description]",
+    examples = ["weather shows the weather of a city"],
+    guild_only = true
+)]
+pub async fn publish(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+    description: RestNoFlags,
+) -> anyhow::Result<()> {
+    let author = ctxt.data.author.id.get();
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be published from guilds.")
+    };
+    let description = description.0.trim();
+    ensure!(!description.is_empty(), "Published tags need a description.");
+    ensure!(
+        description.len() <= MAX_DESCRIPTION_LENGTH,
+        "Descriptions cannot be longer than {MAX_DESCRIPTION_LENGTH} characters."
+    );
+
+    let tag = Tag::get(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        &name.0.to_ascii_lowercase(),
+    )
+    .await
+    .context("Failed to fetch tag")?
+    .context("Tag not found in this server.")?;
+    ensure!(tag.author == author as i64, "You can only publish tags that you own.");
+
+    let (id, version) = PublishedTag {
+        id: 0,
+        name: tag.name.clone(),
+        description: description.to_owned(),
+        data: tag.data,
+        author: author as i64,
+        source_guild_id: guild_id.get() as i64,
+        source_tag_name: tag.name.clone(),
+        version: 0,
+        installs: 0,
+        created_at: unix_timestamp() as i64,
+        updated_at: unix_timestamp() as i64,
+    }
+    .publish(&ctxt.assyst().database_handler)
+    .await
+    .context("Failed to publish tag")?
+    .context(
+        "A tag with this name was already published from this server by someone else; they must unpublish it first.",
+    )?;
+
+    ctxt.reply(format!(
+        "Published tag {} as library tag {} (version {version}). Other servers can install it with `{}library install {id}`.",
+        tag.name.codestring(),
+        id.to_string().codestring(),
+        ctxt.data.calling_prefix
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "remove a tag that you published from the tag library (installed copies are kept)",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[library tag id]",
+    examples = ["12"]
+)]
+pub async fn unpublish(ctxt: CommandCtxt<'_>, id: u64) -> anyhow::Result<()> {
+    ensure!(
+        PublishedTag::unpublish(
+            &ctxt.assyst().database_handler,
+            library_tag_id(id)?,
+            ctxt.data.author.id.get() as i64
+        )
+        .await
+        .context("Failed to unpublish tag")?,
+        "Failed to unpublish that tag. Does it exist, and did you publish it?"
+    );
+
+    ctxt.reply(format!(
+        "Removed library tag {} from the tag library.",
+        id.to_string().codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "search the tag library by name and description",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[query]",
+    examples = ["weather"]
+)]
+pub async fn search(ctxt: CommandCtxt<'_>, query: RestNoFlags) -> anyhow::Result<()> {
+    let handler = &ctxt.assyst().database_handler;
+    let query = query.0.trim();
+
+    let count = PublishedTag::get_search_count(handler, query)
+        .await
+        .context("Failed to search the tag library")?;
+    ensure!(count > 0, "No published tags match your search.");
+
+    let tags = PublishedTag::search(handler, query, 0, DEFAULT_LIST_COUNT)
+        .await
+        .context("Failed to search the tag library")?;
+
+    ctxt.reply(format!(
+        "📚 **Tag library results for {}**\n\n{}\nShowing {} of {count} results. Use `{}library info <id>` to view a tag.",
+        query.codestring(),
+        format_published_tags(&tags),
+        tags.len(),
+        ctxt.data.calling_prefix
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "browse the most installed tags in the tag library",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "<page>",
+    examples = ["", "2"]
+)]
+pub async fn browse(ctxt: CommandCtxt<'_>, page: Option<u64>) -> anyhow::Result<()> {
+    let handler = &ctxt.assyst().database_handler;
+    let page = page.unwrap_or(1).max(1);
+
+    let count = PublishedTag::get_search_count(handler, "")
+        .await
+        .context("Failed to fetch the tag library")?;
+    ensure!(count > 0, "No tags have been published yet.");
+    let pages = (count as f64 / DEFAULT_LIST_COUNT as f64).ceil() as u64;
+    ensure!(page <= pages, "The tag library only has {pages} pages.");
+
+    let tags = PublishedTag::search(handler, "", (page as i64 - 1) * DEFAULT_LIST_COUNT, DEFAULT_LIST_COUNT)
+        .await
+        .context("Failed to fetch the tag library")?;
+
+    ctxt.reply(format!(
+        "📚 **Tag library**\n\n{}\nPage {page}/{pages} ({count} published tags). Use `{}library info <id>` to view a tag.",
+        format_published_tags(&tags),
+        ctxt.data.calling_prefix
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "show information about a tag in the tag library",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[library tag id]",
+    examples = ["12"]
+)]
+pub async fn info(ctxt: CommandCtxt<'_>, id: u64) -> anyhow::Result<()> {
+    let tag = PublishedTag::get(&ctxt.assyst().database_handler, library_tag_id(id)?)
+        .await
+        .context("Failed to fetch library tag")?
+        .context("That library tag does not exist.")?;
+
+    let preview = if tag.data.chars().count() > MAX_PREVIEW_LENGTH {
+        format!("{}...", tag.data.chars().take(MAX_PREVIEW_LENGTH).collect::<String>())
+    } else {
+        tag.data.clone()
+    };
+
+    ctxt.reply(format!(
+        "📚 **Library tag {}: **{}\n\n{}\n\nAuthor: <@{}>\nVersion: {}\nInstalls: {}\nPublished: {}\nUpdated: {}\n{}",
+        tag.id,
+        tag.name,
+        tag.description,
+        tag.author,
+        tag.version,
+        tag.installs,
+        format_discord_timestamp(tag.created_at as u64),
+        format_discord_timestamp(tag.updated_at as u64),
+        preview.codeblock("")
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "install a tag from the tag library in this server, optionally under another name",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[library tag id] <name>",
+    examples = ["12", "12 forecast"],
+    guild_only = true
+)]
+pub async fn install(ctxt: CommandCtxt<'_>, id: u64, name: Option<Word>) -> anyhow::Result<()> {
+    let author = ctxt.data.author.id.get();
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be installed in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    let published = PublishedTag::get(handler, library_tag_id(id)?)
+        .await
+        .context("Failed to fetch library tag")?
+        .context("That library tag does not exist.")?;
+
+    let name = name.map_or_else(|| published.name.clone(), |name| name.0.to_ascii_lowercase());
+    ensure_valid_tag_name(&name)?;
+    ensure_not_alias(&ctxt, guild_id.get(), &name).await?;
+
+    let tag = Tag {
+        name: name.clone(),
+        guild_id: guild_id.get() as i64,
+        data: published.data.clone(),
+        author: author as i64,
+        created_at: unix_timestamp() as i64,
+    };
+    let success = TagInstall {
+        guild_id: guild_id.get() as i64,
+        tag_name: name.clone(),
+        published_id: published.id,
+        version: published.version,
+    }
+    .install(
+        handler,
+        &tag,
+        &user_revision(&ctxt, guild_id.get(), &name, &published.data, TagRevisionKind::Create),
+    )
+    .await
+    .context("Failed to install tag")?;
+    ensure!(
+        success,
+        "That tag name is already used in this server. Install the tag under another name with `{}library install {id} <name>`.",
+        ctxt.data.calling_prefix
+    );
+
+    ctxt.reply(format!(
+        "Installed library tag {} (version {}) as tag {}.",
+        published.id.to_string().codestring(),
+        published.version,
+        name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "update an installed tag to the latest published version, replacing any local changes",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[tag name]",
+    examples = ["weather"],
+    guild_only = true
+)]
+pub async fn update(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+) -> anyhow::Result<()> {
+    let author = ctxt.data.author.id.get();
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be updated in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    let tag = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    ensure!(
+        tag.author == author as i64
+            || TagEditor::is_editor(handler, guild_id.get() as i64, &tag.name, author as i64)
+                .await
+                .context("Failed to fetch tag editors")?,
+        "You can only update tags that you own or are an editor of."
+    );
+
+    let install = TagInstall::get(handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag installation")?
+        .context("This tag was not installed from the tag library.")?;
+
+    let published = PublishedTag::get(handler, install.published_id)
+        .await
+        .context("Failed to fetch library tag")?
+        .context("The library tag this tag was installed from no longer exists.")?;
+
+    if published.version <= install.version {
+        ctxt.reply(format!(
+            "Tag {} is already up to date (version {}).",
+            tag.name.codestring(),
+            install.version
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let revision = user_revision(&ctxt, guild_id.get(), &tag.name, &published.data, TagRevisionKind::Edit);
+    let success = TagInstall {
+        version: published.version,
+        ..install.clone()
+    }
+    .update(handler, tag.author, &revision)
+    .await
+    .context("Failed to update tag")?;
+    ensure!(success, "Failed to update that tag. Does it still exist?");
+
+    ctxt.reply(format!(
+        "Updated tag {} from version {} to version {}.",
+        tag.name.codestring(),
+        install.version,
+        published.version
+    ))
+    .await?;
+
+    Ok(())
+}
+
+define_commandgroup! {
+    name: library,
+    access: Availability::Public,
+    category: Category::Misc,
+    aliases: ["taglibrary", "lib"],
+    cooldown: Duration::from_secs(2),
+    description: "share tags between servers through the global tag library",
+    usage: "[subcommand] <arguments...>",
+    commands: [
+        "publish" => publish,
+        "unpublish" => unpublish,
+        "search" => search,
+        "browse" => browse,
+        "info" => info,
+        "install" => install,
+        "update" => update
+    ]
+}
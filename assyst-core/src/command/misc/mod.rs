@@ -23,11 +23,13 @@ use crate::rest::patreon::PatronTier;
 
 pub mod btchannel;
 pub mod help;
+pub mod library;
 pub mod prefix;
 pub mod remind;
 pub mod run;
 pub mod stats;
 pub mod tag;
+pub mod trigger;
 
 #[command(
     description = "enlarges an image", 
@@ -1,9 +1,8 @@
-use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, bail, Context};
+use anyhow::{bail, Context};
 use assyst_common::util::process::{exec_sync, exec_sync_in_dir, CommandOutput};
-use assyst_proc_macro::command;
+use assyst_proc_macro::{command, Flags};
 use assyst_string_fmt::Markdown;
 use dash_rt::format_value;
 use dash_vm::eval::EvalError;
@@ -12,16 +11,13 @@ use dash_vm::Vm;
 use serde::Deserialize;
 use tokio::fs;
 use toml::from_str;
-use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder};
 
-use crate::command::arguments::{Codeblock, ParseArgument};
-use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::arguments::Codeblock;
 use crate::command::messagebuilder::{Attachment, MessageBuilder};
 use crate::command::{Availability, Category, CommandCtxt};
+use crate::define_commandgroup;
 use crate::downloader::download_content;
 use crate::rest::rust::{run_benchmark, run_binary, run_clippy, run_godbolt, run_miri, OptimizationLevel};
-use crate::{define_commandgroup, int_arg_bool, int_arg_u64};
 
 struct ExecutableDeletionDefer(String);
 impl Drop for ExecutableDeletionDefer {
@@ -30,84 +26,26 @@ impl Drop for ExecutableDeletionDefer {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Flags)]
+#[flags(validate = "ChargeFlags::validate")]
 pub struct ChargeFlags {
+    /// increase verbosity
     pub verbose: bool,
+    /// output LLVM IR
     pub llir: bool,
+    /// optimisation level of LLVM
+    #[flag(choices = [0, 1, 2, 3])]
     pub opt: u64,
+    /// run valgrind on compiled executable
     pub valgrind: bool,
 }
-impl FlagDecode for ChargeFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("verbose", FlagType::NoValue);
-        valid_flags.insert("llir", FlagType::NoValue);
-        valid_flags.insert("opt", FlagType::WithValue);
-        valid_flags.insert("valgrind", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
-        let opt = raw_decode
-            .get("opt")
-            .and_then(|x| x.as_deref())
-            .map_or(Ok(0), str::parse)
-            .context("Failed to parse optimisation level")?;
-
-        let result = Self {
-            verbose: raw_decode.contains_key("verbose"),
-            llir: raw_decode.contains_key("llir"),
-            opt,
-            valgrind: raw_decode.contains_key("valgrind"),
-        };
-
-        if result.llir && result.valgrind {
+impl ChargeFlags {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.llir && self.valgrind {
             bail!("Cannot set both valgrind and llir flags at the same time");
         }
 
-        Ok(result)
-    }
-}
-impl ParseArgument for ChargeFlags {
-    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
-        vec![
-            IntegerBuilder::new("opt", "optimisation level").required(false).build(),
-            BooleanBuilder::new("verbose", "increase verbosity")
-                .required(false)
-                .build(),
-            BooleanBuilder::new("llir", "enable llir").required(false).build(),
-            BooleanBuilder::new("valgrind", "run valgrind on compiled executable")
-                .required(false)
-                .build(),
-        ]
-    }
-
-    async fn parse_raw_message(
-        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
-        label: crate::command::Label,
-    ) -> Result<Self, crate::command::errors::TagParseError> {
-        let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
-        Ok(parsed)
-    }
-
-    async fn parse_command_option(
-        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
-        _: crate::command::Label,
-    ) -> Result<Self, TagParseError> {
-        let opt = int_arg_u64!(ctxt, "opt", 0);
-        let llir = int_arg_bool!(ctxt, "llir", false);
-        let verbose = int_arg_bool!(ctxt, "verbose", false);
-        let valgrind = int_arg_bool!(ctxt, "valgrind", false);
-
-        if llir && valgrind {
-            return Err(TagParseError::FlagParseError(anyhow!(
-                "Cannot set both valgrind and llir flags at the same time"
-            )));
-        }
-
-        Ok(Self { verbose, llir, opt, valgrind })
+        Ok(())
     }
 }
 
@@ -118,13 +56,7 @@ impl ParseArgument for ChargeFlags {
     category = Category::Misc,
     usage = "[script] <flags>",
     examples = ["fn main(): i32 { return 1; }"],
-    send_processing = true,
-    flag_descriptions = [
-        ("verbose", "Get verbose output"),
-        ("llir", "Output LLVM IR"),
-        ("opt [level:0|1|2|3]", "Set optimisation level of LLVM"),
-        ("valgrind", "Run output executable in valgrind")
-    ]
+    send_processing = true
 )]
 pub async fn charge(ctxt: CommandCtxt<'_>, script: Codeblock, flags: ChargeFlags) -> anyhow::Result<()> {
     let dir = "/tmp/charge".to_owned();
@@ -210,10 +142,11 @@ pub async fn charge(ctxt: CommandCtxt<'_>, script: Codeblock, flags: ChargeFlags
         } else {
             ctxt.reply(MessageBuilder {
                 content: None,
-                attachment: Some(Attachment {
+                attachments: vec![Attachment {
                     name: "out.txt".into(),
                     data: stdout.as_bytes().to_vec(),
-                }),
+                }],
+                embeds: Vec::new(),
                 components: None,
                 component_ctxt: None,
             })
@@ -224,75 +157,19 @@ pub async fn charge(ctxt: CommandCtxt<'_>, script: Codeblock, flags: ChargeFlags
     Ok(())
 }
 
-#[derive(Default)]
+#[derive(Default, Flags)]
 pub struct RustFlags {
+    /// use miri debugger
     pub miri: bool,
+    /// output asm
     pub asm: bool,
+    /// check code with clippy
     pub clippy: bool,
+    /// benchmark code
     pub bench: bool,
+    /// compile in release mode
     pub release: bool,
 }
-impl FlagDecode for RustFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("miri", FlagType::NoValue);
-        valid_flags.insert("release", FlagType::NoValue);
-        valid_flags.insert("asm", FlagType::NoValue);
-        valid_flags.insert("clippy", FlagType::NoValue);
-        valid_flags.insert("bench", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
-        let result = Self {
-            miri: raw_decode.contains_key("miri"),
-            asm: raw_decode.contains_key("asm"),
-            release: raw_decode.contains_key("release"),
-            clippy: raw_decode.contains_key("clippy"),
-            bench: raw_decode.contains_key("bench"),
-        };
-
-        Ok(result)
-    }
-}
-impl ParseArgument for RustFlags {
-    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
-        vec![
-            BooleanBuilder::new("miri", "use miri debugger").required(false).build(),
-            BooleanBuilder::new("asm", "output asm").required(false).build(),
-            BooleanBuilder::new("release", "compile in release mode")
-                .required(false)
-                .build(),
-            BooleanBuilder::new("clippy", "check code with clippy")
-                .required(false)
-                .build(),
-            BooleanBuilder::new("bench", "benchmark code").required(false).build(),
-        ]
-    }
-
-    async fn parse_raw_message(
-        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
-        label: crate::command::Label,
-    ) -> Result<Self, crate::command::errors::TagParseError> {
-        let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
-        Ok(parsed)
-    }
-
-    async fn parse_command_option(
-        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
-        _: crate::command::Label,
-    ) -> Result<Self, TagParseError> {
-        let miri = int_arg_bool!(ctxt, "miri", false);
-        let asm = int_arg_bool!(ctxt, "asm", false);
-        let release = int_arg_bool!(ctxt, "release", false);
-        let clippy = int_arg_bool!(ctxt, "clippy", false);
-        let bench = int_arg_bool!(ctxt, "bench", false);
-
-        Ok(Self { miri, asm, clippy, bench, release })
-    }
-}
 
 #[command(
     description = "execute some rust",
@@ -301,14 +178,7 @@ impl ParseArgument for RustFlags {
     category = Category::Misc,
     usage = "[script] <flags>",
     examples = ["println!(\"Hello, world!\")"],
-    send_processing = true,
-    flag_descriptions = [
-        ("miri", "Run code in miri debugger"),
-        ("asm", "Output ASM of Rust code"),
-        ("clippy", "Lint code using Clippy"),
-        ("bench", "Run code as a benchmark"),
-        ("release", "Run code in release mode")
-    ]
+    send_processing = true
 )]
 pub async fn rust(ctxt: CommandCtxt<'_>, script: Codeblock, flags: RustFlags) -> anyhow::Result<()> {
     let opt = if flags.release {
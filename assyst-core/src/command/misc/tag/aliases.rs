@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_alias::TagAlias;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+
+use super::{can_manage_tag, ensure_valid_tag_name};
+use crate::command::arguments::{Word, WordAutocomplete};
+use crate::command::{Availability, Category, CommandCtxt};
+
+const MAX_ALIASES_PER_TAG: usize = 10;
+
+#[command(
+    description = "add another name to a tag that you own, which can be used anywhere the tag name can",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name] [alias]",
+    examples = ["test testing"],
+    guild_only = true
+)]
+pub async fn alias(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+    alias: Word,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag aliases can only be created in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    let alias = alias.0.to_ascii_lowercase();
+
+    ensure_valid_tag_name(&alias)?;
+
+    let tag = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    ensure!(
+        can_manage_tag(&ctxt, &tag).await?,
+        "You can only add aliases to tags that you own."
+    );
+    ensure!(
+        Tag::get(handler, guild_id.get() as i64, &alias)
+            .await
+            .context("Failed to fetch tag")?
+            .is_none(),
+        "That name is already used by a tag in this server."
+    );
+
+    let aliases = TagAlias::list_for_tag(handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag aliases")?;
+    ensure!(
+        aliases.len() < MAX_ALIASES_PER_TAG,
+        "Tags cannot have more than {MAX_ALIASES_PER_TAG} aliases."
+    );
+
+    let success = TagAlias {
+        guild_id: guild_id.get() as i64,
+        alias: alias.clone(),
+        tag_name: tag.name.clone(),
+    }
+    .set(handler)
+    .await
+    .context("Failed to create tag alias")?;
+    ensure!(success, "That alias is already used in this server.");
+
+    ctxt.reply(format!(
+        "{} is now an alias of tag {}.",
+        alias.codestring(),
+        tag.name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "remove an alias of a tag that you own (server managers can remove any alias in the server)",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[alias]",
+    examples = ["testing"],
+    guild_only = true
+)]
+pub async fn unalias(ctxt: CommandCtxt<'_>, alias: Word) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag aliases can only be removed in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    let alias = alias.0.to_ascii_lowercase();
+
+    TagAlias::get(handler, guild_id.get() as i64, &alias)
+        .await
+        .context("Failed to fetch tag alias")?
+        .context("That alias does not exist in this server.")?;
+
+    // aliases always point to an existing tag, as they are deleted together with their tag
+    let tag = Tag::get(handler, guild_id.get() as i64, &alias)
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    ensure!(
+        can_manage_tag(&ctxt, &tag).await?,
+        "You can only remove aliases of tags that you own."
+    );
+
+    TagAlias::delete(handler, guild_id.get() as i64, &alias)
+        .await
+        .context("Failed to delete tag alias")?;
+
+    ctxt.reply(format!(
+        "{} is no longer an alias of tag {}.",
+        alias.codestring(),
+        tag.name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
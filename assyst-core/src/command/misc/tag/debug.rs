@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use assyst_database::model::tag::Tag;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+use assyst_tag::parser::ParseMode;
+use assyst_tag::resumable::ResumableParse;
+use assyst_tag::ParseResult;
+
+use super::{drive_tag, TagContext};
+use crate::command::arguments::{Word, WordAutocomplete};
+use crate::command::messagebuilder::Attachment;
+use crate::command::{Availability, Category, CommandCtxt};
+
+/// Traces longer than this are sent as a file by `tag debug`
+const MAX_INLINE_TRACE_LENGTH: usize = 1900;
+
+#[command(
+    description = "run a tag and show every subtag it executed",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[tag name] <arguments...>",
+    examples = ["test", "whatever"],
+    send_processing = true,
+    guild_only = true
+)]
+pub async fn debug(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] tag_name: WordAutocomplete,
+    arguments: Option<Vec<Word>>,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be used in guilds.")
+    };
+    let arguments = arguments.unwrap_or_default();
+
+    let tag = Tag::get(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        &tag_name.0.to_ascii_lowercase(),
+    )
+    .await
+    .context("Failed to fetch tag")?
+    .context("Tag not found in this server.")?;
+
+    let tcx = TagContext::new(&ctxt, tag.name.clone()).await?;
+    let arguments = arguments.into_iter().map(|Word(word)| word).collect();
+    let parse = ResumableParse::new(tag.data.clone(), arguments, ParseMode::StopOnError, tcx.time_budget).traced();
+    let (res, parse) = drive_tag(parse, &tcx).await;
+    let trace = parse.into_trace();
+
+    let result = match res {
+        Ok(ParseResult { output, .. }) => format!("output: {output:?}"),
+        Err(err) => assyst_tag::errors::format_error(&tag.data, err),
+    };
+
+    let inline = format!("{}\n{result}", trace.render(true));
+    if inline.len() <= MAX_INLINE_TRACE_LENGTH {
+        ctxt.reply(inline.codeblock("ansi")).await?;
+    } else {
+        // too long to show in a message, and files cannot be colored
+        ctxt.reply((
+            Attachment {
+                name: format!("trace-{}.txt", tag.name).into_boxed_str(),
+                data: trace.render(false).into_bytes(),
+            },
+            result.codeblock("ansi"),
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::bail;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+use assyst_tag::registry::{self, SubtagInfo};
+
+use crate::assyst::ThreadSafeAssyst;
+use crate::command::arguments::WordAutocomplete;
+use crate::command::autocomplete::AutocompleteData;
+use crate::command::{Availability, Category, CommandCtxt};
+
+/// Maximum number of subtags shown when `tag docs` matches several subtags
+const MAX_DISPLAYED_SUBTAG_MATCHES: usize = 15;
+
+pub async fn subtag_autocomplete(_: ThreadSafeAssyst, _: AutocompleteData) -> Vec<String> {
+    registry::SUBTAGS
+        .iter()
+        .flat_map(|subtag| std::iter::once(subtag.name).chain(subtag.aliases.iter().copied()))
+        .map(str::to_owned)
+        .collect()
+}
+
+fn format_subtag_docs(subtag: &SubtagInfo) -> String {
+    let mut message = format!("{}\n{}", subtag.signature.codestring(), subtag.description);
+    if !subtag.aliases.is_empty() {
+        message += &format!(
+            "\nAliases: {}",
+            subtag
+                .aliases
+                .iter()
+                .map(|alias| alias.codestring())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    message
+}
+
+#[command(
+    description = "show the documentation of a subtag, or list every subtag",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "<subtag>",
+    examples = ["", "if", "embed"],
+    guild_only = true
+)]
+pub async fn docs(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::docs::subtag_autocomplete"] subtag: Option<WordAutocomplete>,
+) -> anyhow::Result<()> {
+    let Some(WordAutocomplete(query)) = subtag else {
+        let names = registry::SUBTAGS
+            .iter()
+            .map(|subtag| subtag.name.codestring())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        ctxt.reply(format!(
+            "🗒️ **Subtags**\n{names}\n\nUse {} for the documentation of a subtag.",
+            format!("{}tag docs [subtag]", ctxt.data.calling_prefix).codestring()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let query = query.trim_matches(|c| c == '{' || c == '}').to_ascii_lowercase();
+    if let Some(subtag) = registry::get(&query) {
+        ctxt.reply(format_subtag_docs(subtag)).await?;
+        return Ok(());
+    }
+
+    let matches = registry::search(&query);
+    match &matches[..] {
+        [] => bail!("No subtag matches {}.", query.codestring()),
+        [subtag] => ctxt.reply(format_subtag_docs(subtag)).await?,
+        _ => {
+            let mut message = format!("🗒️ **Subtags matching {}**\n\n", query.codestring());
+            for subtag in matches.iter().take(MAX_DISPLAYED_SUBTAG_MATCHES) {
+                writeln!(message, "{}: {}", subtag.signature.codestring(), subtag.description)?;
+            }
+            if matches.len() > MAX_DISPLAYED_SUBTAG_MATCHES {
+                writeln!(message, "...and {} more", matches.len() - MAX_DISPLAYED_SUBTAG_MATCHES)?;
+            }
+
+            ctxt.reply(message).await?;
+        },
+    }
+
+    Ok(())
+}
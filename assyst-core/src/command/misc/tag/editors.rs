@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_editor::TagEditor;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+
+use super::can_manage_tag;
+use crate::command::arguments::{User, WordAutocomplete};
+use crate::command::{Availability, Category, CommandCtxt};
+
+const MAX_EDITORS_PER_TAG: usize = 10;
+
+#[command(
+    description = "list the users who can edit a tag besides its owner",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name]",
+    examples = ["test"],
+    guild_only = true
+)]
+pub async fn editors(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] name: WordAutocomplete,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag editors can only be listed in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    let tag = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    let editors = TagEditor::list_for_tag(handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag editors")?;
+
+    if editors.is_empty() {
+        ctxt.reply(format!(
+            "Tag {} can only be edited by its owner, <@{}>.",
+            tag.name.codestring(),
+            tag.author
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctxt.reply(format!(
+        "Tag {} can be edited by its owner, <@{}>, and by {}.",
+        tag.name.codestring(),
+        tag.author,
+        editors
+            .iter()
+            .map(|editor| format!("<@{}>", editor.user_id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "allow another user to edit a tag that you own",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name] [user]",
+    examples = ["test @jacher"],
+    guild_only = true
+)]
+pub async fn addeditor(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+    user: User,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag editors can only be added in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    let tag = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    ensure!(
+        can_manage_tag(&ctxt, &tag).await?,
+        "You can only add editors to tags that you own."
+    );
+    ensure!(!user.0.bot, "Bots cannot be tag editors.");
+    ensure!(
+        tag.author != user.0.id.get() as i64,
+        "That user owns this tag, so they can already edit it."
+    );
+
+    let editors = TagEditor::list_for_tag(handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag editors")?;
+    ensure!(
+        editors.len() < MAX_EDITORS_PER_TAG,
+        "Tags cannot have more than {MAX_EDITORS_PER_TAG} editors."
+    );
+
+    let success = TagEditor {
+        guild_id: guild_id.get() as i64,
+        tag_name: tag.name.clone(),
+        user_id: user.0.id.get() as i64,
+    }
+    .add(handler)
+    .await
+    .context("Failed to add tag editor")?;
+    ensure!(success, "That user already is an editor of this tag.");
+
+    ctxt.reply(format!("<@{}> can now edit tag {}.", user.0.id, tag.name.codestring()))
+        .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "stop another user from editing a tag that you own",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name] [user]",
+    examples = ["test @jacher"],
+    guild_only = true
+)]
+pub async fn removeeditor(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+    user: User,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag editors can only be removed in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    let tag = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    // editors may remove themselves
+    ensure!(
+        user.0.id == ctxt.data.author.id || can_manage_tag(&ctxt, &tag).await?,
+        "You can only remove editors from tags that you own."
+    );
+
+    let success = TagEditor::remove(handler, guild_id.get() as i64, &tag.name, user.0.id.get() as i64)
+        .await
+        .context("Failed to remove tag editor")?;
+    ensure!(success, "That user is not an editor of this tag.");
+
+    ctxt.reply(format!(
+        "<@{}> can no longer edit tag {}.",
+        user.0.id,
+        tag.name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
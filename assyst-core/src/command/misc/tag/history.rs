@@ -0,0 +1,257 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_common::util::diff::line_diff;
+use assyst_common::util::unix_timestamp;
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_editor::TagEditor;
+use assyst_database::model::tag_revision::{TagRevision, TagRevisionKind};
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+use twilight_model::channel::message::component::ButtonStyle;
+use twilight_model::channel::message::{Component, EmojiReactionType};
+use twilight_model::id::marker::EmojiMarker;
+use twilight_model::id::Id;
+
+use super::{format_history_page, resolve_alias, user_revision, TagPaginatorComponentMetadata, DEFAULT_LIST_COUNT};
+use crate::command::arguments::WordAutocomplete;
+use crate::command::componentctxt::{button_emoji_new, button_new, ComponentCtxt, ComponentMetadata};
+use crate::command::messagebuilder::MessageBuilder;
+use crate::command::{Availability, Category, CommandCtxt};
+
+#[command(
+    description = "list every change that was made to a tag",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name]",
+    examples = ["test"],
+    guild_only = true
+)]
+pub async fn history(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] name: WordAutocomplete,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag history can only be fetched in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    let name = resolve_alias(&ctxt, guild_id.get(), &name.0.to_ascii_lowercase()).await?;
+
+    Tag::record_initial_revision(handler, guild_id.get() as i64, &name)
+        .await
+        .context("Failed to record tag revision")?;
+
+    let count = TagRevision::get_count(handler, guild_id.get() as i64, &name)
+        .await
+        .context("Failed to get tag revision count")?;
+    ensure!(count > 0, "This tag has no history in this server.");
+    let pages = (count as f64 / DEFAULT_LIST_COUNT as f64).ceil() as u64;
+
+    let revisions = TagRevision::get_paged(handler, guild_id.get() as i64, &name, 0, DEFAULT_LIST_COUNT)
+        .await
+        .context("Failed to fetch tag history")?;
+
+    let message = format_history_page(&name, &revisions, 1, pages, count as u64, &ctxt.data.calling_prefix);
+
+    let timestamp = unix_timestamp();
+    let page_next = format!("page_next-{timestamp}");
+    let page_prev = format!("page_prev-{timestamp}");
+    let jump_to_page = format!("page_jump-{timestamp}");
+    let modal_cid = format!("page_jump-modal-{timestamp}");
+    let modal_text_cid = format!("page_jump-modal-text-{timestamp}");
+
+    ctxt.reply(MessageBuilder {
+        content: Some(message),
+        attachments: Vec::new(),
+        embeds: Vec::new(),
+        components: Some(vec![
+            Component::Button(button_emoji_new(
+                &page_prev,
+                EmojiReactionType::Custom {
+                    name: Some("arrow_left".to_owned()),
+                    animated: false,
+                    id: Id::<EmojiMarker>::new(1272681864204779560),
+                },
+                ButtonStyle::Secondary,
+            )),
+            Component::Button(button_new(&jump_to_page, "Jump", ButtonStyle::Primary)),
+            Component::Button(button_emoji_new(
+                &page_next,
+                EmojiReactionType::Custom {
+                    name: Some("arrow_right".to_owned()),
+                    animated: false,
+                    id: Id::<EmojiMarker>::new(1272681890129645568),
+                },
+                ButtonStyle::Secondary,
+            )),
+        ]),
+        component_ctxt: Some((
+            vec![
+                page_next.clone(),
+                page_prev.clone(),
+                jump_to_page.clone(),
+                modal_cid.clone(),
+            ],
+            ComponentCtxt::new(
+                ctxt.assyst().clone(),
+                ComponentMetadata::TagList(TagPaginatorComponentMetadata {
+                    page_next_cid: page_next,
+                    page_prev_cid: page_prev,
+                    page_jump_cid: jump_to_page,
+                    jump_modal_cid: modal_cid,
+                    jump_modal_text_cid: modal_text_cid,
+                    current_page: 1,
+                    invocating_user_id: ctxt.data.author.id,
+                    target_user_id: None,
+                    tag_count: count as u64,
+                    calling_prefix: ctxt.data.calling_prefix.clone(),
+                    search_criteria: None,
+                    history_tag: Some(name),
+                }),
+            ),
+        )),
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Tag revisions are stored as `i32`, so larger revisions can not exist
+fn revision_number(revision: u64) -> anyhow::Result<i32> {
+    i32::try_from(revision).ok().context("That revision does not exist.")
+}
+
+#[command(
+    description = "show how a tag changed since one of its revisions",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name] [revision]",
+    examples = ["test 3"],
+    guild_only = true
+)]
+pub async fn diff(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] name: WordAutocomplete,
+    revision: u64,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag history can only be fetched in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    let name = resolve_alias(&ctxt, guild_id.get(), &name.0.to_ascii_lowercase()).await?;
+
+    let old = TagRevision::get(handler, guild_id.get() as i64, &name, revision_number(revision)?)
+        .await
+        .context("Failed to fetch tag revision")?
+        .context("That revision does not exist.")?;
+
+    // deleted tags are compared to an empty tag
+    let current = Tag::get(handler, guild_id.get() as i64, &name)
+        .await
+        .context("Failed to fetch tag")?
+        .map(|tag| tag.data)
+        .unwrap_or_default();
+
+    if old.data == current {
+        ctxt.reply(format!(
+            "Tag {} has not changed since revision {revision}.",
+            name.codestring()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctxt.reply(format!(
+        "Changes to tag {} since revision {revision}:\n{}",
+        name.codestring(),
+        line_diff(&old.data, &current).codeblock("diff")
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "restore a tag that you own or are an editor of to one of its revisions (server managers can also restore deleted tags)",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name] [revision]",
+    examples = ["test 3"],
+    guild_only = true
+)]
+pub async fn rollback(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+    revision: u64,
+) -> anyhow::Result<()> {
+    let author = ctxt.data.author.id.get();
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be rolled back in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    let name = resolve_alias(&ctxt, guild_id.get(), &name.0.to_ascii_lowercase()).await?;
+
+    let target = TagRevision::get(handler, guild_id.get() as i64, &name, revision_number(revision)?)
+        .await
+        .context("Failed to fetch tag revision")?
+        .context("That revision does not exist.")?;
+    ensure!(
+        target.kind != TagRevisionKind::Delete.as_str(),
+        "Cannot roll back to a deletion, delete the tag instead."
+    );
+
+    let is_manager = ctxt
+        .assyst()
+        .rest_cache_handler
+        .user_is_guild_manager(guild_id.get(), author)
+        .await
+        .context("Failed to fetch user permissions")?;
+
+    let rollback = user_revision(&ctxt, guild_id.get(), &name, &target.data, TagRevisionKind::Rollback);
+    let new_revision = match Tag::get(handler, guild_id.get() as i64, &name)
+        .await
+        .context("Failed to fetch tag")?
+    {
+        Some(tag) => {
+            ensure!(
+                tag.author == author as i64
+                    || is_manager
+                    || TagEditor::is_editor(handler, guild_id.get() as i64, &name, author as i64)
+                        .await
+                        .context("Failed to fetch tag editors")?,
+                "You can only roll back tags that you own or are an editor of."
+            );
+
+            Tag::edit(handler, tag.author, &rollback)
+                .await
+                .context("Failed to edit tag")?
+                .context("Failed to roll back that tag. Does it still exist?")?
+        },
+        None => {
+            ensure!(is_manager, "Only server managers can restore deleted tags.");
+
+            Tag {
+                name: name.clone(),
+                guild_id: guild_id.get() as i64,
+                data: target.data.clone(),
+                author: author as i64,
+                created_at: unix_timestamp() as i64,
+            }
+            .set_with_revision(handler, &rollback)
+            .await
+            .context("Failed to create tag")?
+            .context("That tag name is already used in this server.")?
+        },
+    };
+
+    ctxt.reply(format!(
+        "Rolled back tag {} to revision {revision} (now revision {new_revision}).",
+        name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
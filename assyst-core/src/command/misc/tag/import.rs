@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_common::util::{string_from_likely_utf8, unix_timestamp};
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_alias::TagAlias;
+use assyst_proc_macro::{command, Flags};
+use assyst_string_fmt::Markdown;
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use super::ensure_valid_tag_name;
+use crate::command::arguments::ImageUrl;
+use crate::command::{Availability, Category, CommandCtxt};
+use crate::downloader::{download_content, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES};
+
+const MAX_IMPORTED_TAGS: usize = 500;
+/// Maximum total size of the tags in an import, to guard against zip bombs
+const MAX_IMPORT_SIZE_BYTES: usize = 10_000_000;
+/// Maximum number of conflicting tag names shown in the preview of an import
+const MAX_DISPLAYED_CONFLICTS: usize = 20;
+
+/// A tag in a JSON export. Other bots name the content of a tag differently, so some common names
+/// are accepted too.
+#[derive(Deserialize)]
+struct TagExportEntry {
+    name: String,
+    #[serde(alias = "data", alias = "contents", alias = "text")]
+    content: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagExport {
+    /// `[{"name": "...", "content": "..."}]`
+    List(Vec<TagExportEntry>),
+    /// `{"tags": [{"name": "...", "content": "..."}]}`
+    Wrapped { tags: Vec<TagExportEntry> },
+    /// `{"name": "content"}`
+    Map(HashMap<String, String>),
+}
+
+/// Reads the tags from a zip created by `tag backup`, or from a JSON export
+fn parse_tag_import(data: &[u8]) -> anyhow::Result<Vec<(String, String)>> {
+    if !data.starts_with(b"PK\x03\x04") {
+        let export: TagExport =
+            serde_json::from_slice(data).context("The file is neither a tag backup nor a valid JSON tag export")?;
+
+        return Ok(match export {
+            TagExport::List(tags) | TagExport::Wrapped { tags } => {
+                tags.into_iter().map(|tag| (tag.name, tag.content)).collect()
+            },
+            TagExport::Map(tags) => tags.into_iter().collect(),
+        });
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).context("Failed to read the tag backup")?;
+    let mut tags = Vec::new();
+    let mut total_size = 0;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index).context("Failed to read the tag backup")?;
+        if file.is_dir() {
+            continue;
+        }
+
+        // tag backup names its files tag-{index}-{name}.txt
+        let file_name = file.name().rsplit('/').next().unwrap_or_default().to_owned();
+        let name = file_name
+            .strip_prefix("tag-")
+            .and_then(|name| name.split_once('-'))
+            .and_then(|(_, name)| name.strip_suffix(".txt"))
+            .with_context(|| format!("{} is not a file from a tag backup.", file_name.codestring()))?;
+
+        let mut content = Vec::new();
+        (&mut file)
+            .take((MAX_IMPORT_SIZE_BYTES - total_size + 1) as u64)
+            .read_to_end(&mut content)
+            .context("Failed to read the tag backup")?;
+        total_size += content.len();
+        ensure!(
+            total_size <= MAX_IMPORT_SIZE_BYTES,
+            "Tag imports cannot be larger than {} MB.",
+            MAX_IMPORT_SIZE_BYTES / 1_000_000
+        );
+
+        tags.push((name.to_owned(), string_from_likely_utf8(content)));
+    }
+
+    Ok(tags)
+}
+
+#[derive(Default, Flags)]
+pub struct TagImportFlags {
+    /// replace your existing tags with the imported ones
+    pub overwrite: bool,
+    /// skip imported tags whose names are already used
+    pub skip: bool,
+}
+
+#[command(
+    description = "create tags from a tag backup zip or a JSON export of tags",
+    cooldown = Duration::from_secs(30),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[file] <flags>",
+    examples = ["https://example.com/tags.zip", "https://example.com/tags.json --skip"],
+    guild_only = true
+)]
+pub async fn import(ctxt: CommandCtxt<'_>, file: ImageUrl, flags: TagImportFlags) -> anyhow::Result<()> {
+    let author = ctxt.data.author.id.get();
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be imported in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    ensure!(
+        !(flags.overwrite && flags.skip),
+        "The overwrite and skip flags cannot be used together."
+    );
+
+    let data = download_content(
+        &ctxt.assyst().reqwest_client,
+        &file.0,
+        ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
+        true,
+    )
+    .await
+    .context("Failed to download the file")?;
+
+    let imported = parse_tag_import(&data)?;
+    ensure!(!imported.is_empty(), "The file does not contain any tags.");
+    ensure!(
+        imported.len() <= MAX_IMPORTED_TAGS,
+        "Cannot import more than {MAX_IMPORTED_TAGS} tags at once."
+    );
+
+    let mut names = HashSet::new();
+    for (name, _) in &imported {
+        ensure_valid_tag_name(name)
+            .with_context(|| format!("The file contains the invalid tag name {}.", name.codestring()))?;
+        ensure!(
+            names.insert(name.to_ascii_lowercase()),
+            "The file contains tag {} more than once.",
+            name.to_ascii_lowercase().codestring()
+        );
+    }
+
+    let existing = Tag::get_names_in_guild(handler, guild_id.get() as i64)
+        .await
+        .context("Failed to fetch tags")?
+        .into_iter()
+        .map(|(author, name)| (name, author))
+        .collect::<HashMap<_, _>>();
+    let aliases = TagAlias::list_in_guild(handler, guild_id.get() as i64)
+        .await
+        .context("Failed to fetch tag aliases")?
+        .into_iter()
+        .map(|alias| alias.alias)
+        .collect::<HashSet<_>>();
+
+    // conflicts with tags of other users and with aliases are never overwritten
+    let mut new = Vec::new();
+    let mut own_conflicts = Vec::new();
+    let mut other_conflicts = Vec::new();
+    for (name, data) in imported {
+        let name = name.to_ascii_lowercase();
+        match existing.get(&name) {
+            Some(&owner) if owner == author => own_conflicts.push((name, data)),
+            Some(_) => other_conflicts.push(name),
+            None if aliases.contains(&name) => other_conflicts.push(name),
+            None => new.push((name, data)),
+        }
+    }
+
+    if !flags.overwrite && !flags.skip && (!own_conflicts.is_empty() || !other_conflicts.is_empty()) {
+        let conflicts = own_conflicts
+            .iter()
+            .map(|(name, _)| name)
+            .chain(other_conflicts.iter())
+            .collect::<Vec<_>>();
+
+        let mut message = format!(
+            "{} tags can be imported, but {} already exist in this server: {}",
+            new.len(),
+            conflicts.len(),
+            conflicts
+                .iter()
+                .take(MAX_DISPLAYED_CONFLICTS)
+                .map(|name| name.codestring())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if conflicts.len() > MAX_DISPLAYED_CONFLICTS {
+            message += &format!(" and {} more", conflicts.len() - MAX_DISPLAYED_CONFLICTS);
+        }
+        message += &format!(
+            "\nRun the command again with `--skip` to only import the other tags, or with `--overwrite` to also replace the {} conflicting tags that you own.",
+            own_conflicts.len()
+        );
+
+        ctxt.reply(message).await?;
+        return Ok(());
+    }
+
+    let skipped = other_conflicts.len() + if flags.overwrite { 0 } else { own_conflicts.len() };
+    let overwritten = if flags.overwrite { own_conflicts } else { Vec::new() };
+
+    ensure!(
+        !new.is_empty() || !overwritten.is_empty(),
+        "All tags in the file already exist in this server."
+    );
+
+    let created_at = unix_timestamp() as i64;
+    let tags = new
+        .iter()
+        .chain(overwritten.iter())
+        .map(|(name, data)| Tag {
+            name: name.clone(),
+            guild_id: guild_id.get() as i64,
+            data: data.clone(),
+            author: author as i64,
+            created_at,
+        })
+        .collect::<Vec<_>>();
+
+    // also records the revisions of the imported tags
+    Tag::import(handler, &tags, flags.overwrite)
+        .await
+        .context("Failed to import tags")?;
+
+    let mut message = format!("Imported {} tags", new.len() + overwritten.len());
+    if !overwritten.is_empty() {
+        message += &format!(" ({} replaced)", overwritten.len());
+    }
+    if skipped > 0 {
+        message += &format!(", skipped {skipped} tags whose names are already used");
+    }
+    message += ".";
+
+    ctxt.reply(message).await?;
+
+    Ok(())
+}
@@ -7,38 +7,211 @@ use anyhow::{anyhow, bail, ensure, Context};
 use assyst_common::util::discord::{format_discord_timestamp, format_tag, get_avatar_url};
 use assyst_common::util::{string_from_likely_utf8, unix_timestamp};
 use assyst_database::model::tag::Tag;
-use assyst_proc_macro::command;
+use assyst_database::model::tag_alias::TagAlias;
+use assyst_database::model::tag_command::TagCommand;
+use assyst_database::model::tag_editor::TagEditor;
+use assyst_database::model::tag_install::TagInstall;
+use assyst_database::model::tag_revision::{TagRevision, TagRevisionKind};
+use assyst_database::model::tag_time_budget::TagTimeBudget;
+use assyst_database::model::tag_usage::TagUsage;
+use assyst_database::model::tag_variable::{TagVariable, GUILD_SCOPE};
+use assyst_flux_iface::flux_request::FluxRequest;
+use assyst_flux_iface::jobs::tag_operation_options;
+use assyst_proc_macro::{command, Flags};
 use assyst_string_fmt::Markdown;
+use assyst_tag::errors::{format_lint, TResult};
+use assyst_tag::parser::limits::{DEFAULT_TIME_BUDGET, MAX_PERSISTENT_VARIABLES, MAX_TIME_BUDGET, MIN_TIME_BUDGET};
 use assyst_tag::parser::ParseMode;
-use assyst_tag::ParseResult;
-use tokio::runtime::Handle;
+use assyst_tag::resumable::{ResumableParse, Step};
+use assyst_tag::{ParseResult, StorageScope};
 use twilight_model::channel::message::component::{ActionRow, ButtonStyle, TextInput, TextInputStyle};
-use twilight_model::channel::message::{Component, EmojiReactionType};
+use twilight_model::channel::message::{Component, Embed, EmojiReactionType};
 use twilight_model::channel::Message;
 use twilight_model::id::marker::{ChannelMarker, EmojiMarker, UserMarker};
 use twilight_model::id::Id;
-use twilight_util::builder::command::IntegerBuilder;
+use twilight_util::builder::embed::{EmbedBuilder, EmbedFieldBuilder, EmbedFooterBuilder, ImageSource};
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
+use self::aliases::{alias_command, unalias_command};
+use self::debug::debug_command;
+use self::docs::docs_command;
+use self::editors::{addeditor_command, editors_command, removeeditor_command};
+use self::history::{diff_command, history_command, rollback_command};
+use self::import::import_command;
+use self::promote::{demote_command, promote_command};
+use self::timeout::timeout_command;
+use self::transfer::transfer_command;
+use self::usage::{top_command, unused_command};
 use super::CommandCtxt;
 use crate::assyst::ThreadSafeAssyst;
-use crate::command::arguments::{Image, ImageUrl, ParseArgument, RestNoFlags, User, Word, WordAutocomplete};
+use crate::command::arguments::{ImageUrl, RestNoFlags, User, Word, WordAutocomplete};
 use crate::command::autocomplete::AutocompleteData;
 use crate::command::componentctxt::{
     button_emoji_new, button_new, respond_modal, respond_update_text, ComponentCtxt, ComponentInteractionData,
     ComponentMetadata,
 };
-use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
 use crate::command::messagebuilder::{Attachment, MessageBuilder};
 use crate::command::{Availability, Category};
+use crate::define_commandgroup;
 use crate::downloader::{download_content, ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES};
-use crate::rest::eval::fake_eval;
-use crate::{define_commandgroup, int_arg_u64};
+use crate::js_sandbox::eval_tag_javascript;
+use crate::tag_commands::sync_guild_commands;
+
+pub mod aliases;
+pub mod debug;
+pub mod docs;
+pub mod editors;
+pub mod history;
+pub mod import;
+pub mod promote;
+pub mod timeout;
+pub mod transfer;
+pub mod usage;
 
 const DEFAULT_LIST_COUNT: i64 = 15;
-const RESERVED_NAMES: &[&str] = &["create", "add", "edit", "raw", "remove", "delete", "list", "info"];
+/// Maximum number of warnings shown when creating or editing a tag
+const MAX_DISPLAYED_LINTS: usize = 3;
+const RESERVED_NAMES: &[&str] = &[
+    "create",
+    "add",
+    "edit",
+    "raw",
+    "remove",
+    "delete",
+    "list",
+    "info",
+    "debug",
+    "timeout",
+    "promote",
+    "demote",
+    "history",
+    "diff",
+    "rollback",
+    "transfer",
+    "alias",
+    "unalias",
+    "editors",
+    "addeditor",
+    "removeeditor",
+    "top",
+    "unused",
+    "import",
+    "docs",
+];
+/// How many times its time budget a tag may take in total, including the time spent waiting for
+/// responses to its requests, which does not count towards the time budget itself
+const WALL_CLOCK_BUDGET_FACTOR: u32 = 3;
+/// Name of the slash subcommand that runs a tag
+const TAG_DEFAULT_INTERACTION_SUBCOMMAND: &str = "run";
+
+/// Returns how long tags in a guild may run for
+async fn guild_time_budget(assyst: &ThreadSafeAssyst, guild_id: u64) -> anyhow::Result<Duration> {
+    let budget = TagTimeBudget::get(&assyst.database_handler, guild_id)
+        .await
+        .context("Failed to fetch tag time budget")?;
+
+    Ok(budget.map_or(DEFAULT_TIME_BUDGET, |budget| {
+        Duration::from_millis(budget.milliseconds).clamp(MIN_TIME_BUDGET, MAX_TIME_BUDGET)
+    }))
+}
+
+/// Whether the invoking user owns a tag or is a manager of its server
+pub async fn can_manage_tag(ctxt: &CommandCtxt<'_>, tag: &Tag) -> anyhow::Result<bool> {
+    let author = ctxt.data.author.id.get();
+
+    Ok(tag.author == author as i64
+        || ctxt
+            .assyst()
+            .rest_cache_handler
+            .user_is_guild_manager(tag.guild_id as u64, author)
+            .await
+            .context("Failed to fetch user permissions")?)
+}
+
+/// Checks that a name can be used for a new tag. Tag names are case insensitive.
+pub fn ensure_valid_tag_name(name: &str) -> anyhow::Result<()> {
+    let name = name.to_ascii_lowercase();
+
+    ensure!(!name.is_empty(), "Tag names cannot be empty.");
+    ensure!(name.len() < 20, "Tag names cannot exceed 20 characters.");
+    ensure!(
+        !RESERVED_NAMES.contains(&name.as_str()),
+        "Tag names cannot be a reserved word."
+    );
+    ensure!(
+        !name.contains(char::is_whitespace),
+        "Tag names cannot contain spaces or other whitespace."
+    );
+
+    Ok(())
+}
+
+/// Tag names share their namespace with aliases, so a new tag cannot take the name of an alias
+pub async fn ensure_not_alias(ctxt: &CommandCtxt<'_>, guild_id: u64, name: &str) -> anyhow::Result<()> {
+    let alias = TagAlias::get(&ctxt.assyst().database_handler, guild_id as i64, name)
+        .await
+        .context("Failed to fetch tag alias")?;
+
+    ensure!(
+        alias.is_none(),
+        "That tag name is already used as an alias in this server."
+    );
+
+    Ok(())
+}
+
+/// Resolves an alias to the name of the tag it points to. Other names are returned unchanged.
+async fn resolve_alias(ctxt: &CommandCtxt<'_>, guild_id: u64, name: &str) -> anyhow::Result<String> {
+    let alias = TagAlias::get(&ctxt.assyst().database_handler, guild_id as i64, name)
+        .await
+        .context("Failed to fetch tag alias")?;
+
+    Ok(alias.map_or_else(|| name.to_owned(), |alias| alias.tag_name))
+}
+
+/// A change of a tag made by the invoking user, to be recorded in the history of the tag
+pub fn user_revision(
+    ctxt: &CommandCtxt<'_>,
+    guild_id: u64,
+    name: &str,
+    data: &str,
+    kind: TagRevisionKind,
+) -> TagRevision {
+    TagRevision {
+        guild_id: guild_id as i64,
+        tag_name: name.to_owned(),
+        revision: 0,
+        data: data.to_owned(),
+        author: ctxt.data.author.id.get() as i64,
+        created_at: unix_timestamp() as i64,
+        kind: kind.as_str().to_owned(),
+    }
+}
+
+/// Runs the tag checker on a tag source and formats any warnings it found
+fn format_tag_lints(source: &str) -> Option<String> {
+    let lints = assyst_tag::check(source);
+    if lints.is_empty() {
+        return None;
+    }
+
+    let mut output = lints
+        .iter()
+        .take(MAX_DISPLAYED_LINTS)
+        .map(|lint| format_lint(source, lint))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    if lints.len() > MAX_DISPLAYED_LINTS {
+        output += &format!("\n\n... and {} more", lints.len() - MAX_DISPLAYED_LINTS);
+    }
+
+    Some(format!(
+        "\nThe tag was saved, but it might not work as expected:\n{}",
+        output.codeblock("ansi")
+    ))
+}
 
 #[command(
     description = "create a tag",
@@ -56,12 +229,8 @@ pub async fn create(ctxt: CommandCtxt<'_>, name: Word, contents: RestNoFlags) ->
         bail!("Tags can only be created in guilds.")
     };
 
-    ensure!(name.0.len() < 20, "Tag names cannot exceed 20 characters.");
-    ensure!(
-        !RESERVED_NAMES.contains(&&name.0[..]),
-        "Tag names cannot be a reserved word."
-    );
-    ensure!(!name.0.contains(' '), "Tag names cannot contain spaces.");
+    ensure_valid_tag_name(&name.0)?;
+    ensure_not_alias(&ctxt, guild_id.get(), &name.0.to_ascii_lowercase()).await?;
 
     let tag = Tag {
         name: name.0.to_ascii_lowercase(),
@@ -71,24 +240,30 @@ pub async fn create(ctxt: CommandCtxt<'_>, name: Word, contents: RestNoFlags) ->
         created_at: unix_timestamp() as i64,
     };
 
+    let revision = user_revision(&ctxt, guild_id.get(), &tag.name, &tag.data, TagRevisionKind::Create);
     let success = tag
-        .set(&ctxt.assyst().database_handler)
+        .set_with_revision(&ctxt.assyst().database_handler, &revision)
         .await
-        .context("Failed to create tag")?;
+        .context("Failed to create tag")?
+        .is_some();
 
     ensure!(success, "That tag name is already used in this server.");
 
-    ctxt.reply(format!(
+    let mut message = format!(
         "Successfully created tag {}",
         tag.name.to_ascii_lowercase().codestring()
-    ))
-    .await?;
+    );
+    if let Some(lints) = format_tag_lints(&tag.data) {
+        message += &lints;
+    }
+
+    ctxt.reply(message).await?;
 
     Ok(())
 }
 
 #[command(
-    description = "edit a tag that you own",
+    description = "edit a tag that you own or are an editor of",
     cooldown = Duration::from_secs(2),
     access = Availability::Public,
     category = Category::Misc,
@@ -105,24 +280,35 @@ pub async fn edit(
     let Some(guild_id) = ctxt.data.guild_id else {
         bail!("Tags can only be edited in guilds.")
     };
+    let handler = &ctxt.assyst().database_handler;
 
-    let success = Tag::edit(
-        &ctxt.assyst().database_handler,
-        author as i64,
-        guild_id.get() as i64,
-        &name.0.to_ascii_lowercase(),
-        &contents.0,
-    )
-    .await
-    .context("Failed to edit tag")?;
+    let tag = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    ensure!(
+        tag.author == author as i64
+            || TagEditor::is_editor(handler, guild_id.get() as i64, &tag.name, author as i64)
+                .await
+                .context("Failed to fetch tag editors")?,
+        "Failed to edit that tag. Do you own it, or are you an editor of it?"
+    );
 
-    ensure!(success, "Failed to edit that tag. Does it exist, and do you own it?");
+    let revision = user_revision(&ctxt, guild_id.get(), &tag.name, &contents.0, TagRevisionKind::Edit);
+    let success = Tag::edit(handler, tag.author, &revision)
+        .await
+        .context("Failed to edit tag")?
+        .is_some();
 
-    ctxt.reply(format!(
-        "Successfully edited tag {}",
-        name.0.to_ascii_lowercase().codestring()
-    ))
-    .await?;
+    ensure!(success, "Failed to edit that tag. Does it still exist?");
+
+    let mut message = format!("Successfully edited tag {}", tag.name.codestring());
+    if let Some(lints) = format_tag_lints(&contents.0) {
+        message += &lints;
+    }
+
+    ctxt.reply(message).await?;
 
     Ok(())
 }
@@ -146,33 +332,63 @@ pub async fn delete(
         bail!("Tags can only be deleted in guilds.")
     };
 
-    let success = if ctxt
+    if let Some(tag) = Tag::get(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        &name.0.to_ascii_lowercase(),
+    )
+    .await
+    .context("Failed to fetch tag")?
+    {
+        ensure!(
+            tag.name == name.0.to_ascii_lowercase(),
+            "{} is an alias of tag {}. Remove the alias with `{}t unalias {}` instead.",
+            name.0.to_ascii_lowercase().codestring(),
+            tag.name.codestring(),
+            ctxt.data.calling_prefix,
+            name.0.to_ascii_lowercase()
+        );
+    }
+
+    let is_manager = ctxt
         .assyst()
         .rest_cache_handler
         .user_is_guild_manager(guild_id.get(), author)
         .await
-        .context("Failed to fetch user permissions")?
-    {
-        Tag::delete_force(
-            &ctxt.assyst().database_handler,
-            &name.0.to_ascii_lowercase(),
-            guild_id.get() as i64,
-        )
-        .await
-        .context("Failed to delete tag")?
-    } else {
-        Tag::delete(
-            &ctxt.assyst().database_handler,
-            &name.0.to_ascii_lowercase(),
-            guild_id.get() as i64,
-            author as i64,
-        )
-        .await
-        .context("Failed to delete tag")?
-    };
+        .context("Failed to fetch user permissions")?;
+
+    let promoted = TagCommand::get(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        &name.0.to_ascii_lowercase(),
+    )
+    .await
+    .context("Failed to fetch tag command")?
+    .is_some();
+
+    let revision = user_revision(
+        &ctxt,
+        guild_id.get(),
+        &name.0.to_ascii_lowercase(),
+        "",
+        TagRevisionKind::Delete,
+    );
+    let success = Tag::delete_with_dependents(
+        &ctxt.assyst().database_handler,
+        (!is_manager).then_some(author as i64),
+        &revision,
+    )
+    .await
+    .context("Failed to delete tag")?;
 
     ensure!(success, "Failed to delete that tag. Does it exist, and do you own it?");
 
+    if promoted {
+        sync_guild_commands(ctxt.assyst(), guild_id.get())
+            .await
+            .context("Failed to unregister the slash command of the tag")?;
+    }
+
     ctxt.reply(format!(
         "Successfully deleted tag {}",
         name.0.to_ascii_lowercase().codestring()
@@ -182,7 +398,7 @@ pub async fn delete(
     Ok(())
 }
 
-/// Used for both listing and searching tags
+/// Used for listing and searching tags, and for listing the revisions of a tag
 #[derive(Clone, Debug)]
 pub struct TagPaginatorComponentMetadata {
     pub current_page: u64,
@@ -196,6 +412,8 @@ pub struct TagPaginatorComponentMetadata {
     pub tag_count: u64,
     pub calling_prefix: String,
     pub search_criteria: Option<String>,
+    /// Set when paging through the revisions of this tag. `tag_count` is the number of revisions
+    pub history_tag: Option<String>,
 }
 impl TagPaginatorComponentMetadata {
     pub async fn component_callback(&mut self, data: &ComponentInteractionData) -> anyhow::Result<()> {
@@ -267,6 +485,36 @@ impl TagPaginatorComponentMetadata {
 
         let offset = (self.current_page as i64 - 1) * DEFAULT_LIST_COUNT;
 
+        if let Some(ref tag_name) = self.history_tag {
+            let revisions = TagRevision::get_paged(
+                &data.assyst.database_handler,
+                data.invocation_guild_id.unwrap().get() as i64,
+                tag_name,
+                offset,
+                DEFAULT_LIST_COUNT,
+            )
+            .await?;
+
+            let message = format_history_page(
+                tag_name,
+                &revisions,
+                self.current_page,
+                pages as u64,
+                self.tag_count,
+                &self.calling_prefix,
+            );
+
+            respond_update_text(
+                data.assyst.clone(),
+                data.interaction_id,
+                &data.interaction_token,
+                &message,
+            )
+            .await?;
+
+            return Ok(());
+        }
+
         let tags = match self.target_user_id {
             Some(u) => match self.search_criteria {
                 Some(ref s) => {
@@ -365,53 +613,12 @@ impl TagPaginatorComponentMetadata {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Flags)]
 pub struct TagListFlags {
+    /// go to this page
+    #[flag(default = 1)]
     pub page: u64,
 }
-impl FlagDecode for TagListFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("page", FlagType::WithValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
-        let page = raw_decode
-            .get("page")
-            .and_then(|x| x.as_deref())
-            .map_or(Ok(1), str::parse)
-            .context("Failed to parse page number")?;
-
-        let result = Self { page };
-
-        Ok(result)
-    }
-}
-impl ParseArgument for TagListFlags {
-    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
-        vec![IntegerBuilder::new("page", "go to this page").required(false).build()]
-    }
-
-    async fn parse_raw_message(
-        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
-        label: crate::command::Label,
-    ) -> Result<Self, crate::command::errors::TagParseError> {
-        let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
-        Ok(parsed)
-    }
-
-    async fn parse_command_option(
-        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
-        _: crate::command::Label,
-    ) -> Result<Self, TagParseError> {
-        let page = int_arg_u64!(ctxt, "page", 1);
-
-        Ok(Self { page })
-    }
-}
 
 #[command(
     description = "list tags in the server (or owned by a certain user in the server)",
@@ -420,7 +627,6 @@ impl ParseArgument for TagListFlags {
     category = Category::Misc,
     usage = "<user id|mention>",
     examples = ["@jacher"],
-    flag_descriptions = [("page <page>", "start at this page number")],
     guild_only = true,
     context_menu_user_command = "List Owned Tags"
 )]
@@ -512,7 +718,8 @@ pub async fn list(ctxt: CommandCtxt<'_>, user: Option<User>, flags: TagListFlags
 
     ctxt.reply(MessageBuilder {
         content: Some(message),
-        attachment: None,
+        attachments: Vec::new(),
+        embeds: Vec::new(),
         components: Some(vec![
             Component::Button(button_emoji_new(
                 &page_prev,
@@ -555,6 +762,7 @@ pub async fn list(ctxt: CommandCtxt<'_>, user: Option<User>, flags: TagListFlags
                     tag_count: count as u64,
                     calling_prefix: ctxt.data.calling_prefix.clone(),
                     search_criteria: None,
+                    history_tag: None,
                 }),
             ),
         )),
@@ -590,13 +798,65 @@ pub async fn info(
     .context("Tag not found in this server.")?;
 
     let fmt = format_discord_timestamp(tag.created_at as u64);
-    let message = format!(
+    let mut message = format!(
         "🗒️ **Tag information: **{}\n\nAuthor: <@{}>\nCreated: {}",
         tag.name.to_ascii_lowercase(),
         tag.author,
         fmt
     );
 
+    // uses are written to the database in batches, so add the ones that were not written yet
+    let usage = TagUsage::get(&ctxt.assyst().database_handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag usage statistics")?;
+    let (mut uses, mut last_used) = usage.map_or((0, 0), |usage| (usage.uses, usage.last_used));
+    if let Some((pending_uses, pending_last_used)) = ctxt.assyst().tag_uses.pending(guild_id.get(), &tag.name) {
+        uses += pending_uses;
+        last_used = last_used.max(pending_last_used);
+    }
+    message += &format!("\nUses: {uses}");
+    if last_used > 0 {
+        message += &format!("\nLast used: {}", format_discord_timestamp(last_used as u64));
+    }
+
+    if let Some(install) = TagInstall::get(&ctxt.assyst().database_handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag installation")?
+    {
+        message += &format!(
+            "\nInstalled from: library tag {} (version {})",
+            install.published_id, install.version
+        );
+    }
+
+    let editors = TagEditor::list_for_tag(&ctxt.assyst().database_handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag editors")?;
+    if !editors.is_empty() {
+        message += &format!(
+            "\nEditors: {}",
+            editors
+                .iter()
+                .map(|editor| format!("<@{}>", editor.user_id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let aliases = TagAlias::list_for_tag(&ctxt.assyst().database_handler, guild_id.get() as i64, &tag.name)
+        .await
+        .context("Failed to fetch tag aliases")?;
+    if !aliases.is_empty() {
+        message += &format!(
+            "\nAliases: {}",
+            aliases
+                .iter()
+                .map(|alias| alias.alias.codestring())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
     ctxt.reply(message).await?;
 
     Ok(())
@@ -727,7 +987,8 @@ pub async fn search(ctxt: CommandCtxt<'_>, query: Word, user: Option<User>) -> a
 
     ctxt.reply(MessageBuilder {
         content: Some(message),
-        attachment: None,
+        attachments: Vec::new(),
+        embeds: Vec::new(),
         components: Some(vec![
             Component::Button(button_emoji_new(
                 &page_prev,
@@ -770,6 +1031,7 @@ pub async fn search(ctxt: CommandCtxt<'_>, query: Word, user: Option<User>) -> a
                     tag_count: count as u64,
                     calling_prefix: ctxt.data.calling_prefix.clone(),
                     search_criteria: Some(query.0),
+                    history_tag: None,
                 }),
             ),
         )),
@@ -884,12 +1146,8 @@ pub async fn paste(ctxt: CommandCtxt<'_>, name: Word) -> anyhow::Result<()> {
         bail!("Tags can only be pasted into guilds.")
     };
 
-    ensure!(name.0.len() < 20, "Tag names cannot exceed 20 characters.");
-    ensure!(
-        !RESERVED_NAMES.contains(&&name.0[..]),
-        "Tag names cannot be a reserved word."
-    );
-    ensure!(!name.0.contains(' '), "Tag names cannot contain spaces.");
+    ensure_valid_tag_name(&name.0)?;
+    ensure_not_alias(&ctxt, guild_id.get(), &name.0.to_ascii_lowercase()).await?;
 
     let content = ctxt
         .assyst()
@@ -906,10 +1164,12 @@ pub async fn paste(ctxt: CommandCtxt<'_>, name: Word) -> anyhow::Result<()> {
         created_at: unix_timestamp() as i64,
     };
 
+    let revision = user_revision(&ctxt, guild_id.get(), &t.name, &t.data, TagRevisionKind::Create);
     let success = t
-        .set(&ctxt.assyst().database_handler)
+        .set_with_revision(&ctxt.assyst().database_handler, &revision)
         .await
-        .context("Failed to create tag")?;
+        .context("Failed to create tag")?
+        .is_some();
 
     ensure!(success, "That tag name is already used in this server.");
 
@@ -971,42 +1231,14 @@ pub async fn default(
     .context("Failed to fetch tag")?
     .context("Tag not found in this server.")?;
 
-    let assyst = ctxt.assyst().clone();
-    let message = ctxt.data.message.cloned();
-    let channel_id = ctxt.data.channel_id.get();
-    let author = ctxt.data.author.clone();
-
-    let (res, tag) = tokio::task::spawn_blocking(move || {
-        let tag = tag;
-        let arguments: Vec<&str> = arguments.iter().map(|Word(word)| &**word).collect();
-        let tcx = TagContext {
-            tokio: Handle::current(),
-            message,
-            assyst,
-            guild_id: guild_id.get(),
-            channel_id,
-            author,
-        };
+    run_tag(&ctxt, tag, arguments.into_iter().map(|Word(word)| word).collect()).await
+}
 
-        (
-            assyst_tag::parse(&tag.data, &arguments, ParseMode::StopOnError, tcx),
-            tag,
-        )
-    })
-    .await
-    .expect("Tag task panicked");
-
-    match res {
-        Ok(ParseResult {
-            output,
-            attachment: None,
-        }) => ctxt.reply(output).await?,
-        Ok(ParseResult {
-            output,
-            attachment: Some((data, _)),
-        }) => {
-            ctxt.reply((Image(data), output.as_str())).await?;
-        },
+/// Runs a tag in the context of the invocation and replies with its output, or with the error it
+/// produced.
+pub async fn run_tag(ctxt: &CommandCtxt<'_>, tag: Tag, arguments: Vec<String>) -> anyhow::Result<()> {
+    match execute_tag(ctxt, &tag, arguments).await? {
+        Ok(result) => ctxt.reply(tag_response(result)?).await?,
         Err(err) => {
             ctxt.reply(assyst_tag::errors::format_error(&tag.data, err).codeblock("ansi"))
                 .await?;
@@ -1016,98 +1248,243 @@ pub async fn default(
     Ok(())
 }
 
+/// Runs a tag in the context of the invocation, returning what it produced. Also used by tag
+/// triggers, which run tags without a `tag` command and only reply if the tag has output.
+pub async fn execute_tag(
+    ctxt: &CommandCtxt<'_>,
+    tag: &Tag,
+    arguments: Vec<String>,
+) -> anyhow::Result<TResult<ParseResult>> {
+    let tcx = TagContext::new(ctxt, tag.name.clone()).await?;
+    tcx.assyst.tag_uses.record(tcx.guild_id, &tag.name);
+
+    let parse = ResumableParse::new(tag.data.clone(), arguments, ParseMode::StopOnError, tcx.time_budget);
+    let (res, _) = drive_tag(parse, &tcx).await;
+
+    Ok(res)
+}
+
+/// Runs a tag to completion. The parser runs on the blocking thread pool, but only while parsing:
+/// whenever the tag needs something from its context, it is suspended until the request completes.
+/// The tag times out if it has not finished after [`WALL_CLOCK_BUDGET_FACTOR`] times its time
+/// budget.
+async fn drive_tag(mut parse: ResumableParse, tcx: &TagContext) -> (TResult<ParseResult>, ResumableParse) {
+    let deadline = tokio::time::Instant::now() + tcx.time_budget * WALL_CLOCK_BUDGET_FACTOR;
+
+    loop {
+        let step;
+        (parse, step) = tokio::task::spawn_blocking(move || {
+            let step = parse.step();
+            (parse, step)
+        })
+        .await
+        .expect("Tag task panicked");
+
+        match step {
+            Step::Done(result) => return (result, parse),
+            Step::Suspended(_) => {
+                if tokio::time::timeout_at(deadline, parse.resume(tcx)).await.is_err() {
+                    return (Err(parse.timeout()), parse);
+                }
+            },
+        }
+    }
+}
+
+/// Converts the result of a tag into the message to respond with
+pub fn tag_response(result: ParseResult) -> anyhow::Result<MessageBuilder> {
+    let embeds = match result.embed {
+        Some(embed) => vec![tag_embed(embed)?],
+        None => Vec::new(),
+    };
+
+    Ok(MessageBuilder {
+        content: Some(result.output),
+        attachments: result
+            .attachments
+            .into_iter()
+            .map(|attachment| Attachment {
+                name: attachment.name.into_boxed_str(),
+                data: attachment.data,
+            })
+            .collect(),
+        embeds,
+        components: None,
+        component_ctxt: None,
+    })
+}
+
+fn tag_embed(embed: assyst_tag::output::Embed) -> anyhow::Result<Embed> {
+    let mut builder = EmbedBuilder::new();
+
+    if let Some(title) = embed.title {
+        builder = builder.title(title);
+    }
+    if let Some(description) = embed.description {
+        builder = builder.description(description);
+    }
+    if let Some(colour) = embed.colour {
+        builder = builder.color(colour);
+    }
+    if let Some(url) = embed.image {
+        builder = builder.image(ImageSource::url(url).context("The tag embed has an invalid image URL.")?);
+    }
+    for field in embed.fields {
+        let mut field_builder = EmbedFieldBuilder::new(field.name, field.value);
+        if field.inline {
+            field_builder = field_builder.inline();
+        }
+        builder = builder.field(field_builder);
+    }
+    if let Some(footer) = embed.footer {
+        builder = builder.footer(EmbedFooterBuilder::new(footer));
+    }
+
+    Ok(builder
+        .validate()
+        .context("The tag produced an invalid embed.")?
+        .build())
+}
+
+/// Formats a page of the revisions of a tag, newest first
+fn format_history_page(
+    tag_name: &str,
+    revisions: &[TagRevision],
+    page: u64,
+    pages: u64,
+    count: u64,
+    calling_prefix: &str,
+) -> String {
+    let mut message = format!(
+        "🗒️ **History of tag {}**\nView the changes since a revision by running `{calling_prefix}t diff {tag_name} <revision>`\n\n",
+        tag_name.codestring()
+    );
+
+    for revision in revisions {
+        message += &format!(
+            "{}. {} by <@{}> {}\n",
+            revision.revision,
+            revision.kind,
+            revision.author,
+            format_discord_timestamp(revision.created_at as u64)
+        );
+    }
+
+    message += &format!(
+        "\nShowing {} revisions (page {page}/{pages}) ({count} total revisions)",
+        revisions.len()
+    );
+
+    message
+}
+
 struct TagContext {
-    tokio: Handle,
     message: Option<Message>,
     assyst: ThreadSafeAssyst,
     guild_id: u64,
     channel_id: u64,
     author: twilight_model::user::User,
+    /// The name of the invoked tag, used to scope persistent variables
+    tag_name: String,
+    time_budget: Duration,
 }
 
 impl TagContext {
+    /// The context of a tag run by the invocation of `ctxt`, which must be in a guild
+    async fn new(ctxt: &CommandCtxt<'_>, tag_name: String) -> anyhow::Result<Self> {
+        let Some(guild_id) = ctxt.data.guild_id else {
+            bail!("Tags can only be used in guilds.")
+        };
+
+        let assyst = ctxt.assyst().clone();
+        let time_budget = guild_time_budget(&assyst, guild_id.get()).await?;
+
+        Ok(Self {
+            message: ctxt.data.message.cloned(),
+            assyst,
+            guild_id: guild_id.get(),
+            channel_id: ctxt.data.channel_id.get(),
+            author: ctxt.data.author.clone(),
+            tag_name,
+            time_budget,
+        })
+    }
+
     fn guild_id(&self) -> u64 {
         self.guild_id
     }
+
+    fn storage_tag_name(&self, scope: StorageScope) -> &str {
+        match scope {
+            StorageScope::Tag => &self.tag_name,
+            StorageScope::Guild => GUILD_SCOPE,
+        }
+    }
 }
 
-impl assyst_tag::Context for TagContext {
-    fn execute_javascript(
+impl assyst_tag::AsyncContext for TagContext {
+    async fn execute_javascript(
         &self,
         code: &str,
         args: Vec<String>,
     ) -> anyhow::Result<assyst_common::eval::FakeEvalImageResponse> {
-        self.tokio.block_on(fake_eval(
-            &self.assyst.reqwest_client,
-            code.into(),
-            true,
-            self.message.as_ref(),
-            args,
-        ))
+        eval_tag_javascript(&self.assyst.reqwest_client, code, self.message.as_ref(), args).await
     }
 
-    fn get_last_attachment(&self) -> anyhow::Result<String> {
-        let ImageUrl(attachment) = self.tokio.block_on(ImageUrl::from_channel_history(
-            &self.assyst,
-            Id::<ChannelMarker>::new(self.channel_id),
-        ))?;
+    async fn get_last_attachment(&self) -> anyhow::Result<String> {
+        let ImageUrl(attachment) =
+            ImageUrl::from_channel_history(&self.assyst, Id::<ChannelMarker>::new(self.channel_id)).await?;
         Ok(attachment)
     }
 
-    fn get_avatar(&self, user_id: Option<u64>) -> anyhow::Result<String> {
+    async fn get_avatar(&self, user_id: Option<u64>) -> anyhow::Result<String> {
         let user_id = user_id.unwrap_or(self.author.id.get());
 
-        self.tokio.block_on(async {
-            let user = self.assyst.http_client.user(Id::new(user_id)).await?;
-            ensure!(user.status().get() != 404, "user not found");
+        let user = self.assyst.http_client.user(Id::new(user_id)).await?;
+        ensure!(user.status().get() != 404, "user not found");
 
-            let user = user.model().await?;
+        let user = user.model().await?;
 
-            Ok(get_avatar_url(&user))
-        })
+        Ok(get_avatar_url(&user))
     }
 
-    fn download(&self, url: &str) -> anyhow::Result<String> {
-        self.tokio
-            .block_on(download_content(
-                &self.assyst.reqwest_client,
-                url,
-                ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
-                true,
-            ))
-            .map(string_from_likely_utf8)
-            .map_err(Into::into)
+    async fn download(&self, url: &str) -> anyhow::Result<String> {
+        download_content(
+            &self.assyst.reqwest_client,
+            url,
+            ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
+            true,
+        )
+        .await
+        .map(string_from_likely_utf8)
+        .map_err(Into::into)
     }
 
-    fn channel_id(&self) -> anyhow::Result<u64> {
+    async fn channel_id(&self) -> anyhow::Result<u64> {
         Ok(self.channel_id)
     }
 
-    fn guild_id(&self) -> anyhow::Result<u64> {
+    async fn guild_id(&self) -> anyhow::Result<u64> {
         Ok(TagContext::guild_id(self))
     }
 
-    fn user_id(&self) -> anyhow::Result<u64> {
+    async fn user_id(&self) -> anyhow::Result<u64> {
         Ok(self.author.id.get())
     }
 
-    fn user_tag(&self, id: Option<u64>) -> anyhow::Result<String> {
+    async fn user_tag(&self, id: Option<u64>) -> anyhow::Result<String> {
         if let Some(id) = id {
-            self.tokio.block_on(async {
-                let user = self.assyst.http_client.user(Id::new(id)).await?;
-                ensure!(user.status().get() != 404, "user not found");
+            let user = self.assyst.http_client.user(Id::new(id)).await?;
+            ensure!(user.status().get() != 404, "user not found");
 
-                Ok(format_tag(&user.model().await?))
-            })
+            Ok(format_tag(&user.model().await?))
         } else {
             Ok(format_tag(&self.author))
         }
     }
 
-    fn get_tag_contents(&self, tag: &str) -> anyhow::Result<String> {
-        let tag = self
-            .tokio
-            .block_on(async { Tag::get(&self.assyst.database_handler, self.guild_id() as i64, tag).await });
+    async fn get_tag_contents(&self, tag: &str) -> anyhow::Result<String> {
+        let tag = Tag::get(&self.assyst.database_handler, self.guild_id() as i64, tag).await;
 
         match tag {
             Ok(Some(Tag { data, .. })) => Ok(data),
@@ -1115,6 +1492,152 @@ impl assyst_tag::Context for TagContext {
             Err(e) => Err(e),
         }
     }
+
+    async fn get_persistent(&self, scope: StorageScope, key: &str) -> anyhow::Result<Option<String>> {
+        let variable = TagVariable::get(
+            &self.assyst.database_handler,
+            self.guild_id() as i64,
+            self.storage_tag_name(scope),
+            key,
+        )
+        .await?;
+
+        Ok(variable.map(|v| v.value))
+    }
+
+    async fn set_persistent(&self, scope: StorageScope, key: &str, value: &str) -> anyhow::Result<bool> {
+        let variable = TagVariable {
+            guild_id: self.guild_id() as i64,
+            tag_name: self.storage_tag_name(scope).to_owned(),
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+
+        variable
+            .set(&self.assyst.database_handler, MAX_PERSISTENT_VARIABLES as i64)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn delete_persistent(&self, scope: StorageScope, key: &str) -> anyhow::Result<()> {
+        TagVariable::delete(
+            &self.assyst.database_handler,
+            self.guild_id() as i64,
+            self.storage_tag_name(scope),
+            key,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn run_flux(&self, operation: &str, args: Vec<String>, input_url: &str) -> anyhow::Result<Vec<u8>> {
+        let option_names =
+            tag_operation_options(operation).with_context(|| format!("unknown image operation '{operation}'"))?;
+        let options = option_names
+            .iter()
+            .zip(args)
+            .map(|(name, value)| ((*name).to_owned(), value))
+            .collect::<HashMap<_, _>>();
+
+        let input = download_content(
+            &self.assyst.reqwest_client,
+            input_url,
+            ABSOLUTE_INPUT_FILE_SIZE_LIMIT_BYTES,
+            true,
+        )
+        .await?;
+
+        let limits = self
+            .assyst
+            .flux_handler
+            .get_request_limits(self.author.id.get(), Some(self.guild_id()))
+            .await?;
+
+        let mut request = FluxRequest::new_with_input_and_limits(input, &limits);
+        request.operation(operation.to_owned(), options);
+        request.output();
+
+        // waiting for flux does not count towards the time budget, but a single image operation
+        // shouldn't take longer than the tag may spend parsing
+        self.assyst
+            .flux_handler
+            .run_flux(request, limits.time.min(self.time_budget))
+            .await
+    }
+
+    async fn guild_name(&self) -> anyhow::Result<String> {
+        let guild = self.assyst.rest_cache_handler.get_guild_info(self.guild_id()).await?;
+
+        Ok(guild.name)
+    }
+
+    async fn guild_member_count(&self) -> anyhow::Result<u64> {
+        let guild = self.assyst.rest_cache_handler.get_guild_info(self.guild_id()).await?;
+
+        guild.member_count.context("Member count is not available")
+    }
+
+    async fn channel_name(&self) -> anyhow::Result<String> {
+        let channel = self.assyst.rest_cache_handler.get_channel_info(self.channel_id).await?;
+
+        channel.name.context("Channel has no name")
+    }
+
+    async fn channel_topic(&self) -> anyhow::Result<Option<String>> {
+        let channel = self.assyst.rest_cache_handler.get_channel_info(self.channel_id).await?;
+
+        Ok(channel.topic)
+    }
+
+    async fn nickname(&self) -> anyhow::Result<Option<String>> {
+        let member = self
+            .assyst
+            .rest_cache_handler
+            .get_guild_member(self.guild_id(), self.author.id.get())
+            .await?;
+
+        Ok(member.nickname)
+    }
+
+    async fn role_names(&self) -> anyhow::Result<Vec<String>> {
+        let member = self
+            .assyst
+            .rest_cache_handler
+            .get_guild_member(self.guild_id(), self.author.id.get())
+            .await?;
+        let mut roles = self.assyst.rest_cache_handler.get_guild_roles(self.guild_id()).await?;
+
+        roles.retain(|role| member.roles.contains(&role.id));
+        roles.sort_by(|a, b| b.position.cmp(&a.position));
+
+        Ok(roles.into_iter().map(|role| role.name).collect())
+    }
+
+    async fn joined_at(&self) -> anyhow::Result<i64> {
+        let member = self
+            .assyst
+            .rest_cache_handler
+            .get_guild_member(self.guild_id(), self.author.id.get())
+            .await?;
+
+        member.joined_at.context("Join date is not available")
+    }
+
+    async fn replied_message_content(&self) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .message
+            .as_ref()
+            .and_then(|message| message.referenced_message.as_ref())
+            .map(|message| message.content.clone()))
+    }
+
+    async fn message_id(&self) -> anyhow::Result<u64> {
+        self.message
+            .as_ref()
+            .map(|message| message.id.get())
+            .context("Tag was not invoked by a message")
+    }
 }
 
 define_commandgroup! {
@@ -1136,8 +1659,40 @@ define_commandgroup! {
         "search" => search,
         "backup" => backup,
         "copy" => copy,
-        "paste" => paste
+        "paste" => paste,
+        "debug" => debug,
+        "timeout" => timeout,
+        "promote" => promote,
+        "demote" => demote,
+        "history" => history,
+        "diff" => diff,
+        "rollback" => rollback,
+        "transfer" => transfer,
+        "editors" => editors,
+        "addeditor" => addeditor,
+        "removeeditor" => removeeditor,
+        "alias" => alias,
+        "unalias" => unalias,
+        "top" => top,
+        "unused" => unused,
+        "import" => import,
+        "docs" => docs
     ],
-    default_interaction_subcommand: "run",
+    default_interaction_subcommand: TAG_DEFAULT_INTERACTION_SUBCOMMAND,
     default: default
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_names() {
+        assert!(ensure_valid_tag_name("hello").is_ok());
+        assert!(ensure_valid_tag_name("").is_err());
+        assert!(ensure_valid_tag_name("create").is_err());
+        for name in ["a b", "a\tb", "a\nb", "a\u{3000}b"] {
+            assert!(ensure_valid_tag_name(name).is_err(), "{name:?} was accepted");
+        }
+    }
+}
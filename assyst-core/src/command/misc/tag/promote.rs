@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_command::TagCommand;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+
+use crate::command::arguments::{Word, WordAutocomplete};
+use crate::command::registry::find_command_by_name;
+use crate::command::{Availability, Category, CommandCtxt};
+use crate::tag_commands::{
+    is_valid_command_name, sync_guild_commands, MAX_TAG_COMMANDS_PER_GUILD, MAX_TAG_COMMAND_ARGUMENTS,
+};
+
+#[command(
+    description = "promote a tag to a command of this server, which also registers it as a slash command",
+    cooldown = Duration::from_secs(10),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "[tag name] <argument names...>",
+    examples = ["hello", "weather city"],
+    guild_only = true
+)]
+pub async fn promote(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] name: WordAutocomplete,
+    arguments: Option<Vec<Word>>,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be promoted in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    // aliases resolve to their tag, which is what gets promoted
+    let name = Tag::get(handler, guild_id.get() as i64, &name.0.to_ascii_lowercase())
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?
+        .name;
+
+    ensure!(
+        is_valid_command_name(&name),
+        "Only tags whose names are at most 32 letters, digits, dashes and underscores long can be promoted."
+    );
+    ensure!(
+        find_command_by_name(&name).is_none(),
+        "There already is a command called {}.",
+        name.codestring()
+    );
+
+    let arguments = arguments
+        .unwrap_or_default()
+        .into_iter()
+        .map(|Word(argument)| argument.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    ensure!(
+        arguments.len() <= MAX_TAG_COMMAND_ARGUMENTS,
+        "Tag commands cannot have more than {MAX_TAG_COMMAND_ARGUMENTS} arguments."
+    );
+    for (index, argument) in arguments.iter().enumerate() {
+        ensure!(
+            is_valid_command_name(argument),
+            "Argument names can only be at most 32 letters, digits, dashes and underscores long."
+        );
+        ensure!(
+            !arguments[..index].contains(argument),
+            "The argument {} is declared twice.",
+            argument.codestring()
+        );
+    }
+
+    let commands = TagCommand::list_in_guild(handler, guild_id.get() as i64)
+        .await
+        .context("Failed to fetch tag commands")?;
+    ensure!(
+        commands.len() < MAX_TAG_COMMANDS_PER_GUILD || commands.iter().any(|command| command.tag_name == name),
+        "This server already has the maximum of {MAX_TAG_COMMANDS_PER_GUILD} tag commands."
+    );
+
+    TagCommand {
+        guild_id: guild_id.get() as i64,
+        tag_name: name.clone(),
+        arguments,
+    }
+    .set(handler)
+    .await
+    .context("Failed to promote tag")?;
+
+    sync_guild_commands(ctxt.assyst(), guild_id.get())
+        .await
+        .context("Failed to register the slash command of the tag")?;
+
+    ctxt.reply(format!(
+        "Tag {} can now be run as {} and {}.",
+        name.codestring(),
+        format!("{}{name}", ctxt.data.calling_prefix).codestring(),
+        format!("/{name}").codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "turn a promoted tag back into a regular tag",
+    cooldown = Duration::from_secs(10),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "[tag name]",
+    examples = ["hello"],
+    guild_only = true
+)]
+pub async fn demote(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] name: WordAutocomplete,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be demoted in guilds.")
+    };
+    let name = name.0.to_ascii_lowercase();
+
+    ensure!(
+        TagCommand::delete(&ctxt.assyst().database_handler, guild_id.get() as i64, &name)
+            .await
+            .context("Failed to demote tag")?,
+        "This tag is not promoted to a command."
+    );
+
+    sync_guild_commands(ctxt.assyst(), guild_id.get())
+        .await
+        .context("Failed to unregister the slash command of the tag")?;
+
+    ctxt.reply(format!(
+        "Tag {} is no longer a command of this server.",
+        name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
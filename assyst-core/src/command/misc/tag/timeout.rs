@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_database::model::tag_time_budget::TagTimeBudget;
+use assyst_proc_macro::command;
+use assyst_tag::parser::limits::{DEFAULT_TIME_BUDGET, MAX_TIME_BUDGET, MIN_TIME_BUDGET};
+
+use super::guild_time_budget;
+use crate::command::arguments::Word;
+use crate::command::{Availability, Category, CommandCtxt};
+
+#[command(
+    description = "get or set how long tags in this server may run for",
+    cooldown = Duration::from_secs(2),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "<milliseconds|reset>",
+    examples = ["", "2000", "reset"],
+    guild_only = true
+)]
+pub async fn timeout(ctxt: CommandCtxt<'_>, milliseconds: Option<Word>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag timeouts can only be configured in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+
+    let Some(Word(milliseconds)) = milliseconds else {
+        let budget = guild_time_budget(ctxt.assyst(), guild_id.get()).await?;
+        ctxt.reply(format!(
+            "Tags in this server can run for {} milliseconds.",
+            budget.as_millis()
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    if milliseconds.eq_ignore_ascii_case("reset") {
+        TagTimeBudget::delete(handler, guild_id.get())
+            .await
+            .context("Failed to reset tag time budget")?;
+
+        ctxt.reply(format!(
+            "Tags in this server can now run for {} milliseconds.",
+            DEFAULT_TIME_BUDGET.as_millis()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let milliseconds = milliseconds
+        .parse::<u64>()
+        .context("The timeout must be a number of milliseconds, or \"reset\".")?;
+    ensure!(
+        (MIN_TIME_BUDGET..=MAX_TIME_BUDGET).contains(&Duration::from_millis(milliseconds)),
+        "The timeout must be between {} and {} milliseconds.",
+        MIN_TIME_BUDGET.as_millis(),
+        MAX_TIME_BUDGET.as_millis()
+    );
+
+    TagTimeBudget { milliseconds }
+        .set(handler, guild_id.get())
+        .await
+        .context("Failed to set tag time budget")?;
+
+    ctxt.reply(format!(
+        "Tags in this server can now run for {milliseconds} milliseconds."
+    ))
+    .await?;
+
+    Ok(())
+}
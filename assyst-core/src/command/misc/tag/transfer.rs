@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_common::util::unix_timestamp;
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_editor::TagEditor;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+use twilight_model::channel::message::component::ButtonStyle;
+use twilight_model::channel::message::Component;
+use twilight_model::id::marker::UserMarker;
+use twilight_model::id::Id;
+
+use super::can_manage_tag;
+use crate::command::arguments::{User, WordAutocomplete};
+use crate::command::componentctxt::{
+    button_new, respond_update_text_without_components, ComponentCtxt, ComponentInteractionData, ComponentMetadata,
+};
+use crate::command::messagebuilder::MessageBuilder;
+use crate::command::{Availability, Category, CommandCtxt};
+
+/// Confirmation of a tag transfer, which is only carried out once the owner confirms it
+#[derive(Clone, Debug)]
+pub struct TagTransferComponentMetadata {
+    pub confirm_cid: String,
+    pub cancel_cid: String,
+    pub invocating_user_id: Id<UserMarker>,
+    pub guild_id: u64,
+    pub tag_name: String,
+    pub author: u64,
+    pub new_author: u64,
+}
+
+impl TagTransferComponentMetadata {
+    pub async fn component_callback(&mut self, data: &ComponentInteractionData) -> anyhow::Result<()> {
+        if data.invocation_user_id != self.invocating_user_id {
+            bail!("This command was not ran by you.");
+        }
+
+        if data.custom_id == self.cancel_cid {
+            respond_update_text_without_components(
+                data.assyst.clone(),
+                data.interaction_id,
+                &data.interaction_token,
+                "Tag transfer cancelled.",
+            )
+            .await?;
+
+            return Ok(());
+        }
+
+        let handler = &data.assyst.database_handler;
+
+        let success = Tag::transfer(
+            handler,
+            self.guild_id as i64,
+            &self.tag_name,
+            self.author as i64,
+            self.new_author as i64,
+        )
+        .await
+        .context("Failed to transfer tag")?;
+        ensure!(
+            success,
+            "Failed to transfer that tag. Does it still exist, and is it still owned by the same user?"
+        );
+
+        // the new owner no longer needs to be an editor
+        TagEditor::remove(handler, self.guild_id as i64, &self.tag_name, self.new_author as i64)
+            .await
+            .context("Failed to update tag editors")?;
+
+        respond_update_text_without_components(
+            data.assyst.clone(),
+            data.interaction_id,
+            &data.interaction_token,
+            &format!(
+                "Transferred tag {} to <@{}>.",
+                self.tag_name.codestring(),
+                self.new_author
+            ),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[command(
+    description = "transfer a tag that you own to another user (server managers can transfer any tag in the server)",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "[name] [user]",
+    examples = ["test @jacher"],
+    guild_only = true
+)]
+pub async fn transfer(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete_for_user"] name: WordAutocomplete,
+    user: User,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tags can only be transferred in guilds.")
+    };
+
+    let tag = Tag::get(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        &name.0.to_ascii_lowercase(),
+    )
+    .await
+    .context("Failed to fetch tag")?
+    .context("Tag not found in this server.")?;
+
+    ensure!(
+        can_manage_tag(&ctxt, &tag).await?,
+        "You can only transfer tags that you own."
+    );
+    ensure!(!user.0.bot, "Tags cannot be transferred to bots.");
+    ensure!(tag.author != user.0.id.get() as i64, "That user already owns this tag.");
+
+    let timestamp = unix_timestamp();
+    let confirm = format!("tag_transfer_confirm-{timestamp}");
+    let cancel = format!("tag_transfer_cancel-{timestamp}");
+
+    ctxt.reply(MessageBuilder {
+        content: Some(format!(
+            "Transfer tag {} from <@{}> to <@{}>? Only the new owner will be able to delete it.",
+            tag.name.codestring(),
+            tag.author,
+            user.0.id
+        )),
+        attachments: Vec::new(),
+        embeds: Vec::new(),
+        components: Some(vec![
+            Component::Button(button_new(&confirm, "Transfer", ButtonStyle::Danger)),
+            Component::Button(button_new(&cancel, "Cancel", ButtonStyle::Secondary)),
+        ]),
+        component_ctxt: Some((
+            vec![confirm.clone(), cancel.clone()],
+            ComponentCtxt::new(
+                ctxt.assyst().clone(),
+                ComponentMetadata::TagTransfer(TagTransferComponentMetadata {
+                    confirm_cid: confirm,
+                    cancel_cid: cancel,
+                    invocating_user_id: ctxt.data.author.id,
+                    guild_id: guild_id.get(),
+                    tag_name: tag.name,
+                    author: tag.author as u64,
+                    new_author: user.0.id.get(),
+                }),
+            ),
+        )),
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_common::util::discord::format_discord_timestamp;
+use assyst_common::util::unix_timestamp;
+use assyst_database::model::tag_usage::TagUsage;
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+
+use super::DEFAULT_LIST_COUNT;
+use crate::command::{Availability, Category, CommandCtxt};
+
+const DEFAULT_UNUSED_DAYS: u64 = 30;
+const MILLISECONDS_PER_DAY: u64 = 1000 * 60 * 60 * 24;
+
+/// The unix timestamp in milliseconds `days` days before `now`, which is also in milliseconds
+fn unused_cutoff(now: u64, days: u64) -> i64 {
+    now.saturating_sub(days.saturating_mul(MILLISECONDS_PER_DAY)) as i64
+}
+
+#[command(
+    description = "list the most used tags in this server",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    examples = [""],
+    guild_only = true
+)]
+pub async fn top(ctxt: CommandCtxt<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag leaderboards can only be fetched in guilds.")
+    };
+
+    let top = TagUsage::get_top_in_guild(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        DEFAULT_LIST_COUNT,
+    )
+    .await
+    .context("Failed to fetch tag usage statistics")?;
+    ensure!(!top.is_empty(), "No tags have been used in this server yet.");
+
+    let mut message = "🗒️ **Most used tags in this server**\n\n".to_owned();
+    for (position, usage) in top.iter().enumerate() {
+        writeln!(
+            message,
+            "{}. {} ({} uses)",
+            position + 1,
+            usage.tag_name.codestring(),
+            usage.uses
+        )?;
+    }
+
+    ctxt.reply(message).await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "list tags in this server that have not been used for some days, to help cleaning up",
+    cooldown = Duration::from_secs(5),
+    access = Availability::Public,
+    category = Category::Misc,
+    usage = "<days>",
+    examples = ["", "90"],
+    guild_only = true
+)]
+pub async fn unused(ctxt: CommandCtxt<'_>, days: Option<u64>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Unused tags can only be listed in guilds.")
+    };
+    let days = days.unwrap_or(DEFAULT_UNUSED_DAYS);
+    ensure!(days > 0, "The number of days must be at least 1.");
+
+    let before = unused_cutoff(unix_timestamp(), days);
+    let unused = TagUsage::get_unused_in_guild(
+        &ctxt.assyst().database_handler,
+        guild_id.get() as i64,
+        before,
+        DEFAULT_LIST_COUNT,
+    )
+    .await
+    .context("Failed to fetch tag usage statistics")?;
+    ensure!(
+        !unused.is_empty(),
+        "Every tag in this server was used or created in the last {days} days."
+    );
+
+    let mut message = format!("🗒️ **Tags not used in the last {days} days**\n\n");
+    for usage in unused {
+        if usage.last_used == 0 {
+            writeln!(message, "{} (never used)", usage.tag_name.codestring())?;
+        } else {
+            writeln!(
+                message,
+                "{} (last used {})",
+                usage.tag_name.codestring(),
+                format_discord_timestamp(usage.last_used as u64)
+            )?;
+        }
+    }
+
+    ctxt.reply(message).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unused_cutoff_is_in_milliseconds() {
+        let now = 1_700_000_000_000;
+
+        assert_eq!(unused_cutoff(now, 1), now as i64 - 86_400_000);
+        assert_eq!(unused_cutoff(now, 30), now as i64 - 30 * 86_400_000);
+    }
+
+    #[test]
+    fn unused_cutoff_saturates() {
+        assert_eq!(unused_cutoff(1000, 1), 0);
+        assert_eq!(unused_cutoff(u64::MAX / 2, u64::MAX), 0);
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use assyst_common::util::discord::{channel_mention_to_id, ensure_same_guild};
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_trigger::{TagTrigger, TriggerKind};
+use assyst_proc_macro::command;
+use assyst_string_fmt::Markdown;
+
+use crate::command::arguments::{RestNoFlags, Word, WordAutocomplete};
+use crate::command::{Availability, Category, CommandCtxt};
+use crate::define_commandgroup;
+use crate::tag_triggers::{
+    compile_regex, DEFAULT_TRIGGER_COOLDOWN_SECONDS, MAX_PATTERN_LENGTH, MAX_TRIGGERS_PER_GUILD,
+    MAX_TRIGGER_COOLDOWN_SECONDS,
+};
+
+#[command(
+    description = "run a tag automatically when a message matches a word, prefix or regex",
+    aliases = ["create"],
+    cooldown = Duration::from_secs(5),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "[tag name] [word|prefix|regex] [pattern]",
+    examples = ["hello word hello", "weather prefix !weather", "ping regex ^ping (\\d+)$"],
+    guild_only = true
+)]
+pub async fn add(
+    ctxt: CommandCtxt<'_>,
+    #[autocomplete = "crate::command::misc::tag::tag_names_autocomplete"] tag_name: WordAutocomplete,
+    kind: Word,
+    pattern: RestNoFlags,
+) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag triggers can only be created in guilds.")
+    };
+    let handler = &ctxt.assyst().database_handler;
+    let tag_name = tag_name.0.to_ascii_lowercase();
+
+    let Some(kind) = TriggerKind::from_name(&kind.0.to_ascii_lowercase()) else {
+        bail!("The trigger kind must be one of `word`, `prefix` or `regex`.")
+    };
+
+    let pattern = pattern.0.trim();
+    ensure!(!pattern.is_empty(), "The trigger pattern cannot be empty.");
+    ensure!(
+        pattern.len() <= MAX_PATTERN_LENGTH,
+        "Trigger patterns cannot be longer than {MAX_PATTERN_LENGTH} characters."
+    );
+    if kind == TriggerKind::Regex {
+        compile_regex(pattern).context("Invalid regex")?;
+    }
+
+    Tag::get(handler, guild_id.get() as i64, &tag_name)
+        .await
+        .context("Failed to fetch tag")?
+        .context("Tag not found in this server.")?;
+
+    let triggers = TagTrigger::list_in_guild(handler, guild_id.get() as i64)
+        .await
+        .context("Failed to fetch tag triggers")?;
+    ensure!(
+        triggers.len() < MAX_TRIGGERS_PER_GUILD,
+        "This server already has the maximum of {MAX_TRIGGERS_PER_GUILD} tag triggers."
+    );
+
+    let id = TagTrigger {
+        id: 0,
+        guild_id: guild_id.get() as i64,
+        tag_name: tag_name.clone(),
+        kind: kind.as_str().to_owned(),
+        pattern: pattern.to_owned(),
+        channels: Vec::new(),
+        cooldown_seconds: DEFAULT_TRIGGER_COOLDOWN_SECONDS,
+    }
+    .insert(handler)
+    .await
+    .context("Failed to create tag trigger")?;
+
+    ctxt.reply(format!(
+        "Created trigger {} for tag {}. It runs in every channel, with a cooldown of {DEFAULT_TRIGGER_COOLDOWN_SECONDS} seconds.",
+        id.to_string().codestring(),
+        tag_name.codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "delete a tag trigger",
+    aliases = ["delete"],
+    cooldown = Duration::from_secs(2),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "[trigger id]",
+    examples = ["12"],
+    guild_only = true
+)]
+pub async fn remove(ctxt: CommandCtxt<'_>, id: u64) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag triggers can only be deleted in guilds.")
+    };
+
+    ensure!(
+        TagTrigger::remove(&ctxt.assyst().database_handler, guild_id.get() as i64, id as i32)
+            .await
+            .context("Failed to delete tag trigger")?,
+        "Failed to delete this tag trigger - does it exist?"
+    );
+
+    ctxt.reply(format!("Deleted trigger {}.", id.to_string().codestring()))
+        .await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "list the tag triggers of this server",
+    cooldown = Duration::from_secs(2),
+    access = Availability::Public,
+    category = Category::Misc,
+    examples = [""],
+    guild_only = true
+)]
+pub async fn list(ctxt: CommandCtxt<'_>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag triggers can only be listed in guilds.")
+    };
+
+    let triggers = TagTrigger::list_in_guild(&ctxt.assyst().database_handler, guild_id.get() as i64)
+        .await
+        .context("Failed to fetch tag triggers")?;
+
+    if triggers.is_empty() {
+        ctxt.reply("This server has no tag triggers.").await?;
+        return Ok(());
+    }
+
+    let mut message = String::new();
+    for trigger in triggers {
+        let channels = if trigger.channels.is_empty() {
+            "all channels".to_owned()
+        } else {
+            trigger
+                .channels
+                .iter()
+                .map(|id| format!("<#{id}>"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        writeln!(
+            message,
+            "{}: {} {} runs {} in {channels} (cooldown: {}s)",
+            trigger.id.to_string().codestring(),
+            trigger.kind,
+            trigger.pattern.codestring(),
+            trigger.tag_name.codestring(),
+            trigger.cooldown_seconds
+        )?;
+    }
+
+    ctxt.reply(message).await?;
+
+    Ok(())
+}
+
+#[command(
+    description = "limit a tag trigger to some channels, or allow it everywhere if none are given",
+    cooldown = Duration::from_secs(5),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "[trigger id] <channels...>",
+    examples = ["12 #general #bot-commands", "12"],
+    guild_only = true
+)]
+pub async fn channels(ctxt: CommandCtxt<'_>, id: u64, channels: Option<Vec<Word>>) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag triggers can only be configured in guilds.")
+    };
+
+    let mut channel_ids = Vec::new();
+    for Word(channel) in channels.unwrap_or_default() {
+        let channel_id =
+            channel_mention_to_id(&channel).with_context(|| format!("{} is not a channel.", channel.codestring()))?;
+        ensure_same_guild(&ctxt.assyst().http_client, channel_id, guild_id.get()).await?;
+        channel_ids.push(channel_id as i64);
+    }
+
+    ensure!(
+        TagTrigger::set_channels(
+            &ctxt.assyst().database_handler,
+            guild_id.get() as i64,
+            id as i32,
+            &channel_ids
+        )
+        .await
+        .context("Failed to update tag trigger")?,
+        "Failed to update this tag trigger - does it exist?"
+    );
+
+    if channel_ids.is_empty() {
+        ctxt.reply(format!(
+            "Trigger {} now runs in every channel.",
+            id.to_string().codestring()
+        ))
+        .await?;
+    } else {
+        ctxt.reply(format!(
+            "Trigger {} now only runs in {} channel(s).",
+            id.to_string().codestring(),
+            channel_ids.len()
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[command(
+    description = "set how many seconds must pass before a tag trigger runs again",
+    cooldown = Duration::from_secs(2),
+    access = Availability::ServerManagers,
+    category = Category::Misc,
+    usage = "[trigger id] [seconds]",
+    examples = ["12 30"],
+    guild_only = true
+)]
+pub async fn cooldown(ctxt: CommandCtxt<'_>, id: u64, seconds: u64) -> anyhow::Result<()> {
+    let Some(guild_id) = ctxt.data.guild_id else {
+        bail!("Tag triggers can only be configured in guilds.")
+    };
+
+    ensure!(
+        seconds <= MAX_TRIGGER_COOLDOWN_SECONDS as u64,
+        "Trigger cooldowns cannot be longer than {MAX_TRIGGER_COOLDOWN_SECONDS} seconds."
+    );
+
+    ensure!(
+        TagTrigger::set_cooldown(
+            &ctxt.assyst().database_handler,
+            guild_id.get() as i64,
+            id as i32,
+            seconds as i32
+        )
+        .await
+        .context("Failed to update tag trigger")?,
+        "Failed to update this tag trigger - does it exist?"
+    );
+
+    ctxt.reply(format!(
+        "Trigger {} now has a cooldown of {seconds} seconds.",
+        id.to_string().codestring()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+define_commandgroup! {
+    name: trigger,
+    access: Availability::Public,
+    category: Category::Misc,
+    aliases: ["triggers", "autoresponder"],
+    cooldown: Duration::from_secs(2),
+    description: "run tags automatically when messages match a pattern",
+    usage: "[subcommand] <arguments...>",
+    guild_only: true,
+    commands: [
+        "add" => add,
+        "remove" => remove,
+        "list" => list,
+        "channels" => channels,
+        "cooldown" => cooldown
+    ]
+}
@@ -26,9 +26,9 @@
 //!   entry point (and the only relevant for the outside) is [`registry::find_command_by_name`],
 //!   which does the mapping mentioned above.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
-use std::str::SplitAsciiWhitespace;
 use std::time::{Duration, Instant};
 
 use assyst_common::config::CONFIG;
@@ -49,6 +49,7 @@ use twilight_util::builder::command::SubCommandBuilder;
 use self::errors::{ArgsExhausted, ExecutionError, MetadataCheckError};
 use self::messagebuilder::MessageBuilder;
 use self::source::Source;
+use self::tokenizer::{split_flags, RawMessageArgsIter};
 use super::gateway_handler::reply as gateway_reply;
 use crate::assyst::ThreadSafeAssyst;
 
@@ -65,6 +66,7 @@ pub mod misc;
 pub mod registry;
 pub mod services;
 pub mod source;
+pub mod tokenizer;
 
 /// Defines who can use a command in a server.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -286,7 +288,6 @@ pub struct CommandData<'a> {
     pub resolved_users: Option<Vec<User>>,
 }
 
-pub type RawMessageArgsIter<'a> = SplitAsciiWhitespace<'a>;
 pub type InteractionMessageArgs<'a> = HashMap<String, &'a CommandDataOption>;
 
 /// A parsing context. Parsing contexts can either be for raw message commands or interaction
@@ -340,38 +341,34 @@ pub type Label = Option<(String, String)>;
 impl<'a> ParseCtxt<'a, RawMessageArgsIter<'a>> {
     pub fn new(ctxt: CommandCtxt<'a>, args: &'a str) -> Self {
         Self {
-            args: args.split_ascii_whitespace(),
+            args: RawMessageArgsIter::new(args),
             cx: ctxt,
         }
     }
 
-    /// Eagerly takes a word.
+    /// Eagerly takes a word, which may be quoted to contain whitespace.
     /// If you want to "peek" or you aren't sure if you might want to undo this,
     /// consider using `commit_if_ok` or `fork` to try it in a subcontext.
-    pub fn next_word(&mut self, label: Label) -> Result<&'a str, ArgsExhausted> {
-        self.args.next().ok_or(ArgsExhausted(label))
+    pub fn next_word(&mut self, label: Label) -> Result<Cow<'a, str>, ArgsExhausted> {
+        self.args.next().map(|word| word.text).ok_or(ArgsExhausted(label))
     }
 
-    /// The rest of the message, excluding flags.
+    /// The rest of the message, excluding flags. See [`tokenizer::split_flags`].
     pub fn rest(&mut self, label: Label) -> Result<String, TagParseError> {
         let raw = self
             .args
             .remainder()
             .ok_or(TagParseError::ArgsExhausted(ArgsExhausted(label.clone())))?;
 
-        let (args, flags) = if let Some(idx) = raw.find("--") {
-            (&raw[..idx], &raw[idx..])
-        } else {
-            (raw, "")
-        };
+        let (args, flags) = split_flags(raw);
 
         if args.is_empty() {
             return Err(TagParseError::ArgsExhausted(ArgsExhausted(label)));
         }
 
-        self.args = flags.split_ascii_whitespace();
+        self.args = RawMessageArgsIter::new(flags);
 
-        Ok(args.to_owned())
+        Ok(args.into_owned())
     }
 
     pub fn rest_all(&self, _: Label) -> String {
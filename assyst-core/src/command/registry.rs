@@ -88,6 +88,7 @@ declare_commands!(
     misc::eval_command,
     misc::exec_command,
     misc::help::help_command,
+    misc::library::library_command,
     misc::info_command,
     misc::invite_command,
     misc::patronstatus_command,
@@ -98,6 +99,7 @@ declare_commands!(
     misc::stats::stats_command,
     misc::tag::tag_command,
     misc::topcommands_command,
+    misc::trigger::trigger_command,
     misc::url_command,
     services::burntext_command,
     services::cooltext::cooltext_command,
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::io::{Cursor, Write};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -6,101 +5,31 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use anyhow::Context;
 use assyst_common::config::CONFIG;
 use assyst_common::util::{filetype, format_duration, sanitise_filename};
-use assyst_proc_macro::command;
+use assyst_proc_macro::{command, Flags};
 use assyst_string_fmt::Markdown;
 use rand::{thread_rng, Rng};
 use tokio::sync::Mutex;
 use tokio::task::JoinSet;
 use tokio::time::{sleep, timeout};
-use twilight_util::builder::command::{BooleanBuilder, IntegerBuilder};
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
-use crate::command::arguments::{ParseArgument, Word};
-use crate::command::errors::TagParseError;
-use crate::command::flags::{flags_from_str, FlagDecode, FlagType};
+use crate::command::arguments::Word;
 use crate::command::messagebuilder::Attachment;
 use crate::command::{Availability, Category, CommandCtxt};
 //use crate::flag_parse_argument;
 use crate::rest::web_media_download::{download_web_media, get_youtube_playlist_entries, WebDownloadOpts};
-use crate::{int_arg_bool, int_arg_u64};
 
-#[derive(Default)]
+#[derive(Default, Flags)]
 pub struct DownloadFlags {
+    /// whether to download the media as an audio file
     pub audio: bool,
+    /// downloaded video quality
+    #[flag(default = 720, choices = [144, 240, 360, 480, 720, 1080])]
     pub quality: u64,
+    /// for playlist downloading, show detailed information
     pub verbose: bool,
 }
-impl FlagDecode for DownloadFlags {
-    fn from_str(input: &str) -> anyhow::Result<Self> {
-        let mut valid_flags = HashMap::new();
-        valid_flags.insert("quality", FlagType::WithValue);
-        valid_flags.insert("audio", FlagType::NoValue);
-        valid_flags.insert("verbose", FlagType::NoValue);
-
-        let raw_decode = flags_from_str(input, valid_flags)?;
-        let result = Self {
-            audio: raw_decode.contains_key("audio"),
-            quality: raw_decode
-                .get("quality")
-                .unwrap_or(&None)
-                .clone()
-                .unwrap_or("720".to_owned())
-                .parse()
-                .context("Provided quality is invalid")?,
-            verbose: raw_decode.contains_key("verbose"),
-        };
-
-        Ok(result)
-    }
-}
-impl ParseArgument for DownloadFlags {
-    fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
-        vec![
-            IntegerBuilder::new("quality", "downloaded video quality")
-                .required(false)
-                .choices(vec![
-                    ("144", 144),
-                    ("240", 240),
-                    ("360", 360),
-                    ("480", 480),
-                    ("720", 720),
-                    ("1080", 1080),
-                ])
-                .build(),
-            BooleanBuilder::new("audio", "whether to download the media as an audio file")
-                .required(false)
-                .build(),
-            BooleanBuilder::new("verbose", "for playlist downloading, show detailed information")
-                .required(false)
-                .build(),
-        ]
-    }
-
-    async fn parse_raw_message(
-        ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
-        label: crate::command::Label,
-    ) -> Result<Self, crate::command::errors::TagParseError> {
-        let args = ctxt.rest_all(label);
-        let parsed = Self::from_str(&args).map_err(TagParseError::FlagParseError)?;
-        Ok(parsed)
-    }
-
-    async fn parse_command_option(
-        ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
-        _: crate::command::Label,
-    ) -> Result<Self, TagParseError> {
-        let quality = int_arg_u64!(ctxt, "quality", 720);
-        let audio = int_arg_bool!(ctxt, "audio", false);
-        let verbose = int_arg_bool!(ctxt, "verbose", false);
-
-        Ok(Self {
-            audio,
-            quality,
-            verbose,
-        })
-    }
-}
 
 #[command(
     name = "download",
@@ -111,11 +40,7 @@ impl ParseArgument for DownloadFlags {
     category = Category::Services,
     usage = "[url] <flags>",
     examples = ["https://youtu.be/dQw4w9WgXcQ", "https://youtu.be/dQw4w9WgXcQ --audio", "https://youtu.be/dQw4w9WgXcQ --quality 480"],
-    send_processing = true,
-    flag_descriptions = [
-        ("audio", "Get content as MP3"),
-        ("quality [quality:144|240|360|480|720|1080|max]", "Set resolution of output"),
-    ]
+    send_processing = true
 )]
 pub async fn download(ctxt: CommandCtxt<'_>, url: Word, options: DownloadFlags) -> anyhow::Result<()> {
     let mut opts = WebDownloadOpts::from_download_flags(options, CONFIG.urls.clone().cobalt_api);
@@ -0,0 +1,386 @@
+//! Splitting of raw message arguments into words.
+//!
+//! Words are separated by ASCII whitespace. A word can contain whitespace if (part of) it is
+//! wrapped in double quotes, like `"two words"` or `a" b "c`. A backslash escapes a following `"`,
+//! `\`, `-` or whitespace character, so `\"` is a literal quote and `\--text` is not a flag.
+//! Backslashes before any other character are kept as they are, and a quote without a closing
+//! quote is a literal quote.
+//!
+//! A word starting with `--` is the start of the flags of a command (see [`split_flags`]), unless
+//! the `--` is quoted or escaped. A standalone `--` before the text of a command ends the text
+//! early: everything after it is text, even if it looks like a flag.
+//!
+//! The text of a command is left as it was written, unless it is a single quoted word like
+//! `"some text"`, whose quotes and escapes are removed.
+
+use std::borrow::Cow;
+
+/// A word of a raw message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    /// The word as it was written, including quotes and escapes
+    pub raw: &'a str,
+    /// The word with quotes and escapes removed
+    pub text: Cow<'a, str>,
+}
+impl Token<'_> {
+    /// Whether this word starts a flag, i.e. starts with an unquoted and unescaped `--` or `—`
+    /// (which some keyboards replace `--` with) followed by the name of the flag.
+    pub fn is_flag(&self) -> bool {
+        (self.raw.starts_with("--") && self.raw.len() > 2)
+            || (self.raw.starts_with('—') && self.raw.len() > '—'.len_utf8())
+    }
+}
+
+/// The words of a raw message. This is cheap to copy, which `ParseCtxt::fork` relies on.
+#[derive(Debug, Clone, Copy)]
+pub struct RawMessageArgsIter<'a> {
+    input: &'a str,
+}
+impl<'a> RawMessageArgsIter<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input }
+    }
+
+    /// The input that has not been consumed yet, without leading whitespace, or `None` if there
+    /// are no words left.
+    pub fn remainder(&self) -> Option<&'a str> {
+        let remainder = self.input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+        (!remainder.is_empty()).then_some(remainder)
+    }
+}
+impl<'a> Iterator for RawMessageArgsIter<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = self.remainder()?;
+        let end = word_end(input);
+        self.input = &input[end..];
+
+        let raw = &input[..end];
+        let text = if raw.contains(['"', '\\']) {
+            Cow::Owned(unescape(raw))
+        } else {
+            Cow::Borrowed(raw)
+        };
+
+        Some(Token { raw, text })
+    }
+}
+
+/// Splits the text of a command from its flags, returning the text with escaped flags unescaped
+/// and the flags. The text is otherwise left as it was written, except that text which is a single
+/// quoted word loses its quotes (see [`quoted_word`]). Text after a `--` terminator is always left
+/// as it was written.
+pub fn split_flags(input: &str) -> (Cow<'_, str>, &str) {
+    let mut words = RawMessageArgsIter::new(input);
+
+    if let Some(first) = words.next()
+        && first.raw == "--"
+    {
+        return (Cow::Borrowed(words.remainder().unwrap_or_default().trim_end()), "");
+    }
+
+    let mut text_end = input.len();
+    let mut escaped_flags = false;
+    for word in RawMessageArgsIter::new(input) {
+        if word.is_flag() {
+            text_end = offset_in(input, word.raw);
+            break;
+        }
+        escaped_flags |= word.raw.starts_with("\\--");
+    }
+
+    let text = input[..text_end].trim();
+    let text = match quoted_word(text) {
+        Some(word) => Cow::Owned(word),
+        None if escaped_flags => Cow::Owned(unescape_flags(text)),
+        None => Cow::Borrowed(text),
+    };
+
+    (text, &input[text_end..])
+}
+
+/// The text of a word that is quoted as a whole, like `"some text"` but not `a" b "c` or
+/// `"a" "b"`, without its quotes and escapes.
+fn quoted_word(text: &str) -> Option<String> {
+    let inner = text.strip_prefix('"')?;
+    (closing_quote(inner)? + 1 == inner.len()).then(|| unescape(text))
+}
+
+/// The byte offset of a substring in the string it was sliced from
+fn offset_in(input: &str, part: &str) -> usize {
+    part.as_ptr() as usize - input.as_ptr() as usize
+}
+
+fn is_escapable(c: char) -> bool {
+    matches!(c, '"' | '\\' | '-') || c.is_ascii_whitespace()
+}
+
+/// Finds the end of the word at the start of the input.
+fn word_end(input: &str) -> usize {
+    let mut chars = input.char_indices();
+    let mut quoted = false;
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if input[i + 1..].starts_with(is_escapable) => {
+                chars.next();
+            },
+            '"' if quoted => quoted = false,
+            '"' => quoted = closing_quote(&input[i + 1..]).is_some(),
+            c if c.is_ascii_whitespace() && !quoted => return i,
+            _ => {},
+        }
+    }
+
+    input.len()
+}
+
+/// Finds the unescaped quote that closes a quote opened right before the input.
+fn closing_quote(input: &str) -> Option<usize> {
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if input[i + 1..].starts_with(is_escapable) => {
+                chars.next();
+            },
+            '"' => return Some(i),
+            _ => {},
+        }
+    }
+
+    None
+}
+
+/// Removes the quotes and escapes of a single word.
+fn unescape(word: &str) -> String {
+    let mut out = String::with_capacity(word.len());
+    let mut chars = word.char_indices();
+    let mut quoted = false;
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if word[i + 1..].starts_with(is_escapable) => {
+                out.extend(chars.next().map(|(_, c)| c));
+            },
+            '"' if quoted => quoted = false,
+            '"' if closing_quote(&word[i + 1..]).is_some() => quoted = true,
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Removes the backslash of words starting with `\--`, leaving the rest of the text as it is.
+fn unescape_flags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last_end = 0;
+
+    for word in RawMessageArgsIter::new(text) {
+        if word.raw.starts_with("\\--") {
+            let start = offset_in(text, word.raw);
+            out.push_str(&text[last_end..start]);
+            last_end = start + 1;
+        }
+    }
+    out.push_str(&text[last_end..]);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::flags::{flags_from_str, FlagType};
+
+    fn words(input: &str) -> Vec<String> {
+        RawMessageArgsIter::new(input)
+            .map(|word| word.text.into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(words("  a  b\n\tc "), ["a", "b", "c"]);
+        assert_eq!(words("   "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn quotes_group_words() {
+        assert_eq!(words(r#"a "b c" d"#), ["a", "b c", "d"]);
+        assert_eq!(words(r#"x" y "z"#), ["x y z"]);
+        assert_eq!(words(r#""" a"#), ["", "a"]);
+    }
+
+    #[test]
+    fn unclosed_quote_is_literal() {
+        assert_eq!(words(r#""a b"#), [r#""a"#, "b"]);
+        assert_eq!(words(r#""a" "b"#), ["a", r#""b"#]);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(words(r#"\"a b\""#), [r#""a"#, r#"b""#]);
+        assert_eq!(words(r"a\ b c"), ["a b", "c"]);
+        assert_eq!(words(r#""a \" b""#), [r#"a " b"#]);
+        assert_eq!(words(r"\\ \--x"), [r"\", "--x"]);
+        // backslashes that escape nothing are kept
+        assert_eq!(words(r"C:\path\to"), [r"C:\path\to"]);
+    }
+
+    #[test]
+    fn words_borrow_when_possible() {
+        let mut words = RawMessageArgsIter::new(r#"plain "quoted""#);
+        assert!(matches!(words.next().unwrap().text, Cow::Borrowed("plain")));
+        assert!(matches!(words.next().unwrap().text, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn copies_are_independent() {
+        let mut args = RawMessageArgsIter::new("a b c");
+        args.next();
+
+        let mut fork = args;
+        assert_eq!(fork.next().unwrap().text, "b");
+        assert_eq!(fork.remainder(), Some("c"));
+        assert_eq!(args.remainder(), Some("b c"));
+    }
+
+    #[test]
+    fn rest_splits_off_flags() {
+        assert_eq!(
+            split_flags("some text --bottom --black"),
+            ("some text".into(), "--bottom --black")
+        );
+        assert_eq!(split_flags("no flags here"), ("no flags here".into(), ""));
+        assert_eq!(split_flags("--bottom"), ("".into(), "--bottom"));
+    }
+
+    #[test]
+    fn rest_keeps_dashes_in_text() {
+        assert_eq!(
+            split_flags("wait -- what --bottom"),
+            ("wait -- what".into(), "--bottom")
+        );
+        assert_eq!(split_flags("a--b c—d"), ("a--b c—d".into(), ""));
+        assert_eq!(split_flags("the —black cat"), ("the".into(), "—black cat"));
+    }
+
+    #[test]
+    fn rest_escaped_and_quoted_flags() {
+        assert_eq!(
+            split_flags(r"use \--force --bottom"),
+            ("use --force".into(), "--bottom")
+        );
+        assert_eq!(
+            split_flags(r#"say "--hi" --black"#),
+            (r#"say "--hi""#.into(), "--black")
+        );
+    }
+
+    #[test]
+    fn rest_keeps_text_as_written() {
+        assert_eq!(
+            split_flags("  line one\n  \"line\" two\\n  --bottom"),
+            ("line one\n  \"line\" two\\n".into(), "--bottom")
+        );
+    }
+
+    #[test]
+    fn rest_terminator() {
+        assert_eq!(split_flags("-- --not a flag "), ("--not a flag".into(), ""));
+        assert_eq!(split_flags("  --   text --bottom"), ("text --bottom".into(), ""));
+        assert_eq!(split_flags("--"), ("".into(), ""));
+    }
+
+    #[test]
+    fn rest_no_flags_is_verbatim() {
+        let input = "  tag {args} --not-a-flag \"quoted\" \\-- ";
+        assert_eq!(RawMessageArgsIter::new(input).remainder(), Some(input.trim_start()));
+    }
+
+    fn decode(input: &str) -> anyhow::Result<Vec<(String, Option<String>)>> {
+        let valid_flags = [
+            ("bottom", FlagType::NoValue),
+            ("text", FlagType::WithValue),
+            ("page", FlagType::WithValue),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut flags = flags_from_str(input, valid_flags)?.into_iter().collect::<Vec<_>>();
+        flags.sort();
+        Ok(flags)
+    }
+
+    #[test]
+    fn flags_with_quoted_values() {
+        assert_eq!(
+            decode(r#"--text "hello world" --bottom"#).unwrap(),
+            [
+                ("bottom".to_owned(), None),
+                ("text".to_owned(), Some("hello world".to_owned()))
+            ]
+        );
+        assert_eq!(
+            decode(r#"--text "--bottom""#).unwrap(),
+            [("text".to_owned(), Some("--bottom".to_owned()))]
+        );
+        assert_eq!(
+            decode(r"--text \--page").unwrap(),
+            [("text".to_owned(), Some("--page".to_owned()))]
+        );
+    }
+
+    #[test]
+    fn flags_errors() {
+        assert!(decode("--unknown").is_err());
+        assert!(decode("--text").is_err());
+        assert!(decode("--bottom yes").is_err());
+        assert!(decode("--page --bottom").is_err());
+    }
+
+    #[test]
+    fn flags_em_dash() {
+        assert_eq!(decode("—page 2").unwrap(), [("page".to_owned(), Some("2".to_owned()))]);
+    }
+
+    #[test]
+    fn rest_removes_quotes_of_a_single_word() {
+        assert_eq!(split_flags(r#""some text" --bottom"#), ("some text".into(), "--bottom"));
+        assert_eq!(split_flags(r#""--hi \" there""#), (r#"--hi " there"#.into(), ""));
+        assert_eq!(split_flags(r#""  padded  ""#), ("  padded  ".into(), ""));
+        // the quotes do not wrap all of the text
+        assert_eq!(split_flags(r#""a" "b""#), (r#""a" "b""#.into(), ""));
+        assert_eq!(split_flags(r#""a"b"#), (r#""a"b"#.into(), ""));
+        assert_eq!(split_flags(r#""unclosed"#), (r#""unclosed"#.into(), ""));
+        assert_eq!(split_flags(r#"-- "kept""#), (r#""kept""#.into(), ""));
+    }
+
+    /// What `ParseCtxt::rest` leaves for `#[derive(Flags)]` to decode
+    #[test]
+    fn rest_leaves_flags_to_decode() {
+        let mut args = RawMessageArgsIter::new(r#"first "some text" --text "a b" --bottom"#);
+        assert_eq!(args.next().unwrap().text, "first");
+
+        let (text, flags) = split_flags(args.remainder().unwrap());
+        assert_eq!(text, "some text");
+        assert_eq!(flags, r#"--text "a b" --bottom"#);
+        assert_eq!(
+            decode(flags).unwrap(),
+            [("bottom".to_owned(), None), ("text".to_owned(), Some("a b".to_owned()))]
+        );
+    }
+
+    #[test]
+    fn rest_no_flags_after_words() {
+        // `RestNoFlags` takes what is left as it was written
+        let mut args = RawMessageArgsIter::new(r#"tag "a b" --not-a-flag \-- "#);
+        assert_eq!(args.next().unwrap().text, "tag");
+        assert_eq!(args.remainder(), Some(r#""a b" --not-a-flag \-- "#));
+        assert_eq!(args.next().unwrap().text, "a b");
+    }
+}
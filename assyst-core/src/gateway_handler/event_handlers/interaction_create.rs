@@ -27,6 +27,7 @@ use crate::command::{
     CommandCtxt, CommandData, CommandGroupingInteractionInfo, ExecutionTimings, InteractionCommandParseCtxt,
 };
 use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
+use crate::tag_commands;
 
 fn parse_subcommand_data(data: &DiscordCommandData) -> Option<(String, CommandOptionValue)> {
     if let Some(option_zero) = data.options.first()
@@ -203,6 +204,29 @@ pub async fn handle(assyst: ThreadSafeAssyst, InteractionCreate(interaction): In
                     .await
                     .map_err(|e| err!("Error handling post-command: {e:#}"));
             }
+        } else if let Some(guild_id) = interaction.guild_id
+            && let Some(channel) = interaction.channel
+        {
+            // guild slash commands are registered for tags that were promoted to commands
+            let author = interaction.member.and_then(|x| x.user).or(interaction.user).unwrap();
+            match tag_commands::handle_interaction(
+                &assyst,
+                guild_id,
+                channel.id,
+                author,
+                interaction.id,
+                interaction.token,
+                &command_data,
+            )
+            .await
+            {
+                Ok(true) => {},
+                Ok(false) => warn!(
+                    "Received interaction for non-existent command: {}, ignoring",
+                    command_data.name
+                ),
+                Err(e) => err!("Failed to handle tag command interaction: {e:#}"),
+            }
         } else {
             warn!(
                 "Received interaction for non-existent command: {}, ignoring",
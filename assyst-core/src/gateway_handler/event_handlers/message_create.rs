@@ -3,20 +3,22 @@ use std::time::Instant;
 
 use assyst_common::err;
 use tracing::{debug, error};
+use twilight_model::channel::Message;
 use twilight_model::gateway::payload::incoming::MessageCreate;
 
 use super::after_command_execution_success;
 use crate::command::errors::{ExecutionError, TagParseError};
 use crate::command::source::Source;
 use crate::command::{CommandCtxt, CommandData, RawMessageParseCtxt};
-use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
+use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity, ParseError, PreParseError};
 use crate::gateway_handler::message_parser::parser::parse_message_into_command;
-use crate::ThreadSafeAssyst;
+use crate::{tag_triggers, ThreadSafeAssyst};
 
 /// Handle a [`MessageCreate`] event received from the Discord gateway.
 ///
 /// This function passes the message to the command parser, which then attempts to convert the
-/// message to a command for further processing.
+/// message to a command for further processing. Messages that do not invoke a command are checked
+/// against the tag triggers of the guild.
 pub async fn handle(assyst: ThreadSafeAssyst, MessageCreate(message): MessageCreate) {
     if assyst.bad_translator.is_channel(message.channel_id.get()).await && !assyst.bad_translator.is_disabled().await {
         match assyst.bad_translator.handle_message(&assyst, Box::new(message)).await {
@@ -78,7 +80,10 @@ pub async fn handle(assyst: ThreadSafeAssyst, MessageCreate(message): MessageCre
                     .map_err(|e| err!("Error handling post-command: {e:#}"));
             }
         },
-        Ok(None) => { /* command not found */ },
+        Ok(None) => handle_tag_triggers(&assyst, &message).await,
+        Err(ParseError::PreParseFail(PreParseError::MessageNotPrefixed(_))) => {
+            handle_tag_triggers(&assyst, &message).await;
+        },
         Err(error) => {
             if error.get_severity() == ErrorSeverity::High {
                 err!("{error}");
@@ -88,3 +93,9 @@ pub async fn handle(assyst: ThreadSafeAssyst, MessageCreate(message): MessageCre
         },
     };
 }
+
+async fn handle_tag_triggers(assyst: &ThreadSafeAssyst, message: &Message) {
+    if let Err(e) = tag_triggers::handle_message(assyst, message).await {
+        err!("Tag trigger execution failed: {e:#}");
+    }
+}
@@ -75,6 +75,8 @@ impl Display for MetadataCheckInvalidated {
 pub enum ParseError {
     /// Failure with preprocessing of the message.
     PreParseFail(PreParseError),
+    /// Other unknown failure while identifying the command. Unexpected error with high severity.
+    Failure(String),
 }
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -82,6 +84,9 @@ impl Display for ParseError {
             Self::PreParseFail(message) => {
                 write!(f, "Pre-parse failed: {message}")
             },
+            Self::Failure(message) => {
+                write!(f, "Parse failed: {message}")
+            },
         }
     }
 }
@@ -90,6 +95,7 @@ impl GetErrorSeverity for ParseError {
     fn get_severity(&self) -> ErrorSeverity {
         match self {
             ParseError::PreParseFail(e) => e.get_severity(),
+            ParseError::Failure(_) => ErrorSeverity::High,
         }
     }
 }
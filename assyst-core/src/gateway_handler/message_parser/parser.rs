@@ -1,9 +1,11 @@
 use std::time::Instant;
 
+use assyst_database::model::tag_command::TagCommand;
 use twilight_model::channel::Message;
 
 use super::error::ParseError;
 use super::preprocess::preprocess;
+use crate::command::misc::tag::tag_command;
 use crate::command::registry::find_command_by_name;
 use crate::command::{ExecutionTimings, TCommand};
 use crate::ThreadSafeAssyst;
@@ -15,6 +17,18 @@ pub struct ParseResult<'a> {
     pub execution_timings: ExecutionTimings,
 }
 
+/// Returns the arguments to run the tag command with if `command_name` is one of the `promoted` tag
+/// commands of the guild, or `None` if it is not.
+///
+/// The tag command takes the tag name as its first argument, which is the name of the promoted
+/// command, so this is the whole command text.
+fn promoted_tag_args<'a>(command_text: &'a str, command_name: &str, promoted: &[TagCommand]) -> Option<&'a str> {
+    promoted
+        .iter()
+        .any(|command| command.tag_name.eq_ignore_ascii_case(command_name))
+        .then(|| command_text.trim_start())
+}
+
 /// Parse any generic Message object into a Command.
 ///
 /// This function takes all steps necessary to split a message into critical command components,
@@ -40,7 +54,9 @@ pub struct ParseResult<'a> {
 /// These events have a timeout for handling, to prevent editing of very old
 /// messages. If it is expired, prematurely return.
 ///
-/// **Step 4**: Parse the Command from the Message itself. If it fails to parse, prematurely return.
+/// **Step 4**: Parse the Command from the Message itself. If no command has the given name, fall
+/// back to the tags of the guild that were promoted to commands, which are run through the tag
+/// command. If it fails to parse, prematurely return.
 ///
 /// Once all steps are complete, a Command is returned, ready for execution.
 /// Note that metadata is checked *during* execution (i.e., in the base command's `Command::execute`
@@ -64,12 +80,27 @@ pub async fn parse_message_into_command(
     let command_text = &message.content[preprocess.prefix.len()..];
 
     let mut args = command_text.split_ascii_whitespace();
-    let Some(command) = args.next() else {
+    let Some(command_name) = args.next() else {
         return Ok(None);
     };
-    let args = args.remainder().unwrap_or("");
-    let Some(command) = find_command_by_name(command) else {
-        return Ok(None);
+    let mut args = args.remainder().unwrap_or("");
+    let command = match find_command_by_name(command_name) {
+        Some(command) => command,
+        None => {
+            let Some(guild_id) = message.guild_id else {
+                return Ok(None);
+            };
+
+            let promoted = TagCommand::list_in_guild(&assyst.database_handler, guild_id.get() as i64)
+                .await
+                .map_err(|e| ParseError::Failure(format!("failed to fetch tag commands: {e}")))?;
+            let Some(tag_args) = promoted_tag_args(command_text, command_name, &promoted) else {
+                return Ok(None);
+            };
+
+            args = tag_args;
+            &tag_command
+        },
     };
 
     Ok(Some(ParseResult {
@@ -85,3 +116,33 @@ pub async fn parse_message_into_command(
         },
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn promoted(names: &[&str]) -> Vec<TagCommand> {
+        names
+            .iter()
+            .map(|name| TagCommand {
+                guild_id: 1,
+                tag_name: (*name).to_owned(),
+                arguments: Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn promoted_tag_runs_through_tag_command() {
+        let commands = promoted(&["greet", "roll"]);
+
+        assert_eq!(promoted_tag_args(" greet a b", "greet", &commands), Some("greet a b"));
+        assert_eq!(promoted_tag_args("ROLL 20", "ROLL", &commands), Some("ROLL 20"));
+    }
+
+    #[test]
+    fn unknown_command_is_ignored() {
+        assert_eq!(promoted_tag_args("gret a", "gret", &promoted(&["greet"])), None);
+        assert_eq!(promoted_tag_args("greet", "greet", &[]), None);
+    }
+}
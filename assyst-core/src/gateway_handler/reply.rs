@@ -10,23 +10,27 @@ use twilight_model::http::interaction::InteractionResponse;
 use twilight_model::id::Id;
 use twilight_util::builder::InteractionResponseDataBuilder;
 
-use crate::command::messagebuilder::MessageBuilder;
+use crate::command::messagebuilder::{Attachment, MessageBuilder};
 use crate::command::CommandCtxt;
 use crate::replies::{Reply, ReplyInUse, ReplyState};
 use crate::rest::filer::upload_to_filer;
 use crate::rest::NORMAL_DISCORD_UPLOAD_LIMIT_BYTES;
 
-/// Trims a `String` in-place such that it fits in Discord's 2000 character message limit.
-fn trim_content_fits(content: &mut String) {
+/// Discord's message length limit, in characters
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// Trims a `String` in-place such that it fits in `max_length` characters.
+fn trim_content_fits(content: &mut String, max_length: usize) {
     const CODEBLOCK: &str = "```";
     let codeblocked = content.ends_with(CODEBLOCK);
-    if let Some((truncated_byte_index, _)) =
-        content
-            .char_indices()
-            .nth(if codeblocked { 2000 - CODEBLOCK.len() } else { 2000 })
-    {
-        // If the content length exceeds 2000 characters, truncate it at the 2000th characters' byte
-        // index
+    let max_length = if codeblocked {
+        max_length.saturating_sub(CODEBLOCK.len())
+    } else {
+        max_length
+    };
+    if let Some((truncated_byte_index, _)) = content.char_indices().nth(max_length) {
+        // If the content length exceeds the limit, truncate it at the byte index of the character
+        // at the limit
         content.truncate(truncated_byte_index);
         if codeblocked {
             *content += CODEBLOCK;
@@ -34,42 +38,83 @@ fn trim_content_fits(content: &mut String) {
     }
 }
 
-/// Gets the Filer URL for this attachment if it exceeds the guild's upload limit.
-async fn get_filer_url(
-    ctxt: &CommandCtxt<'_>,
-    content: Option<&String>,
-    data: Vec<u8>,
-) -> anyhow::Result<Option<String>> {
-    let filer_url;
-    let filer_formatted;
-
-    if data.len() > NORMAL_DISCORD_UPLOAD_LIMIT_BYTES as usize {
-        let guild_upload_limit = if let Some(guild_id) = ctxt.data.guild_id {
+/// Gets the upload limit of the guild of the invocation, which Discord applies to the total size of
+/// the attachments of a message.
+async fn upload_limit(ctxt: &CommandCtxt<'_>) -> anyhow::Result<u64> {
+    match ctxt.data.guild_id {
+        Some(guild_id) => {
             ctxt.assyst()
                 .rest_cache_handler
                 .get_guild_upload_limit_bytes(guild_id.get())
-                .await?
-        } else {
-            NORMAL_DISCORD_UPLOAD_LIMIT_BYTES
+                .await
+        },
+        None => Ok(NORMAL_DISCORD_UPLOAD_LIMIT_BYTES),
+    }
+}
+
+/// Uploads an attachment to Filer, returning its URL.
+async fn get_filer_url(ctxt: &CommandCtxt<'_>, data: Vec<u8>) -> anyhow::Result<String> {
+    let mime = get_sig(&data).unwrap_or(Type::PNG).as_mime();
+    upload_to_filer(&ctxt.assyst().reqwest_client, data, mime).await
+}
+
+/// Splits attachments into the ones that can be uploaded to Discord, and the Filer URLs of the ones
+/// that do not fit in the guild's upload limit together with the attachments before them.
+async fn prepare_attachments(
+    ctxt: &CommandCtxt<'_>,
+    attachments: Vec<Attachment>,
+) -> anyhow::Result<(Vec<TwilightAttachment>, Vec<String>)> {
+    let mut uploads = Vec::new();
+    let mut filer_urls = Vec::new();
+    let mut total_size = 0;
+    // only looked up once the attachments exceed the limit that applies to every guild
+    let mut limit = None;
+
+    for attachment in attachments {
+        let size = total_size + attachment.data.len() as u64;
+        let fits = size <= NORMAL_DISCORD_UPLOAD_LIMIT_BYTES || {
+            let limit = match limit {
+                Some(limit) => limit,
+                None => *limit.insert(upload_limit(ctxt).await?),
+            };
+            size <= limit
         };
 
-        if data.len() > guild_upload_limit as usize {
-            filer_url = upload_to_filer(
-                &ctxt.assyst().reqwest_client,
-                data.clone(),
-                get_sig(&data).unwrap_or(Type::PNG).as_mime(),
-            )
-            .await?;
-
-            if let Some(content) = content {
-                filer_formatted = format!("{content} {filer_url}");
-                return Ok(Some(filer_formatted));
-            }
-            return Ok(Some(filer_url));
+        if fits {
+            total_size = size;
+            uploads.push(TwilightAttachment::from_bytes(
+                attachment.name.into(),
+                attachment.data,
+                uploads.len() as u64,
+            ));
+        } else {
+            filer_urls.push(get_filer_url(ctxt, attachment.data).await?);
         }
-        return Ok(None);
     }
-    Ok(None)
+
+    Ok((uploads, filer_urls))
+}
+
+/// Builds the content of a message, with the Filer URLs of oversized attachments appended.
+/// `has_other_output` is whether the message has anything besides its content (files or embeds).
+fn message_content(content: Option<String>, filer_urls: &[String], has_other_output: bool) -> String {
+    let mut content = content.unwrap_or_default();
+
+    if content.trim().is_empty() && filer_urls.is_empty() && !has_other_output {
+        return "[Empty Response]".to_owned();
+    }
+
+    // leave room for the URLs, which are each preceded by a space
+    let urls_length = filer_urls.iter().map(|url| url.chars().count() + 1).sum::<usize>();
+    trim_content_fits(&mut content, MAX_MESSAGE_LENGTH.saturating_sub(urls_length));
+    for url in filer_urls {
+        if !content.is_empty() {
+            content.push(' ');
+        }
+        content += url;
+    }
+
+    content
 }
 
 pub async fn edit(ctxt: &CommandCtxt<'_>, builder: MessageBuilder, reply: ReplyInUse) -> anyhow::Result<()> {
@@ -82,33 +127,20 @@ pub async fn edit(ctxt: &CommandCtxt<'_>, builder: MessageBuilder, reply: ReplyI
         .update_message(ctxt.data.channel_id, Id::new(reply.message_id))
         .allowed_mentions(Some(&allowed_mentions));
 
-    let mut content_clone = builder.content.clone();
+    let (attachments, filer_urls) = prepare_attachments(ctxt, builder.attachments).await?;
+    let content = message_content(
+        builder.content,
+        &filer_urls,
+        !attachments.is_empty() || !builder.embeds.is_empty(),
+    );
+    message = message.content(Some(&content));
 
-    if builder.attachment.is_none() && builder.content.as_ref().map_or(true, |x| x.trim().is_empty()) {
-        message = message.content(Some("[Empty Response]"));
-    } else if let Some(content) = &mut content_clone {
-        trim_content_fits(content);
-        message = message.content(Some(content));
+    if !attachments.is_empty() {
+        message = message.attachments(&attachments);
     }
 
-    let attachments;
-    let url;
-    if let Some(attachment) = builder.attachment {
-        if let Some(found_url) = get_filer_url(ctxt, builder.content.as_ref(), attachment.data.clone()).await? {
-            url = found_url;
-            message = message.content(Some(&url));
-        } else {
-            attachments = [TwilightAttachment::from_bytes(
-                attachment.name.into(),
-                attachment.data,
-                0,
-            )];
-            message = message.attachments(&attachments);
-            if builder.content.is_none() {
-                message = message.content(Some(""));
-            }
-        };
-    }
+    // an empty list removes the embeds of the previous response
+    message = message.embeds(Some(&builder.embeds));
 
     message.await?;
     Ok(())
@@ -136,32 +168,20 @@ async fn create_message(ctxt: &CommandCtxt<'_>, builder: MessageBuilder) -> anyh
         message = message.reply(source_message.id);
     }
 
-    let mut content_clone = builder.content.clone();
+    let (attachments, filer_urls) = prepare_attachments(ctxt, builder.attachments).await?;
+    let content = message_content(
+        builder.content,
+        &filer_urls,
+        !attachments.is_empty() || !builder.embeds.is_empty(),
+    );
+    message = message.content(&content);
 
-    if builder.attachment.is_none() && builder.content.as_ref().map_or(true, |x| x.trim().is_empty()) {
-        message = message.content("[Empty Response]");
-    } else if let Some(content) = &mut content_clone {
-        trim_content_fits(content);
-        message = message.content(content);
+    if !attachments.is_empty() {
+        message = message.attachments(&attachments);
     }
 
-    let attachments;
-    let url;
-    if let Some(attachment) = builder.attachment {
-        if let Some(found_url) = get_filer_url(ctxt, builder.content.as_ref(), attachment.data.clone()).await? {
-            url = found_url;
-            message = message.content(&url);
-        } else {
-            attachments = [TwilightAttachment::from_bytes(
-                attachment.name.into(),
-                attachment.data,
-                0,
-            )];
-            message = message.attachments(&attachments);
-            if builder.content.is_none() {
-                message = message.content("");
-            }
-        };
+    if !builder.embeds.is_empty() {
+        message = message.embeds(&builder.embeds);
     }
 
     let cs;
@@ -218,18 +238,25 @@ pub async fn reply_interaction_command(ctxt: &CommandCtxt<'_>, builder: MessageB
 
     let c = ctxt.assyst().interaction_client();
     let mut response_data = InteractionResponseDataBuilder::new();
-    if let Some(ref a) = builder.attachment {
-        let attachments = [TwilightAttachment::from_bytes(a.name.clone().into(), a.data.clone(), 0)];
-        response_data = response_data.attachments(attachments);
-        response_data = response_data.content("");
-    }
 
-    response_data = response_data.allowed_mentions(AllowedMentions::default());
+    let (attachments, filer_urls) = prepare_attachments(ctxt, builder.attachments).await?;
+    let content = message_content(
+        builder.content,
+        &filer_urls,
+        !attachments.is_empty() || !builder.embeds.is_empty(),
+    );
+    response_data = response_data.content(content.clone());
 
-    if let Some(c) = builder.content.clone() {
-        response_data = response_data.content(c);
+    if !attachments.is_empty() {
+        response_data = response_data.attachments(attachments.clone());
     }
 
+    if !builder.embeds.is_empty() {
+        response_data = response_data.embeds(builder.embeds.clone());
+    }
+
+    response_data = response_data.allowed_mentions(AllowedMentions::default());
+
     let response = InteractionResponse {
         kind: twilight_model::http::interaction::InteractionResponseType::ChannelMessageWithSource,
         data: Some(response_data.build()),
@@ -238,16 +265,15 @@ pub async fn reply_interaction_command(ctxt: &CommandCtxt<'_>, builder: MessageB
     if reply_in_use {
         let token = ctxt.data.interaction_token.clone().unwrap();
         let mut update = c.update_response(&token);
-        let attachments;
 
-        if let Some(ref a) = builder.attachment {
-            attachments = [TwilightAttachment::from_bytes(a.name.clone().into(), a.data.clone(), 0)];
+        if !attachments.is_empty() {
             update = update.attachments(&attachments);
         }
 
-        if let Some(ref c) = builder.content {
-            update = update.content(Some(c));
-        }
+        // an empty list removes the embeds of the previous response
+        update = update.embeds(Some(&builder.embeds));
+
+        update = update.content(Some(&content));
 
         if let Some(ref components) = builder.components {
             update = update.components(Some(components));
@@ -0,0 +1,147 @@
+//! The local JavaScript backend of tags, which runs scripts in the embedded dash VM instead of the
+//! eval service. Scripts run in the `js-sandbox` binary as a child process, with a memory limit
+//! set through `setrlimit` and a CPU time limit that the sandbox enforces with a profiling timer.
+
+use std::os::unix::process::ExitStatusExt;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use assyst_common::config::config::JavascriptBackend;
+use assyst_common::config::CONFIG;
+use assyst_common::err;
+use assyst_common::eval::{FakeEvalImageResponse, FakeEvalResponse};
+use assyst_common::util::string_from_likely_utf8;
+use assyst_tag::parser::limits::MAX_STRING_LENGTH;
+use reqwest::Client;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::time::timeout;
+use twilight_model::channel::Message;
+
+use crate::rest::eval::fake_eval;
+
+/// Name of the sandbox binary, which is built next to the main binary
+const SANDBOX_BINARY: &str = "js-sandbox";
+/// Exit code of the sandbox when an allocation failed, see `OUT_OF_MEMORY_EXIT_CODE` in
+/// `bin/js-sandbox.rs`
+const OUT_OF_MEMORY_EXIT_CODE: i32 = 3;
+
+/// Runs JavaScript from a tag with the backend selected in the config.
+pub async fn eval_tag_javascript(
+    client: &Client,
+    code: &str,
+    message: Option<&Message>,
+    args: Vec<String>,
+) -> anyhow::Result<FakeEvalImageResponse> {
+    match CONFIG.javascript.backend {
+        JavascriptBackend::Remote => fake_eval(client, code.to_owned(), true, message, args).await,
+        JavascriptBackend::Local => local_eval(code, message, &args).await,
+        JavascriptBackend::Fallback => match fake_eval(client, code.to_owned(), true, message, args.clone()).await {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                err!("Eval service failed, falling back to the dash VM: {e:#}");
+                local_eval(code, message, &args).await
+            },
+        },
+    }
+}
+
+/// Runs JavaScript in the dash VM. Like with the eval service, the invoking message and the
+/// arguments are available to the script as the `message` and `args` globals.
+pub async fn local_eval(
+    code: &str,
+    message: Option<&Message>,
+    args: &[String],
+) -> anyhow::Result<FakeEvalImageResponse> {
+    let limits = &CONFIG.javascript;
+
+    let script = format!(
+        "const message = {};\nconst args = {};\n{code}",
+        serde_json::to_string(&message)?,
+        serde_json::to_string(args)?
+    );
+
+    let path = std::env::current_exe()
+        .context("Failed to locate the JavaScript sandbox")?
+        .with_file_name(SANDBOX_BINARY);
+    let memory_limit = limits.memory_limit_mb * 1024 * 1024;
+
+    let mut command = Command::new(path);
+    command
+        .arg(limits.time_limit_ms.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    // SAFETY: the closure only calls setrlimit, which is async-signal-safe, so it is fine to run
+    // between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            let memory = libc::rlimit {
+                rlim_cur: memory_limit,
+                rlim_max: memory_limit,
+            };
+
+            if libc::setrlimit(libc::RLIMIT_AS, &memory) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn().context("Failed to start the JavaScript sandbox")?;
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Failed to pass the script to the JavaScript sandbox")?;
+    stdin
+        .write_all(script.as_bytes())
+        .await
+        .context("Failed to pass the script to the JavaScript sandbox")?;
+    // closing stdin lets the sandbox know the whole script was sent
+    drop(stdin);
+
+    let stdout = child
+        .stdout
+        .take()
+        .context("Failed to read the output of the JavaScript sandbox")?;
+    let run = async {
+        // reads at most one byte more than a tag can hold, to tell if the output is too long
+        let mut output = Vec::new();
+        stdout
+            .take(MAX_STRING_LENGTH as u64 + 1)
+            .read_to_end(&mut output)
+            .await?;
+        if output.len() > MAX_STRING_LENGTH {
+            // the sandbox is killed when the child is dropped
+            return Ok((None, output));
+        }
+
+        child.wait().await.map(|status| (Some(status), output))
+    };
+
+    // the child is killed when its future is dropped on timeout
+    let Ok(result) = timeout(Duration::from_millis(limits.time_limit_ms), run).await else {
+        bail!("The script took too long to run");
+    };
+    let (status, output) = result.context("Failed to run the JavaScript sandbox")?;
+    let Some(status) = status else {
+        bail!("The script produced too much output");
+    };
+
+    if status.code() == Some(OUT_OF_MEMORY_EXIT_CODE) {
+        bail!("The script ran out of memory");
+    }
+
+    match status.signal() {
+        // sent by the CPU time limit of the sandbox
+        Some(libc::SIGPROF) => bail!("The script took too long to run"),
+        Some(signal) => bail!("The JavaScript sandbox crashed (signal {signal})"),
+        None => Ok(FakeEvalImageResponse::Text(FakeEvalResponse {
+            message: string_from_likely_utf8(output),
+        })),
+    }
+}
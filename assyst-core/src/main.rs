@@ -25,6 +25,7 @@ use gateway_handler::incoming_event::IncomingEvent;
 use rest::patreon::init_patreon_refresh;
 use task::tasks::refresh_entitlements::refresh_entitlements;
 use task::tasks::reminders::handle_reminders;
+use task::tasks::tag_uses::flush_tag_uses;
 use tokio::spawn;
 use tracing::{info /* trace */};
 use twilight_gateway::EventTypeFlags;
@@ -42,9 +43,13 @@ mod command;
 mod command_ratelimits;
 mod downloader;
 mod gateway_handler;
+mod js_sandbox;
 mod persistent_cache_handler;
 mod replies;
 mod rest;
+mod tag_commands;
+mod tag_triggers;
+mod tag_uses;
 mod task;
 
 // Jemallocator is probably unnecessary for the average instance,
@@ -150,6 +155,14 @@ async fn main() {
         info!("Entitlement refreshing disabled in config.dev.disable_entitlement_fetching: not registering task");
     }
 
+    assyst.register_task(Task::new_delayed(
+        assyst.clone(),
+        Duration::from_secs(crate::task::tasks::tag_uses::FLUSH_INTERVAL_SECONDS),
+        Duration::from_secs(crate::task::tasks::tag_uses::FLUSH_INTERVAL_SECONDS),
+        function_task_callback!(flush_tag_uses),
+    ));
+    info!("Registered tag use flushing task");
+
     info!("Starting assyst-webserver");
     assyst_webserver::run(
         assyst.database_handler.clone(),
@@ -24,6 +24,35 @@ fn default_cache<K: TCacheK, V: TCacheV>() -> Cache<K, V> {
         .build()
 }
 
+#[derive(Clone)]
+pub struct GuildInfo {
+    pub name: String,
+    /// Only an approximation, as reported by Discord
+    pub member_count: Option<u64>,
+}
+
+#[derive(Clone)]
+pub struct ChannelInfo {
+    pub name: Option<String>,
+    pub topic: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct MemberInfo {
+    pub nickname: Option<String>,
+    /// Role IDs
+    pub roles: Vec<u64>,
+    /// Unix timestamp in seconds
+    pub joined_at: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct RoleInfo {
+    pub id: u64,
+    pub name: String,
+    pub position: i64,
+}
+
 /// Rest cache handler for any common data structures loaded from a network resource.
 pub struct RestCacheHandler {
     http_client: Arc<HttpClient>,
@@ -33,6 +62,14 @@ pub struct RestCacheHandler {
     channel_nsfw_status: Cache<u64, bool>,
     /// Guild ID -> User ID
     guild_owners: Cache<u64, u64>,
+    /// Guild ID -> Guild name and member count
+    guild_info: Cache<u64, GuildInfo>,
+    /// Channel ID -> Channel name and topic
+    channel_info: Cache<u64, ChannelInfo>,
+    /// (Guild ID, User ID) -> Member
+    guild_members: Cache<(u64, u64), MemberInfo>,
+    /// Guild ID -> Roles
+    guild_roles: Cache<u64, Vec<RoleInfo>>,
 }
 impl RestCacheHandler {
     pub fn new(client: Arc<HttpClient>) -> RestCacheHandler {
@@ -41,6 +78,10 @@ impl RestCacheHandler {
             guild_upload_limits: default_cache(),
             channel_nsfw_status: default_cache(),
             guild_owners: default_cache(),
+            guild_info: default_cache(),
+            channel_info: default_cache(),
+            guild_members: default_cache(),
+            guild_roles: default_cache(),
         }
     }
 
@@ -49,10 +90,19 @@ impl RestCacheHandler {
         self.guild_upload_limits.run_pending_tasks();
         self.channel_nsfw_status.run_pending_tasks();
         self.guild_owners.run_pending_tasks();
+        self.guild_info.run_pending_tasks();
+        self.channel_info.run_pending_tasks();
+        self.guild_members.run_pending_tasks();
+        self.guild_roles.run_pending_tasks();
 
         size += self.guild_upload_limits.entry_count() * size_of::<(u64, u64)>() as u64;
         size += self.channel_nsfw_status.entry_count() * size_of::<(u64, bool)>() as u64;
         size += self.guild_owners.entry_count() * size_of::<(u64, u64)>() as u64;
+        // only the fixed size parts, strings and role lists are not counted
+        size += self.guild_info.entry_count() * size_of::<(u64, GuildInfo)>() as u64;
+        size += self.channel_info.entry_count() * size_of::<(u64, ChannelInfo)>() as u64;
+        size += self.guild_members.entry_count() * size_of::<((u64, u64), MemberInfo)>() as u64;
+        size += self.guild_roles.entry_count() * size_of::<(u64, Vec<RoleInfo>)>() as u64;
         size
     }
 
@@ -177,4 +227,96 @@ impl RestCacheHandler {
 
         Ok(owner == user_id || member_is_manager)
     }
+
+    pub async fn get_guild_info(&self, guild_id: u64) -> anyhow::Result<GuildInfo> {
+        if let Some(info) = self.guild_info.get(&guild_id) {
+            return Ok(info);
+        }
+
+        let guild = self
+            .http_client
+            .guild(Id::<GuildMarker>::new(guild_id))
+            .with_counts(true)
+            .await?
+            .model()
+            .await?;
+
+        let info = GuildInfo {
+            name: guild.name,
+            member_count: guild.approximate_member_count,
+        };
+
+        self.guild_info.insert(guild_id, info.clone());
+
+        Ok(info)
+    }
+
+    pub async fn get_channel_info(&self, channel_id: u64) -> anyhow::Result<ChannelInfo> {
+        if let Some(info) = self.channel_info.get(&channel_id) {
+            return Ok(info);
+        }
+
+        let channel = self
+            .http_client
+            .channel(Id::<ChannelMarker>::new(channel_id))
+            .await?
+            .model()
+            .await?;
+
+        let info = ChannelInfo {
+            name: channel.name,
+            topic: channel.topic,
+        };
+
+        self.channel_info.insert(channel_id, info.clone());
+
+        Ok(info)
+    }
+
+    pub async fn get_guild_member(&self, guild_id: u64, user_id: u64) -> anyhow::Result<MemberInfo> {
+        if let Some(member) = self.guild_members.get(&(guild_id, user_id)) {
+            return Ok(member);
+        }
+
+        let member = self
+            .http_client
+            .guild_member(Id::<GuildMarker>::new(guild_id), Id::<UserMarker>::new(user_id))
+            .await?
+            .model()
+            .await?;
+
+        let info = MemberInfo {
+            nickname: member.nick,
+            roles: member.roles.iter().map(|id| id.get()).collect(),
+            joined_at: member.joined_at.map(|joined_at| joined_at.as_secs()),
+        };
+
+        self.guild_members.insert((guild_id, user_id), info.clone());
+
+        Ok(info)
+    }
+
+    pub async fn get_guild_roles(&self, guild_id: u64) -> anyhow::Result<Vec<RoleInfo>> {
+        if let Some(roles) = self.guild_roles.get(&guild_id) {
+            return Ok(roles);
+        }
+
+        let roles = self
+            .http_client
+            .roles(Id::<GuildMarker>::new(guild_id))
+            .await?
+            .models()
+            .await?
+            .into_iter()
+            .map(|role| RoleInfo {
+                id: role.id.get(),
+                name: role.name,
+                position: role.position,
+            })
+            .collect::<Vec<_>>();
+
+        self.guild_roles.insert(guild_id, roles.clone());
+
+        Ok(roles)
+    }
 }
@@ -0,0 +1,195 @@
+//! Tag commands: tags that were promoted to commands of their guild, so they can be run as `-name`
+//! or as the guild slash command `/name` instead of through the tag command.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use assyst_common::err;
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_command::TagCommand;
+use tracing::debug;
+use twilight_model::application::command::CommandType;
+use twilight_model::application::interaction::application_command::{
+    CommandData as DiscordCommandData, CommandDataOption, CommandOptionValue,
+};
+use twilight_model::id::marker::{ChannelMarker, GuildMarker, InteractionMarker};
+use twilight_model::id::Id;
+use twilight_model::user::User;
+use twilight_util::builder::command::{CommandBuilder, StringBuilder};
+
+use crate::assyst::ThreadSafeAssyst;
+use crate::command::errors::ExecutionError;
+use crate::command::misc::tag::{default_command, run_tag, tag_command};
+use crate::command::source::Source;
+use crate::command::{check_metadata, Command, CommandCtxt, CommandData, ExecutionTimings};
+use crate::gateway_handler::event_handlers::after_command_execution_success;
+use crate::gateway_handler::message_parser::error::{ErrorSeverity, GetErrorSeverity};
+
+/// Discord allows 100 slash commands per guild
+pub const MAX_TAG_COMMANDS_PER_GUILD: usize = 50;
+/// Discord allows 25 options per slash command
+pub const MAX_TAG_COMMAND_ARGUMENTS: usize = 25;
+
+/// Checks that a name can be used as the name of a slash command or one of its options
+pub fn is_valid_command_name(name: &str) -> bool {
+    (1..=32).contains(&name.len())
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Replaces the slash commands of a guild with its current tag commands.
+pub async fn sync_guild_commands(assyst: &ThreadSafeAssyst, guild_id: u64) -> anyhow::Result<()> {
+    let commands = TagCommand::list_in_guild(&assyst.database_handler, guild_id as i64).await?;
+
+    let commands = commands
+        .iter()
+        .map(|command| {
+            let mut builder = CommandBuilder::new(
+                &command.tag_name,
+                format!("run the {} tag", command.tag_name),
+                CommandType::ChatInput,
+            );
+            for argument in &command.arguments {
+                builder = builder.option(StringBuilder::new(argument, "tag argument").required(true));
+            }
+            builder.build()
+        })
+        .collect::<Vec<_>>();
+
+    assyst
+        .interaction_client()
+        .set_guild_commands(Id::new(guild_id), &commands)
+        .await?;
+
+    Ok(())
+}
+
+/// Runs a tag command that was invoked as a guild slash command. Returns false if the guild has no
+/// tag command with the name of the invoked command.
+///
+/// Like the `-name` fallback of the message parser, the invocation goes through the metadata checks
+/// of the default subcommand of the tag command. The values of the options are the arguments of the
+/// tag, one per option.
+pub async fn handle_interaction(
+    assyst: &ThreadSafeAssyst,
+    guild_id: Id<GuildMarker>,
+    channel_id: Id<ChannelMarker>,
+    author: User,
+    interaction_id: Id<InteractionMarker>,
+    interaction_token: String,
+    command_data: &DiscordCommandData,
+) -> anyhow::Result<bool> {
+    let Some(command) = TagCommand::get(&assyst.database_handler, guild_id.get() as i64, &command_data.name).await?
+    else {
+        return Ok(false);
+    };
+
+    let arguments = tag_command_arguments(&command.arguments, &command_data.options);
+
+    let data = CommandData {
+        source: Source::Interaction,
+        assyst,
+        execution_timings: ExecutionTimings {
+            parse_total: Duration::from_secs(0),
+            prefix_determiner: Duration::from_secs(0),
+            preprocess_total: Duration::from_secs(0),
+            processing_time_start: Instant::now(),
+            metadata_check_start: Instant::now(),
+        },
+        calling_prefix: "/".to_owned(),
+        message: None,
+        interaction_subcommand: None,
+        channel_id,
+        guild_id: Some(guild_id),
+        author,
+        interaction_token: Some(interaction_token),
+        interaction_id: Some(interaction_id),
+        interaction_attachments: HashMap::new(),
+        command_from_install_context: false,
+        resolved_messages: None,
+        resolved_users: None,
+    };
+
+    let mut ctxt = CommandCtxt::new(&data);
+
+    if let Err(err) = run_tag_command(&mut ctxt, guild_id.get(), &command.tag_name, arguments).await {
+        match err.get_severity() {
+            ErrorSeverity::Low => debug!("{err:?}"),
+            ErrorSeverity::High => {
+                let _ = ctxt.reply(format!(":warning: ``{err:#}``")).await;
+            },
+        }
+    } else {
+        let _ = after_command_execution_success(ctxt, &tag_command)
+            .await
+            .map_err(|e| err!("Error handling post-command: {e:#}"));
+    }
+
+    Ok(true)
+}
+
+/// The arguments of a tag command invocation: the value of every declared option in declared order,
+/// or an empty string for options that were not given.
+fn tag_command_arguments(declared: &[String], options: &[CommandDataOption]) -> Vec<String> {
+    declared
+        .iter()
+        .map(|name| {
+            options
+                .iter()
+                .find(|option| option.name == *name)
+                .and_then(|option| match &option.value {
+                    CommandOptionValue::String(value) => Some(value.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Runs the tag of a tag command with the metadata checks of the default subcommand of the tag
+/// command, which would run it otherwise.
+async fn run_tag_command(
+    ctxt: &mut CommandCtxt<'_>,
+    guild_id: u64,
+    tag_name: &str,
+    arguments: Vec<String>,
+) -> Result<(), ExecutionError> {
+    check_metadata(default_command.metadata(), ctxt).await?;
+
+    let tag = Tag::get(&ctxt.assyst().database_handler, guild_id as i64, tag_name)
+        .await
+        .context("Failed to fetch tag")
+        .and_then(|tag| tag.context("Tag not found in this server."))
+        .map_err(ExecutionError::Command)?;
+
+    run_tag(ctxt, tag, arguments).await.map_err(ExecutionError::Command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(name: &str, value: &str) -> CommandDataOption {
+        CommandDataOption {
+            name: name.to_owned(),
+            value: CommandOptionValue::String(value.to_owned()),
+        }
+    }
+
+    #[test]
+    fn arguments_keep_their_position() {
+        let declared = ["first", "second", "third"].map(str::to_owned);
+
+        // options are matched by name, not by the order they were sent in
+        let options = [option("third", "c"), option("first", "multiple words here")];
+        assert_eq!(
+            tag_command_arguments(&declared, &options),
+            ["multiple words here", "", "c"]
+        );
+
+        let options = [option("first", "a"), option("second", " b  c ")];
+        assert_eq!(tag_command_arguments(&declared, &options), ["a", " b  c ", ""]);
+    }
+}
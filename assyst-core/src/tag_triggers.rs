@@ -0,0 +1,178 @@
+//! Tag triggers (auto-responders): tags that run automatically when a message in a guild matches a
+//! pattern, without being invoked through the `tag` command.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use assyst_database::model::global_blacklist::GlobalBlacklist;
+use assyst_database::model::tag::Tag;
+use assyst_database::model::tag_trigger::{TagTrigger, TriggerKind};
+use moka::sync::Cache;
+use regex::{Regex, RegexBuilder};
+use tracing::debug;
+use twilight_model::channel::message::MessageType;
+use twilight_model::channel::Message;
+
+use crate::assyst::ThreadSafeAssyst;
+use crate::command::misc::tag::{execute_tag, tag_response};
+use crate::command::source::Source;
+use crate::command::{CommandCtxt, CommandData, ExecutionTimings};
+
+/// Trigger cooldowns are stored in `CommandRatelimits` under this name, keyed by the trigger ID.
+pub const TRIGGER_RATELIMIT_NAME: &str = "tag trigger";
+pub const MAX_TRIGGERS_PER_GUILD: usize = 25;
+pub const DEFAULT_TRIGGER_COOLDOWN_SECONDS: i32 = 5;
+/// `CommandRatelimits` entries expire after five minutes without being accessed, so longer
+/// cooldowns would not be enforced.
+pub const MAX_TRIGGER_COOLDOWN_SECONDS: i32 = 300;
+pub const MAX_PATTERN_LENGTH: usize = 200;
+/// Maximum size of a compiled trigger regex, in bytes
+const MAX_REGEX_SIZE: usize = 1 << 16;
+
+/// Compiled regexes of regex triggers, so they are not compiled again for every message
+static REGEXES: LazyLock<Cache<String, Regex>> = LazyLock::new(|| {
+    Cache::builder()
+        .max_capacity(1000)
+        .time_to_idle(Duration::from_secs(60 * 10))
+        .build()
+});
+
+pub fn compile_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).size_limit(MAX_REGEX_SIZE).build()
+}
+
+/// Checks if `word` appears in `content` surrounded by non-alphanumeric characters, ignoring case
+fn contains_word(content: &str, word: &str) -> bool {
+    let content = content.to_lowercase();
+    let word = word.to_lowercase();
+    let is_boundary = |c: Option<char>| !c.is_some_and(char::is_alphanumeric);
+
+    content.match_indices(&word).any(|(idx, _)| {
+        is_boundary(content[..idx].chars().next_back()) && is_boundary(content[idx + word.len()..].chars().next())
+    })
+}
+
+/// Returns the arguments to run the tag of the trigger with, or `None` if the message does not
+/// match the trigger.
+///
+/// Prefix triggers pass the words following the prefix, regex triggers pass their capture groups.
+fn match_trigger(trigger: &TagTrigger, content: &str) -> Option<Vec<String>> {
+    match trigger.kind()? {
+        TriggerKind::Word => contains_word(content, &trigger.pattern).then(Vec::new),
+        TriggerKind::Prefix => {
+            let prefix = content.get(..trigger.pattern.len())?;
+            if !prefix.eq_ignore_ascii_case(&trigger.pattern) {
+                return None;
+            }
+
+            Some(
+                content[trigger.pattern.len()..]
+                    .split_ascii_whitespace()
+                    .map(str::to_owned)
+                    .collect(),
+            )
+        },
+        TriggerKind::Regex => {
+            let regex = match REGEXES.get(&trigger.pattern) {
+                Some(regex) => regex,
+                None => {
+                    // the pattern was validated when the trigger was created
+                    let regex = compile_regex(&trigger.pattern).ok()?;
+                    REGEXES.insert(trigger.pattern.clone(), regex.clone());
+                    regex
+                },
+            };
+
+            let captures = regex.captures(content)?;
+            Some(
+                captures
+                    .iter()
+                    .skip(1)
+                    .map(|group| group.map_or("", |group| group.as_str()).to_owned())
+                    .collect(),
+            )
+        },
+    }
+}
+
+/// Runs the first trigger of the guild that matches the message, if any.
+///
+/// Only one trigger runs per message, so that a single message can not cause a flood of responses.
+pub async fn handle_message(assyst: &ThreadSafeAssyst, message: &Message) -> anyhow::Result<()> {
+    let Some(guild_id) = message.guild_id else {
+        return Ok(());
+    };
+
+    if message.author.bot
+        || message.webhook_id.is_some()
+        || !matches!(message.kind, MessageType::Regular | MessageType::Reply)
+    {
+        return Ok(());
+    }
+
+    let triggers = TagTrigger::list_in_guild(&assyst.database_handler, guild_id.get() as i64).await?;
+    let channel_id = message.channel_id.get() as i64;
+
+    let Some((trigger, arguments)) = triggers
+        .iter()
+        .filter(|trigger| trigger.channels.is_empty() || trigger.channels.contains(&channel_id))
+        .find_map(|trigger| match_trigger(trigger, &message.content).map(|arguments| (trigger, arguments)))
+    else {
+        return Ok(());
+    };
+
+    // checked after matching to prevent looking up the author of every message
+    if GlobalBlacklist::is_blacklisted(&assyst.database_handler, message.author.id.get()).await? {
+        return Ok(());
+    }
+
+    let ratelimit_id = trigger.id as u64;
+    if let Some(last_run) = assyst.command_ratelimits.get(ratelimit_id, TRIGGER_RATELIMIT_NAME)
+        && last_run.elapsed() < Duration::from_secs(trigger.cooldown_seconds as u64)
+    {
+        return Ok(());
+    }
+    assyst
+        .command_ratelimits
+        .insert(ratelimit_id, TRIGGER_RATELIMIT_NAME, Instant::now());
+
+    let Some(tag) = Tag::get(&assyst.database_handler, guild_id.get() as i64, &trigger.tag_name).await? else {
+        return Ok(());
+    };
+
+    let data = CommandData {
+        source: Source::RawMessage,
+        assyst,
+        execution_timings: ExecutionTimings {
+            preprocess_total: Duration::ZERO,
+            prefix_determiner: Duration::ZERO,
+            parse_total: Duration::ZERO,
+            metadata_check_start: Instant::now(),
+            processing_time_start: Instant::now(),
+        },
+        calling_prefix: String::new(),
+        message: Some(message),
+        interaction_subcommand: None,
+        channel_id: message.channel_id,
+        guild_id: message.guild_id,
+        author: message.author.clone(),
+        interaction_token: None,
+        interaction_id: None,
+        interaction_attachments: HashMap::new(),
+        command_from_install_context: false,
+        resolved_messages: None,
+        resolved_users: None,
+    };
+
+    let ctxt = CommandCtxt::new(&data);
+    match execute_tag(&ctxt, &tag, arguments).await? {
+        // a trigger runs on messages that were not meant for the bot, so it stays silent unless the
+        // tag has something to say
+        Ok(result) if result.output.trim().is_empty() && result.attachments.is_empty() && result.embed.is_none() => {},
+        Ok(result) => ctxt.reply(tag_response(result)?).await?,
+        Err(err) => debug!("Tag trigger {} failed: {err:?}", trigger.id),
+    }
+
+    Ok(())
+}
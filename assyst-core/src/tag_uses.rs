@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use assyst_common::util::unix_timestamp;
+use assyst_database::model::tag_usage::TagUsage;
+
+/// Uses of tags that were not written to the database yet, in the format <(guild id, tag name) =>
+/// (uses, time of last use)>.
+///
+/// Popular tags can be used many times per second, so uses are counted here and written to the
+/// database in batches by the `flush_tag_uses` task instead of once per use.
+pub struct TagUses(Mutex<HashMap<(u64, String), (i64, i64)>>);
+impl TagUses {
+    pub fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+
+    pub fn record(&self, guild_id: u64, tag_name: &str) {
+        let mut uses = self.0.lock().unwrap();
+        let entry = uses.entry((guild_id, tag_name.to_owned())).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 = unix_timestamp() as i64;
+    }
+
+    /// Uses of a tag that were not written to the database yet
+    pub fn pending(&self, guild_id: u64, tag_name: &str) -> Option<(i64, i64)> {
+        self.0.lock().unwrap().get(&(guild_id, tag_name.to_owned())).copied()
+    }
+
+    /// Takes all pending uses, leaving none behind
+    pub fn take(&self) -> Vec<TagUsage> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+            .into_iter()
+            .map(|((guild_id, tag_name), (uses, last_used))| TagUsage {
+                guild_id: guild_id as i64,
+                tag_name,
+                uses,
+                last_used,
+            })
+            .collect()
+    }
+
+    /// Puts back uses that could not be written to the database, so they are retried next time
+    pub fn restore(&self, usages: Vec<TagUsage>) {
+        let mut uses = self.0.lock().unwrap();
+        for usage in usages {
+            let entry = uses.entry((usage.guild_id as u64, usage.tag_name)).or_insert((0, 0));
+            entry.0 += usage.uses;
+            entry.1 = entry.1.max(usage.last_used);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 pub mod get_premium_users;
 pub mod refresh_entitlements;
 pub mod reminders;
+pub mod tag_uses;
 pub mod top_gg_stats;
@@ -0,0 +1,22 @@
+use assyst_common::err;
+use assyst_database::model::tag_usage::TagUsage;
+use tracing::debug;
+
+use crate::assyst::ThreadSafeAssyst;
+
+// 1 minute
+pub const FLUSH_INTERVAL_SECONDS: u64 = 60;
+
+pub async fn flush_tag_uses(assyst: ThreadSafeAssyst) {
+    let usages = assyst.tag_uses.take();
+    if usages.is_empty() {
+        return;
+    }
+
+    debug!("Writing uses of {} tags", usages.len());
+
+    if let Err(e) = TagUsage::add_uses(&assyst.database_handler, &usages).await {
+        err!("Failed to write tag uses: {}", e.to_string());
+        assyst.tag_uses.restore(usages);
+    }
+}
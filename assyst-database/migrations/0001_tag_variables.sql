@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS tag_variables (
+    guild_id BIGINT NOT NULL,
+    -- empty for variables shared by every tag in the guild
+    tag_name TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (guild_id, tag_name, key)
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS tag_time_budgets (
+    guild_id BIGINT PRIMARY KEY,
+    milliseconds BIGINT NOT NULL
+);
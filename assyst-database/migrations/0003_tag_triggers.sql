@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS tag_triggers (
+    id SERIAL PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    tag_name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    pattern TEXT NOT NULL,
+    -- empty if the trigger runs in every channel
+    channels BIGINT[] NOT NULL DEFAULT '{}',
+    cooldown_seconds INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS tag_triggers_guild_id_tag_name ON tag_triggers (guild_id, tag_name);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS tag_commands (
+    guild_id BIGINT NOT NULL,
+    tag_name TEXT NOT NULL,
+    arguments TEXT[] NOT NULL DEFAULT '{}',
+    PRIMARY KEY (guild_id, tag_name)
+);
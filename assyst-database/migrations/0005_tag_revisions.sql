@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS tag_revisions (
+    guild_id BIGINT NOT NULL,
+    tag_name TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    -- empty for deletions
+    data TEXT NOT NULL,
+    author BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    PRIMARY KEY (guild_id, tag_name, revision)
+);
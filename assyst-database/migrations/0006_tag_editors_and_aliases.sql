@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS tag_editors (
+    guild_id BIGINT NOT NULL,
+    tag_name TEXT NOT NULL,
+    user_id BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, tag_name, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS tag_aliases (
+    guild_id BIGINT NOT NULL,
+    alias TEXT NOT NULL,
+    -- the name of the tag the alias points to
+    tag_name TEXT NOT NULL,
+    PRIMARY KEY (guild_id, alias)
+);
+
+CREATE INDEX IF NOT EXISTS tag_aliases_guild_id_tag_name ON tag_aliases (guild_id, tag_name);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS tag_uses (
+    guild_id BIGINT NOT NULL,
+    tag_name TEXT NOT NULL,
+    uses BIGINT NOT NULL,
+    -- unix timestamp in milliseconds
+    last_used BIGINT NOT NULL,
+    PRIMARY KEY (guild_id, tag_name)
+);
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS published_tags (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    data TEXT NOT NULL,
+    author BIGINT NOT NULL,
+    source_guild_id BIGINT NOT NULL,
+    source_tag_name TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    installs BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL,
+    -- publishing the same tag again updates the published copy
+    UNIQUE (source_guild_id, source_tag_name)
+);
+
+CREATE TABLE IF NOT EXISTS tag_installs (
+    guild_id BIGINT NOT NULL,
+    tag_name TEXT NOT NULL,
+    published_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, tag_name)
+);
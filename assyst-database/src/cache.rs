@@ -1,13 +1,16 @@
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::mem::size_of;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 use moka::sync::Cache;
 
 use crate::model::colour_role::ColourRole;
 use crate::model::prefix::Prefix;
+use crate::model::tag_command::TagCommand;
+use crate::model::tag_time_budget::TagTimeBudget;
+use crate::model::tag_trigger::TagTrigger;
 
 trait TCacheV = Send + Sync + Clone + 'static;
 trait TCacheK = Hash + Send + Sync + Eq + Clone + 'static;
@@ -34,6 +37,14 @@ pub struct DatabaseCache {
     copied_tags: Cache<u64 /* user id */, String /* content */>,
     guild_tag_names: Cache<u64, Vec<(u64 /* author id */, String)>>,
     guild_colour_roles: Cache<u64, Vec<ColourRole>>,
+    tag_time_budgets: Cache<u64, Option<TagTimeBudget> /* None if the guild uses the default */>,
+    guild_tag_triggers: Cache<u64, Vec<TagTrigger>>,
+    /// Loaded when connecting to the database, so that guilds without triggers are never looked up
+    guilds_with_tag_triggers: RwLock<HashSet<u64>>,
+    guild_tag_commands: Cache<u64, Vec<TagCommand>>,
+    /// Loaded when connecting to the database, so that guilds without tag commands are never
+    /// looked up
+    guilds_with_tag_commands: RwLock<HashSet<u64>>,
 }
 impl DatabaseCache {
     pub fn new() -> Self {
@@ -44,6 +55,11 @@ impl DatabaseCache {
             copied_tags: default_cache_sized(u64::MAX),
             guild_tag_names: default_cache(),
             guild_colour_roles: default_cache(),
+            tag_time_budgets: default_cache(),
+            guild_tag_triggers: default_cache(),
+            guilds_with_tag_triggers: RwLock::new(HashSet::new()),
+            guild_tag_commands: default_cache(),
+            guilds_with_tag_commands: RwLock::new(HashSet::new()),
         }
     }
 
@@ -160,6 +176,62 @@ impl DatabaseCache {
     pub fn get_guild_colour_roles(&self, guild_id: u64) -> Option<Vec<ColourRole>> {
         self.guild_colour_roles.get(&guild_id)
     }
+
+    pub fn set_tag_time_budget(&self, guild_id: u64, budget: Option<TagTimeBudget>) {
+        self.tag_time_budgets.insert(guild_id, budget);
+    }
+
+    pub fn get_tag_time_budget(&self, guild_id: u64) -> Option<Option<TagTimeBudget>> {
+        self.tag_time_budgets.get(&guild_id)
+    }
+
+    pub fn insert_guild_tag_triggers(&self, guild_id: u64, triggers: Vec<TagTrigger>) {
+        self.guild_tag_triggers.insert(guild_id, triggers);
+    }
+
+    pub fn get_guild_tag_triggers(&self, guild_id: u64) -> Option<Vec<TagTrigger>> {
+        self.guild_tag_triggers.get(&guild_id)
+    }
+
+    pub fn invalidate_guild_tag_triggers(&self, guild_id: u64) {
+        self.guild_tag_triggers.invalidate(&guild_id);
+    }
+
+    pub fn set_guilds_with_tag_triggers(&self, guild_ids: HashSet<u64>) {
+        *self.guilds_with_tag_triggers.write().unwrap() = guild_ids;
+    }
+
+    pub fn add_guild_with_tag_triggers(&self, guild_id: u64) {
+        self.guilds_with_tag_triggers.write().unwrap().insert(guild_id);
+    }
+
+    pub fn guild_has_tag_triggers(&self, guild_id: u64) -> bool {
+        self.guilds_with_tag_triggers.read().unwrap().contains(&guild_id)
+    }
+
+    pub fn insert_guild_tag_commands(&self, guild_id: u64, commands: Vec<TagCommand>) {
+        self.guild_tag_commands.insert(guild_id, commands);
+    }
+
+    pub fn get_guild_tag_commands(&self, guild_id: u64) -> Option<Vec<TagCommand>> {
+        self.guild_tag_commands.get(&guild_id)
+    }
+
+    pub fn invalidate_guild_tag_commands(&self, guild_id: u64) {
+        self.guild_tag_commands.invalidate(&guild_id);
+    }
+
+    pub fn set_guilds_with_tag_commands(&self, guild_ids: HashSet<u64>) {
+        *self.guilds_with_tag_commands.write().unwrap() = guild_ids;
+    }
+
+    pub fn add_guild_with_tag_commands(&self, guild_id: u64) {
+        self.guilds_with_tag_commands.write().unwrap().insert(guild_id);
+    }
+
+    pub fn guild_has_tag_commands(&self, guild_id: u64) -> bool {
+        self.guilds_with_tag_commands.read().unwrap().contains(&guild_id)
+    }
 }
 
 impl Default for DatabaseCache {
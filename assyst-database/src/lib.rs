@@ -3,6 +3,8 @@
 use std::borrow::Cow;
 
 use cache::DatabaseCache;
+use model::tag_command::TagCommand;
+use model::tag_trigger::TagTrigger;
 use sqlx::postgres::{PgPool, PgPoolOptions};
 use tracing::info;
 
@@ -40,8 +42,16 @@ impl DatabaseHandler {
             .await?;
 
         info!("Connected to database on {}", safe_url);
+
+        // creates the tables of features added after the initial schema, see `migrations/`
+        sqlx::migrate!().run(&pool).await?;
+
         let cache = DatabaseCache::new();
-        Ok(Self { pool, cache })
+        let handler = Self { pool, cache };
+        TagTrigger::cache_guilds_with_triggers(&handler).await?;
+        TagCommand::cache_guilds_with_commands(&handler).await?;
+
+        Ok(handler)
     }
 
     pub async fn database_size(&self) -> anyhow::Result<DatabaseSize> {
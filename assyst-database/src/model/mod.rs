@@ -7,6 +7,16 @@ pub mod free_tier_2_requests;
 pub mod global_blacklist;
 pub mod guild_disabled_command;
 pub mod prefix;
+pub mod published_tag;
 pub mod reminder;
 pub mod tag;
+pub mod tag_alias;
+pub mod tag_command;
+pub mod tag_editor;
+pub mod tag_install;
+pub mod tag_revision;
+pub mod tag_time_budget;
+pub mod tag_trigger;
+pub mod tag_usage;
+pub mod tag_variable;
 pub mod user_votes;
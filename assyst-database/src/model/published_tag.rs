@@ -0,0 +1,108 @@
+use crate::{Count, DatabaseHandler};
+
+/// A published tag is a copy of a tag in the global tag library, which any guild can install.
+/// Publishing the same tag again updates the published copy and increases its version.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct PublishedTag {
+    pub id: i32,
+    pub name: String,
+    pub description: String,
+    pub data: String,
+    pub author: i64,
+    /// The guild and name of the tag this was published from
+    pub source_guild_id: i64,
+    pub source_tag_name: String,
+    /// Starts at 1 and increases by one every time the tag is published again
+    pub version: i32,
+    pub installs: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+impl PublishedTag {
+    /// Publish a tag, or update the published copy if the tag was published before. `id`,
+    /// `version`, `installs` and `created_at` are ignored for updates. Returns the id and version
+    /// of the published tag, or `None` if another user published a tag with the same name from the
+    /// same guild. Only the author of a published tag can update it: if their tag is deleted and
+    /// someone else creates a tag with its name, publishing that tag must not replace theirs.
+    pub async fn publish(&self, handler: &DatabaseHandler) -> Result<Option<(i32, i32)>, sqlx::Error> {
+        let query = r"INSERT INTO published_tags (name, description, data, author, source_guild_id, source_tag_name, version, installs, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, 1, 0, $7, $7)
+            ON CONFLICT (source_guild_id, source_tag_name) DO UPDATE SET name = $1, description = $2, data = $3,
+            version = published_tags.version + 1, updated_at = $7
+            WHERE published_tags.author = EXCLUDED.author
+            RETURNING id, version";
+
+        sqlx::query_as(query)
+            .bind(&self.name)
+            .bind(&self.description)
+            .bind(&self.data)
+            .bind(self.author)
+            .bind(self.source_guild_id)
+            .bind(&self.source_tag_name)
+            .bind(self.updated_at)
+            .fetch_optional(&handler.pool)
+            .await
+    }
+
+    pub async fn get(handler: &DatabaseHandler, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM published_tags WHERE id = $1";
+
+        let result = sqlx::query_as(query).bind(id).fetch_one(&handler.pool).await;
+
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Published tags whose name or description contains `search`, most installed first. An empty
+    /// search matches every published tag.
+    pub async fn search(
+        handler: &DatabaseHandler,
+        search: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM published_tags WHERE position(lower($1) in lower(name || ' ' || description)) > 0
+            ORDER BY installs DESC, id OFFSET $2 LIMIT $3";
+
+        sqlx::query_as(query)
+            .bind(search)
+            .bind(offset)
+            .bind(limit)
+            .fetch_all(&handler.pool)
+            .await
+    }
+
+    pub async fn get_search_count(handler: &DatabaseHandler, search: &str) -> Result<i64, sqlx::Error> {
+        let query =
+            r"SELECT count(*) FROM published_tags WHERE position(lower($1) in lower(name || ' ' || description)) > 0";
+
+        let count: Count = sqlx::query_as(query).bind(search).fetch_one(&handler.pool).await?;
+
+        Ok(count.count)
+    }
+
+    pub(crate) async fn increment_installs_in<'e>(
+        executor: impl sqlx::PgExecutor<'e>,
+        id: i32,
+    ) -> Result<(), sqlx::Error> {
+        let query = r"UPDATE published_tags SET installs = installs + 1 WHERE id = $1";
+
+        sqlx::query(query).bind(id).execute(executor).await.map(|_| ())
+    }
+
+    /// Remove a tag from the library. True on successful delete, false if the tag does not exist
+    /// or was published by someone else.
+    pub async fn unpublish(handler: &DatabaseHandler, id: i32, author: i64) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM published_tags WHERE id = $1 AND author = $2";
+
+        sqlx::query(query)
+            .bind(id)
+            .bind(author)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)
+    }
+}
@@ -1,3 +1,6 @@
+use sqlx::PgConnection;
+
+use crate::model::tag_revision::{TagRevision, TagRevisionKind};
 use crate::{is_unique_violation, Count, DatabaseHandler};
 
 #[derive(sqlx::FromRow, Debug, Clone)]
@@ -9,8 +12,9 @@ pub struct Tag {
     pub created_at: i64,
 }
 impl Tag {
+    /// Fetch a tag by its name or by one of its aliases. The returned tag always has its real name.
     pub async fn get(handler: &DatabaseHandler, guild_id: i64, name: &str) -> anyhow::Result<Option<Self>> {
-        let query = r"SELECT * FROM tags WHERE name = $1 AND guild_id = $2";
+        let query = r"SELECT * FROM tags WHERE name = COALESCE((SELECT tag_name FROM tag_aliases WHERE alias = $1 AND guild_id = $2), $1) AND guild_id = $2";
 
         let result = sqlx::query_as(query)
             .bind(name)
@@ -26,6 +30,11 @@ impl Tag {
     }
 
     pub async fn set(&self, handler: &DatabaseHandler) -> Result<bool, sqlx::Error> {
+        self.set_in(&handler.pool).await
+    }
+
+    /// Like [`Tag::set`], but on any executor, e.g. a transaction.
+    pub(crate) async fn set_in<'e>(&self, executor: impl sqlx::PgExecutor<'e>) -> Result<bool, sqlx::Error> {
         let query = r"INSERT INTO tags VALUES ($1, $2, $3, $4, $5)";
 
         sqlx::query(query)
@@ -34,53 +43,230 @@ impl Tag {
             .bind(self.author)
             .bind(self.guild_id)
             .bind(self.created_at)
-            .execute(&handler.pool)
+            .execute(executor)
             .await
             .map(|_| true)
             .or_else(|e| if is_unique_violation(&e) { Ok(false) } else { Err(e) })
     }
 
-    pub async fn delete_force(handler: &DatabaseHandler, name: &str, guild_id: i64) -> Result<bool, sqlx::Error> {
-        let query = r"DELETE FROM tags WHERE name = $1 AND guild_id = $2";
+    /// Like [`Tag::set`], but also records `revision` in the same transaction. Returns the assigned
+    /// revision number, or `None` if the tag name is already used.
+    pub async fn set_with_revision(
+        &self,
+        handler: &DatabaseHandler,
+        revision: &TagRevision,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let mut transaction = handler.pool.begin().await?;
 
-        sqlx::query(query)
-            .bind(name)
-            .bind(guild_id)
-            .execute(&handler.pool)
-            .await
-            .map(|rows| rows.rows_affected() > 0)
-            .or_else(|e| if is_unique_violation(&e) { Ok(false) } else { Err(e) })
+        // a failed insert aborts the transaction, so nothing else can be done in it
+        if !self.set_in(&mut *transaction).await? {
+            return Ok(None);
+        }
+
+        let revision = revision.record_in(&mut *transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(revision))
     }
 
-    pub async fn delete(
+    /// Create many tags at once, in a single transaction. If `overwrite` is set, existing tags with
+    /// the same name and author are replaced, otherwise existing tags are left unchanged. Every
+    /// created or replaced tag gets a revision by the author of the imported tag, in the same
+    /// transaction.
+    pub async fn import(handler: &DatabaseHandler, tags: &[Tag], overwrite: bool) -> Result<(), sqlx::Error> {
+        // xmax is only 0 for newly inserted rows
+        let query = if overwrite {
+            r"INSERT INTO tags VALUES ($1, $2, $3, $4, $5) ON CONFLICT (name, guild_id) DO UPDATE SET data = EXCLUDED.data WHERE tags.author = EXCLUDED.author RETURNING (xmax = 0)"
+        } else {
+            r"INSERT INTO tags VALUES ($1, $2, $3, $4, $5) ON CONFLICT (name, guild_id) DO NOTHING RETURNING (xmax = 0)"
+        };
+        let mut transaction = handler.pool.begin().await?;
+
+        for tag in tags {
+            if overwrite {
+                Self::record_initial_revision_in(&mut *transaction, tag.guild_id, &tag.name).await?;
+            }
+
+            let inserted: Option<(bool,)> = sqlx::query_as(query)
+                .bind(&tag.name)
+                .bind(&tag.data)
+                .bind(tag.author)
+                .bind(tag.guild_id)
+                .bind(tag.created_at)
+                .fetch_optional(&mut *transaction)
+                .await?;
+
+            if let Some((inserted,)) = inserted {
+                let kind = if inserted {
+                    TagRevisionKind::Create
+                } else {
+                    TagRevisionKind::Edit
+                };
+
+                TagRevision {
+                    guild_id: tag.guild_id,
+                    tag_name: tag.name.clone(),
+                    revision: 0,
+                    data: tag.data.clone(),
+                    author: tag.author,
+                    created_at: tag.created_at,
+                    kind: kind.as_str().to_owned(),
+                }
+                .record_in(&mut *transaction)
+                .await?;
+            }
+        }
+
+        transaction.commit().await
+    }
+
+    /// Tags created before revisions were recorded have no history, so their current content is
+    /// recorded as their first revision before they are changed.
+    pub async fn record_initial_revision(
         handler: &DatabaseHandler,
+        guild_id: i64,
         name: &str,
+    ) -> Result<(), sqlx::Error> {
+        Self::record_initial_revision_in(&handler.pool, guild_id, name).await
+    }
+
+    /// Like [`Tag::record_initial_revision`], but on any executor, e.g. a transaction.
+    pub(crate) async fn record_initial_revision_in<'e>(
+        executor: impl sqlx::PgExecutor<'e>,
         guild_id: i64,
-        author: i64,
-    ) -> Result<bool, sqlx::Error> {
-        let query = r"DELETE FROM tags WHERE name = $1 AND author = $2 AND guild_id = $3";
+        name: &str,
+    ) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO tag_revisions (guild_id, tag_name, revision, data, author, created_at, kind)
+            SELECT guild_id, name, 1, data, author, created_at, $3 FROM tags
+            WHERE guild_id = $1 AND name = $2
+            AND NOT EXISTS (SELECT 1 FROM tag_revisions WHERE guild_id = $1 AND tag_name = $2)";
 
         sqlx::query(query)
-            .bind(name)
-            .bind(author)
             .bind(guild_id)
-            .execute(&handler.pool)
+            .bind(name)
+            .bind(TagRevisionKind::Create.as_str())
+            .execute(executor)
             .await
-            .map(|rows| rows.rows_affected() > 0)
-            .or_else(|e| if is_unique_violation(&e) { Ok(false) } else { Err(e) })
+            .map(|_| ())
     }
 
+    /// Delete a tag along with everything that belongs to it: its variables, triggers, editors,
+    /// aliases, usage statistics, installation and command. If `author` is set, the tag is only
+    /// deleted if it is owned by them. The deletion is recorded as `revision`, whose guild and tag
+    /// name select the tag to delete, in the same transaction. False if no tag was deleted.
+    pub async fn delete_with_dependents(
+        handler: &DatabaseHandler,
+        author: Option<i64>,
+        revision: &TagRevision,
+    ) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM tags WHERE name = $1 AND guild_id = $2 AND ($3::BIGINT IS NULL OR author = $3)";
+        let dependents = [
+            r"DELETE FROM tag_variables WHERE tag_name = $1 AND guild_id = $2",
+            r"DELETE FROM tag_triggers WHERE tag_name = $1 AND guild_id = $2",
+            r"DELETE FROM tag_editors WHERE tag_name = $1 AND guild_id = $2",
+            r"DELETE FROM tag_aliases WHERE tag_name = $1 AND guild_id = $2",
+            r"DELETE FROM tag_uses WHERE tag_name = $1 AND guild_id = $2",
+            r"DELETE FROM tag_installs WHERE tag_name = $1 AND guild_id = $2",
+            r"DELETE FROM tag_commands WHERE tag_name = $1 AND guild_id = $2",
+        ];
+        let (name, guild_id) = (&revision.tag_name, revision.guild_id);
+
+        let mut transaction = handler.pool.begin().await?;
+
+        // the deleted content can still be restored even if the tag had no history
+        Self::record_initial_revision_in(&mut *transaction, guild_id, name).await?;
+
+        let deleted = sqlx::query(query)
+            .bind(name)
+            .bind(guild_id)
+            .bind(author)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected()
+            > 0;
+        if !deleted {
+            return Ok(false);
+        }
+
+        for query in dependents {
+            sqlx::query(query)
+                .bind(name)
+                .bind(guild_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        revision.record_in(&mut *transaction).await?;
+
+        transaction.commit().await?;
+
+        handler.cache.invalidate_guild_tag_triggers(guild_id as u64);
+        handler.cache.invalidate_guild_tag_commands(guild_id as u64);
+
+        Ok(true)
+    }
+
+    /// Replace the content of a tag owned by `author` with the content of `revision`, and record
+    /// the revision in the same transaction. The guild and tag name of `revision` select the tag.
+    ///
+    /// Returns the assigned revision number, or `None` if the tag does not exist or is not owned
+    /// by `author`.
     pub async fn edit(
         handler: &DatabaseHandler,
         author: i64,
+        revision: &TagRevision,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let mut transaction = handler.pool.begin().await?;
+
+        let Some(revision) = Self::edit_in(&mut *transaction, author, revision).await? else {
+            return Ok(None);
+        };
+
+        transaction.commit().await?;
+
+        Ok(Some(revision))
+    }
+
+    /// Like [`Tag::edit`], but on a connection that is already in a transaction.
+    pub(crate) async fn edit_in(
+        connection: &mut PgConnection,
+        author: i64,
+        revision: &TagRevision,
+    ) -> Result<Option<i32>, sqlx::Error> {
+        let query = r"UPDATE tags SET data = $1 WHERE name = $2 AND author = $3 AND guild_id = $4";
+
+        Self::record_initial_revision_in(&mut *connection, revision.guild_id, &revision.tag_name).await?;
+
+        let edited = sqlx::query(query)
+            .bind(&revision.data)
+            .bind(&revision.tag_name)
+            .bind(author)
+            .bind(revision.guild_id)
+            .execute(&mut *connection)
+            .await?
+            .rows_affected()
+            > 0;
+        if !edited {
+            return Ok(None);
+        }
+
+        revision.record_in(&mut *connection).await.map(Some)
+    }
+
+    /// Transfer a tag to a new owner. False if the tag does not exist or is no longer owned by
+    /// `author`.
+    pub async fn transfer(
+        handler: &DatabaseHandler,
         guild_id: i64,
         name: &str,
-        new_content: &str,
+        author: i64,
+        new_author: i64,
     ) -> Result<bool, sqlx::Error> {
-        let query = r"UPDATE tags SET data = $1 WHERE name = $2 AND author = $3 AND guild_id = $4";
+        let query = r"UPDATE tags SET author = $1 WHERE name = $2 AND author = $3 AND guild_id = $4";
 
         sqlx::query(query)
-            .bind(new_content)
+            .bind(new_author)
             .bind(name)
             .bind(author)
             .bind(guild_id)
@@ -0,0 +1,77 @@
+use crate::{is_unique_violation, DatabaseHandler};
+
+/// A tag alias is an additional name of a tag. [`Tag::get`](super::tag::Tag::get) resolves aliases
+/// to the tag they point to, so aliases can be used anywhere a tag name is expected.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagAlias {
+    pub guild_id: i64,
+    pub alias: String,
+    /// The name of the tag this alias points to
+    pub tag_name: String,
+}
+impl TagAlias {
+    /// Fetch an alias by its name, if it exists.
+    pub async fn get(handler: &DatabaseHandler, guild_id: i64, alias: &str) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_aliases WHERE guild_id = $1 AND alias = $2";
+
+        let result = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(alias)
+            .fetch_one(&handler.pool)
+            .await;
+
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// List the aliases of a tag.
+    pub async fn list_for_tag(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_aliases WHERE guild_id = $1 AND tag_name = $2 ORDER BY alias";
+
+        sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .fetch_all(&handler.pool)
+            .await
+    }
+
+    /// List all aliases in a guild.
+    pub async fn list_in_guild(handler: &DatabaseHandler, guild_id: i64) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_aliases WHERE guild_id = $1";
+
+        sqlx::query_as(query).bind(guild_id).fetch_all(&handler.pool).await
+    }
+
+    /// Create a new alias. False if the alias already exists.
+    pub async fn set(&self, handler: &DatabaseHandler) -> Result<bool, sqlx::Error> {
+        let query = r"INSERT INTO tag_aliases VALUES ($1, $2, $3)";
+
+        sqlx::query(query)
+            .bind(self.guild_id)
+            .bind(&self.alias)
+            .bind(&self.tag_name)
+            .execute(&handler.pool)
+            .await
+            .map(|_| true)
+            .or_else(|e| if is_unique_violation(&e) { Ok(false) } else { Err(e) })
+    }
+
+    /// Delete an alias. True on successful delete, false if the alias did not exist.
+    pub async fn delete(handler: &DatabaseHandler, guild_id: i64, alias: &str) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM tag_aliases WHERE guild_id = $1 AND alias = $2";
+
+        sqlx::query(query)
+            .bind(guild_id)
+            .bind(alias)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)
+    }
+}
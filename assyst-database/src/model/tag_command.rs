@@ -0,0 +1,88 @@
+use crate::DatabaseHandler;
+
+/// A tag command is a tag that was promoted to a command of its guild, so it can be run as `name`
+/// or `/name` instead of through the tag command.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagCommand {
+    pub guild_id: i64,
+    /// The name of the tag, which is also the name of the command
+    pub tag_name: String,
+    /// Names of the arguments of the tag, in order. Used as the options of the slash command.
+    pub arguments: Vec<String>,
+}
+impl TagCommand {
+    /// Load the guilds that have tag commands into the cache. Called once when connecting to the
+    /// database, after which [`TagCommand::set`] keeps it up to date.
+    pub(crate) async fn cache_guilds_with_commands(handler: &DatabaseHandler) -> Result<(), sqlx::Error> {
+        let query = r"SELECT DISTINCT guild_id FROM tag_commands";
+
+        let guild_ids: Vec<(i64,)> = sqlx::query_as(query).fetch_all(&handler.pool).await?;
+        handler
+            .cache
+            .set_guilds_with_tag_commands(guild_ids.into_iter().map(|(id,)| id as u64).collect());
+
+        Ok(())
+    }
+
+    /// List all tag commands in a guild. This is looked up for every message that looks like an
+    /// unknown command, so the result is cached, and guilds that never had a tag command are not
+    /// looked up at all.
+    pub async fn list_in_guild(handler: &DatabaseHandler, guild_id: i64) -> Result<Vec<Self>, sqlx::Error> {
+        if !handler.cache.guild_has_tag_commands(guild_id as u64) {
+            return Ok(Vec::new());
+        }
+
+        if let Some(commands) = handler.cache.get_guild_tag_commands(guild_id as u64) {
+            return Ok(commands);
+        }
+
+        let query = r"SELECT * FROM tag_commands WHERE guild_id = $1 ORDER BY tag_name";
+
+        let commands: Vec<TagCommand> = sqlx::query_as(query).bind(guild_id).fetch_all(&handler.pool).await?;
+        handler
+            .cache
+            .insert_guild_tag_commands(guild_id as u64, commands.clone());
+
+        Ok(commands)
+    }
+
+    /// Fetch the tag command with the given name, if the tag was promoted.
+    pub async fn get(handler: &DatabaseHandler, guild_id: i64, name: &str) -> Result<Option<Self>, sqlx::Error> {
+        let commands = Self::list_in_guild(handler, guild_id).await?;
+
+        Ok(commands.into_iter().find(|command| command.tag_name == name))
+    }
+
+    /// Insert a new tag command, or replace the arguments of an existing one.
+    pub async fn set(&self, handler: &DatabaseHandler) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO tag_commands (guild_id, tag_name, arguments) VALUES ($1, $2, $3) ON CONFLICT (guild_id, tag_name) DO UPDATE SET arguments = $3";
+
+        sqlx::query(query)
+            .bind(self.guild_id)
+            .bind(&self.tag_name)
+            .bind(&self.arguments)
+            .execute(&handler.pool)
+            .await?;
+
+        handler.cache.add_guild_with_tag_commands(self.guild_id as u64);
+        handler.cache.invalidate_guild_tag_commands(self.guild_id as u64);
+
+        Ok(())
+    }
+
+    /// Delete a tag command. True on successful delete, false if the tag was not promoted.
+    pub async fn delete(handler: &DatabaseHandler, guild_id: i64, tag_name: &str) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM tag_commands WHERE guild_id = $1 AND tag_name = $2";
+
+        let deleted = sqlx::query(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)?;
+
+        handler.cache.invalidate_guild_tag_commands(guild_id as u64);
+
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{is_unique_violation, DatabaseHandler};
+
+/// A tag editor is a user other than the owner of a tag who is allowed to edit it.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagEditor {
+    pub guild_id: i64,
+    pub tag_name: String,
+    pub user_id: i64,
+}
+impl TagEditor {
+    /// List the editors of a tag.
+    pub async fn list_for_tag(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_editors WHERE guild_id = $1 AND tag_name = $2";
+
+        sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .fetch_all(&handler.pool)
+            .await
+    }
+
+    pub async fn is_editor(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+        user_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let query = r"SELECT * FROM tag_editors WHERE guild_id = $1 AND tag_name = $2 AND user_id = $3";
+
+        let result: Result<TagEditor, sqlx::Error> = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .bind(user_id)
+            .fetch_one(&handler.pool)
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(sqlx::Error::RowNotFound) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Add an editor to a tag. False if the user already is an editor of the tag.
+    pub async fn add(&self, handler: &DatabaseHandler) -> Result<bool, sqlx::Error> {
+        let query = r"INSERT INTO tag_editors VALUES ($1, $2, $3)";
+
+        sqlx::query(query)
+            .bind(self.guild_id)
+            .bind(&self.tag_name)
+            .bind(self.user_id)
+            .execute(&handler.pool)
+            .await
+            .map(|_| true)
+            .or_else(|e| if is_unique_violation(&e) { Ok(false) } else { Err(e) })
+    }
+
+    /// Remove an editor from a tag. True on successful removal, false if the user was not an
+    /// editor of the tag.
+    pub async fn remove(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+        user_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM tag_editors WHERE guild_id = $1 AND tag_name = $2 AND user_id = $3";
+
+        sqlx::query(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .bind(user_id)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::model::published_tag::PublishedTag;
+use crate::model::tag::Tag;
+use crate::model::tag_revision::TagRevision;
+use crate::DatabaseHandler;
+
+/// Links a tag to the published tag it was installed from, so that updates of the published tag
+/// can be pulled.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagInstall {
+    pub guild_id: i64,
+    pub tag_name: String,
+    /// The id of the [`PublishedTag`](super::published_tag::PublishedTag)
+    pub published_id: i32,
+    /// The version of the published tag that is installed
+    pub version: i32,
+}
+impl TagInstall {
+    pub async fn get(handler: &DatabaseHandler, guild_id: i64, tag_name: &str) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_installs WHERE guild_id = $1 AND tag_name = $2";
+
+        let result = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .fetch_one(&handler.pool)
+            .await;
+
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert a new installation, or update the installed version of an existing one.
+    pub async fn set(&self, handler: &DatabaseHandler) -> Result<(), sqlx::Error> {
+        self.set_in(&handler.pool).await
+    }
+
+    /// Like [`TagInstall::set`], but on any executor, e.g. a transaction.
+    async fn set_in<'e>(&self, executor: impl sqlx::PgExecutor<'e>) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO tag_installs (guild_id, tag_name, published_id, version) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (guild_id, tag_name) DO UPDATE SET published_id = $3, version = $4";
+
+        sqlx::query(query)
+            .bind(self.guild_id)
+            .bind(&self.tag_name)
+            .bind(self.published_id)
+            .bind(self.version)
+            .execute(executor)
+            .await
+            .map(|_| ())
+    }
+
+    /// Create `tag` as this installation, in one transaction with the installation itself,
+    /// `revision` and the install count of the published tag. False if the tag name is already
+    /// used.
+    pub async fn install(
+        &self,
+        handler: &DatabaseHandler,
+        tag: &Tag,
+        revision: &TagRevision,
+    ) -> Result<bool, sqlx::Error> {
+        let mut transaction = handler.pool.begin().await?;
+
+        if !tag.set_in(&mut *transaction).await? {
+            return Ok(false);
+        }
+
+        self.set_in(&mut *transaction).await?;
+        PublishedTag::increment_installs_in(&mut *transaction, self.published_id).await?;
+        revision.record_in(&mut *transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Replace the content of the installed tag, which is owned by `author`, with the content of
+    /// `revision` and store the newly installed version, in one transaction. False if the tag does
+    /// not exist or is no longer owned by `author`.
+    pub async fn update(
+        &self,
+        handler: &DatabaseHandler,
+        author: i64,
+        revision: &TagRevision,
+    ) -> Result<bool, sqlx::Error> {
+        let mut transaction = handler.pool.begin().await?;
+
+        if Tag::edit_in(&mut *transaction, author, revision).await?.is_none() {
+            return Ok(false);
+        }
+
+        self.set_in(&mut *transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+}
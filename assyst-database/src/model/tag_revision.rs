@@ -0,0 +1,118 @@
+use crate::{Count, DatabaseHandler};
+
+/// What happened to a tag in a [`TagRevision`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagRevisionKind {
+    Create,
+    Edit,
+    Delete,
+    Rollback,
+}
+impl TagRevisionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TagRevisionKind::Create => "create",
+            TagRevisionKind::Edit => "edit",
+            TagRevisionKind::Delete => "delete",
+            TagRevisionKind::Rollback => "rollback",
+        }
+    }
+}
+
+/// A tag revision records the content of a tag after every change, so that earlier versions of the
+/// tag can be viewed and restored. Revisions are kept when the tag is deleted.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagRevision {
+    pub guild_id: i64,
+    pub tag_name: String,
+    /// Starts at 1 and increases by one with every change of the tag
+    pub revision: i32,
+    /// The content of the tag after the change, empty for deletions
+    pub data: String,
+    /// The user who made the change
+    pub author: i64,
+    pub created_at: i64,
+    /// See [`TagRevisionKind`]
+    pub kind: String,
+}
+impl TagRevision {
+    /// Record a change of a tag. `revision` is ignored and assigned by the database instead.
+    /// Returns the assigned revision number.
+    pub async fn record(&self, handler: &DatabaseHandler) -> Result<i32, sqlx::Error> {
+        self.record_in(&handler.pool).await
+    }
+
+    /// Like [`TagRevision::record`], but on any executor, e.g. a transaction.
+    pub(crate) async fn record_in<'e>(&self, executor: impl sqlx::PgExecutor<'e>) -> Result<i32, sqlx::Error> {
+        let query = r"INSERT INTO tag_revisions (guild_id, tag_name, revision, data, author, created_at, kind)
+            SELECT $1, $2, COALESCE(MAX(revision), 0) + 1, $3, $4, $5, $6
+            FROM tag_revisions WHERE guild_id = $1 AND tag_name = $2
+            RETURNING revision";
+
+        let (revision,) = sqlx::query_as::<_, (i32,)>(query)
+            .bind(self.guild_id)
+            .bind(&self.tag_name)
+            .bind(&self.data)
+            .bind(self.author)
+            .bind(self.created_at)
+            .bind(&self.kind)
+            .fetch_one(executor)
+            .await?;
+
+        Ok(revision)
+    }
+
+    /// Fetch a single revision of a tag, if it exists.
+    pub async fn get(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+        revision: i32,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_revisions WHERE guild_id = $1 AND tag_name = $2 AND revision = $3";
+
+        let result = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .bind(revision)
+            .fetch_one(&handler.pool)
+            .await;
+
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Revisions of a tag, newest first.
+    pub async fn get_paged(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_revisions WHERE guild_id = $1 AND tag_name = $2 ORDER BY revision DESC OFFSET $3 LIMIT $4";
+
+        sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .bind(offset)
+            .bind(limit)
+            .fetch_all(&handler.pool)
+            .await
+    }
+
+    pub async fn get_count(handler: &DatabaseHandler, guild_id: i64, tag_name: &str) -> Result<i64, sqlx::Error> {
+        let query = r"SELECT count(*) FROM tag_revisions WHERE guild_id = $1 AND tag_name = $2";
+
+        let count: Count = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .fetch_one(&handler.pool)
+            .await?;
+
+        Ok(count.count)
+    }
+}
@@ -0,0 +1,58 @@
+use crate::DatabaseHandler;
+
+/// The tag time budget is how long tags in a guild may run for before they are aborted, in
+/// milliseconds. Guilds without a row use the default budget of the tag parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TagTimeBudget {
+    pub milliseconds: u64,
+}
+impl TagTimeBudget {
+    pub async fn set(&self, handler: &DatabaseHandler, guild_id: u64) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO tag_time_budgets(guild_id, milliseconds) VALUES($1, $2) ON CONFLICT (guild_id) DO UPDATE SET milliseconds = $2 WHERE tag_time_budgets.guild_id = $1";
+
+        sqlx::query(query)
+            .bind(guild_id as i64)
+            .bind(self.milliseconds as i64)
+            .execute(&handler.pool)
+            .await?;
+
+        handler.cache.set_tag_time_budget(guild_id, Some(*self));
+
+        Ok(())
+    }
+
+    /// Resets the budget of a guild back to the default.
+    pub async fn delete(handler: &DatabaseHandler, guild_id: u64) -> Result<(), sqlx::Error> {
+        let query = r"DELETE FROM tag_time_budgets WHERE guild_id = $1";
+
+        sqlx::query(query).bind(guild_id as i64).execute(&handler.pool).await?;
+        handler.cache.set_tag_time_budget(guild_id, None);
+
+        Ok(())
+    }
+
+    /// Fetch the budget of a guild, or `None` if it uses the default.
+    pub async fn get(handler: &DatabaseHandler, guild_id: u64) -> Result<Option<Self>, sqlx::Error> {
+        if let Some(budget) = handler.cache.get_tag_time_budget(guild_id) {
+            return Ok(budget);
+        }
+
+        let query = r"SELECT milliseconds FROM tag_time_budgets WHERE guild_id = $1";
+
+        let budget = match sqlx::query_as::<_, (i64,)>(query)
+            .bind(guild_id as i64)
+            .fetch_one(&handler.pool)
+            .await
+        {
+            Ok(res) => Some(TagTimeBudget {
+                milliseconds: res.0 as u64,
+            }),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(err) => return Err(err),
+        };
+
+        // this is looked up on every tag invocation, so also cache guilds that use the default
+        handler.cache.set_tag_time_budget(guild_id, budget);
+        Ok(budget)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::DatabaseHandler;
+
+/// How the content of a message is matched against the pattern of a [`TagTrigger`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerKind {
+    /// The pattern appears in the message as a whole word, ignoring case
+    Word,
+    /// The message starts with the pattern, ignoring case
+    Prefix,
+    /// The pattern is a regular expression that matches somewhere in the message
+    Regex,
+}
+impl TriggerKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TriggerKind::Word => "word",
+            TriggerKind::Prefix => "prefix",
+            TriggerKind::Regex => "regex",
+        }
+    }
+
+    pub fn from_name(kind: &str) -> Option<Self> {
+        match kind {
+            "word" => Some(TriggerKind::Word),
+            "prefix" => Some(TriggerKind::Prefix),
+            "regex" => Some(TriggerKind::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// A tag trigger (auto-responder) runs a tag whenever a message in the guild matches its pattern.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagTrigger {
+    /// Assigned by the database on insert
+    pub id: i32,
+    pub guild_id: i64,
+    pub tag_name: String,
+    /// See [`TriggerKind`]
+    pub kind: String,
+    pub pattern: String,
+    /// Channels the trigger is limited to. Empty if it runs in every channel.
+    pub channels: Vec<i64>,
+    pub cooldown_seconds: i32,
+}
+impl TagTrigger {
+    pub fn kind(&self) -> Option<TriggerKind> {
+        TriggerKind::from_name(&self.kind)
+    }
+
+    /// Load the guilds that have triggers into the cache. Called once when connecting to the
+    /// database, after which [`TagTrigger::insert`] keeps it up to date.
+    pub(crate) async fn cache_guilds_with_triggers(handler: &DatabaseHandler) -> Result<(), sqlx::Error> {
+        let query = r"SELECT DISTINCT guild_id FROM tag_triggers";
+
+        let guild_ids: Vec<(i64,)> = sqlx::query_as(query).fetch_all(&handler.pool).await?;
+        handler
+            .cache
+            .set_guilds_with_tag_triggers(guild_ids.into_iter().map(|(id,)| id as u64).collect());
+
+        Ok(())
+    }
+
+    /// List all triggers in a guild. This is looked up on every message, so the result is cached,
+    /// and guilds that never had a trigger are not looked up at all.
+    pub async fn list_in_guild(handler: &DatabaseHandler, guild_id: i64) -> Result<Vec<Self>, sqlx::Error> {
+        if !handler.cache.guild_has_tag_triggers(guild_id as u64) {
+            return Ok(Vec::new());
+        }
+
+        if let Some(triggers) = handler.cache.get_guild_tag_triggers(guild_id as u64) {
+            return Ok(triggers);
+        }
+
+        let query = r"SELECT * FROM tag_triggers WHERE guild_id = $1 ORDER BY id";
+
+        let triggers: Vec<TagTrigger> = sqlx::query_as(query).bind(guild_id).fetch_all(&handler.pool).await?;
+        handler
+            .cache
+            .insert_guild_tag_triggers(guild_id as u64, triggers.clone());
+
+        Ok(triggers)
+    }
+
+    /// Insert a new trigger, ignoring `id`. Returns the ID of the inserted trigger.
+    pub async fn insert(&self, handler: &DatabaseHandler) -> Result<i32, sqlx::Error> {
+        let query = r"INSERT INTO tag_triggers (guild_id, tag_name, kind, pattern, channels, cooldown_seconds) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id";
+
+        let (id,) = sqlx::query_as::<_, (i32,)>(query)
+            .bind(self.guild_id)
+            .bind(&self.tag_name)
+            .bind(&self.kind)
+            .bind(&self.pattern)
+            .bind(&self.channels)
+            .bind(self.cooldown_seconds)
+            .fetch_one(&handler.pool)
+            .await?;
+
+        handler.cache.add_guild_with_tag_triggers(self.guild_id as u64);
+        handler.cache.invalidate_guild_tag_triggers(self.guild_id as u64);
+
+        Ok(id)
+    }
+
+    /// Remove a trigger. Returns true on successful removal, false if the trigger did not exist.
+    pub async fn remove(handler: &DatabaseHandler, guild_id: i64, id: i32) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM tag_triggers WHERE guild_id = $1 AND id = $2";
+
+        let removed = sqlx::query(query)
+            .bind(guild_id)
+            .bind(id)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)?;
+
+        handler.cache.invalidate_guild_tag_triggers(guild_id as u64);
+
+        Ok(removed)
+    }
+
+    /// Replace the channel allow list of a trigger. Returns false if the trigger did not exist.
+    pub async fn set_channels(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        id: i32,
+        channels: &[i64],
+    ) -> Result<bool, sqlx::Error> {
+        let query = r"UPDATE tag_triggers SET channels = $3 WHERE guild_id = $1 AND id = $2";
+
+        let updated = sqlx::query(query)
+            .bind(guild_id)
+            .bind(id)
+            .bind(channels)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)?;
+
+        handler.cache.invalidate_guild_tag_triggers(guild_id as u64);
+
+        Ok(updated)
+    }
+
+    /// Set the cooldown of a trigger. Returns false if the trigger did not exist.
+    pub async fn set_cooldown(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        id: i32,
+        cooldown_seconds: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let query = r"UPDATE tag_triggers SET cooldown_seconds = $3 WHERE guild_id = $1 AND id = $2";
+
+        let updated = sqlx::query(query)
+            .bind(guild_id)
+            .bind(id)
+            .bind(cooldown_seconds)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)?;
+
+        handler.cache.invalidate_guild_tag_triggers(guild_id as u64);
+
+        Ok(updated)
+    }
+}
@@ -0,0 +1,87 @@
+use crate::DatabaseHandler;
+
+/// How often a tag was used, and when it was last used.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagUsage {
+    pub guild_id: i64,
+    pub tag_name: String,
+    pub uses: i64,
+    /// Unix timestamp in milliseconds of the last use, or 0 if the tag was never used
+    pub last_used: i64,
+}
+impl TagUsage {
+    pub async fn get(handler: &DatabaseHandler, guild_id: i64, tag_name: &str) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_uses WHERE guild_id = $1 AND tag_name = $2";
+
+        let result = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .fetch_one(&handler.pool)
+            .await;
+
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Add the uses of many tags at once. `uses` of every entry is added to the stored uses of the
+    /// tag, and `last_used` replaces the stored time if it is more recent. Uses of tags that no
+    /// longer exist, e.g. because they were deleted since they were used, are dropped.
+    pub async fn add_uses(handler: &DatabaseHandler, usages: &[Self]) -> Result<(), sqlx::Error> {
+        let query = r"INSERT INTO tag_uses (guild_id, tag_name, uses, last_used)
+            SELECT * FROM UNNEST($1::BIGINT[], $2::TEXT[], $3::BIGINT[], $4::BIGINT[]) AS u(guild_id, tag_name, uses, last_used)
+            WHERE EXISTS (SELECT 1 FROM tags WHERE tags.guild_id = u.guild_id AND tags.name = u.tag_name)
+            ON CONFLICT (guild_id, tag_name) DO UPDATE SET uses = tag_uses.uses + EXCLUDED.uses,
+            last_used = GREATEST(tag_uses.last_used, EXCLUDED.last_used)";
+
+        sqlx::query(query)
+            .bind(usages.iter().map(|u| u.guild_id).collect::<Vec<_>>())
+            .bind(usages.iter().map(|u| u.tag_name.clone()).collect::<Vec<_>>())
+            .bind(usages.iter().map(|u| u.uses).collect::<Vec<_>>())
+            .bind(usages.iter().map(|u| u.last_used).collect::<Vec<_>>())
+            .execute(&handler.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The most used tags of a guild, most used first.
+    pub async fn get_top_in_guild(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT tag_uses.* FROM tag_uses JOIN tags ON tags.guild_id = tag_uses.guild_id AND tags.name = tag_uses.tag_name
+            WHERE tag_uses.guild_id = $1 ORDER BY tag_uses.uses DESC LIMIT $2";
+
+        sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(limit)
+            .fetch_all(&handler.pool)
+            .await
+    }
+
+    /// The tags of a guild which were neither used nor created since `before`, a unix timestamp in
+    /// milliseconds, least recently used first. Tags that were never used have 0 uses and a
+    /// `last_used` of 0.
+    pub async fn get_unused_in_guild(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        before: i64,
+        limit: i64,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        let query = r"SELECT tags.guild_id, tags.name AS tag_name, COALESCE(tag_uses.uses, 0) AS uses, COALESCE(tag_uses.last_used, 0) AS last_used
+            FROM tags LEFT JOIN tag_uses ON tags.guild_id = tag_uses.guild_id AND tags.name = tag_uses.tag_name
+            WHERE tags.guild_id = $1 AND COALESCE(tag_uses.last_used, tags.created_at) < $2
+            ORDER BY COALESCE(tag_uses.last_used, tags.created_at) LIMIT $3";
+
+        sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(before)
+            .bind(limit)
+            .fetch_all(&handler.pool)
+            .await
+    }
+}
@@ -0,0 +1,79 @@
+use crate::DatabaseHandler;
+
+/// The `tag_name` used for variables that are shared by every tag in a guild.
+pub const GUILD_SCOPE: &str = "";
+
+/// A tag variable is a key-value pair that persists across tag invocations. Variables are either
+/// scoped to a single tag, or shared by all tags in the guild (in which case `tag_name` is
+/// [`GUILD_SCOPE`]).
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct TagVariable {
+    pub guild_id: i64,
+    pub tag_name: String,
+    pub key: String,
+    pub value: String,
+}
+impl TagVariable {
+    /// Fetch a single variable, if it exists.
+    pub async fn get(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+        key: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let query = r"SELECT * FROM tag_variables WHERE guild_id = $1 AND tag_name = $2 AND key = $3";
+
+        let result = sqlx::query_as(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .bind(key)
+            .fetch_one(&handler.pool)
+            .await;
+
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert or overwrite a variable. New keys are only inserted while the scope holds fewer than
+    /// `limit` variables; overwriting an existing key is always allowed.
+    ///
+    /// Returns false if the variable was not stored because the scope is full.
+    pub async fn set(&self, handler: &DatabaseHandler, limit: i64) -> Result<bool, sqlx::Error> {
+        let query = r"INSERT INTO tag_variables (guild_id, tag_name, key, value)
+            SELECT $1, $2, $3, $4
+            WHERE EXISTS (SELECT 1 FROM tag_variables WHERE guild_id = $1 AND tag_name = $2 AND key = $3)
+                OR (SELECT count(*) FROM tag_variables WHERE guild_id = $1 AND tag_name = $2) < $5
+            ON CONFLICT (guild_id, tag_name, key) DO UPDATE SET value = $4";
+
+        sqlx::query(query)
+            .bind(self.guild_id)
+            .bind(&self.tag_name)
+            .bind(&self.key)
+            .bind(&self.value)
+            .bind(limit)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)
+    }
+
+    /// Delete a single variable. True on successful delete, false if it did not exist.
+    pub async fn delete(
+        handler: &DatabaseHandler,
+        guild_id: i64,
+        tag_name: &str,
+        key: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let query = r"DELETE FROM tag_variables WHERE guild_id = $1 AND tag_name = $2 AND key = $3";
+
+        sqlx::query(query)
+            .bind(guild_id)
+            .bind(tag_name)
+            .bind(key)
+            .execute(&handler.pool)
+            .await
+            .map(|r| r.rows_affected() > 0)
+    }
+}
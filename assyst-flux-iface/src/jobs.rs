@@ -36,6 +36,71 @@ pub enum MediaInfo {
 
 pub type FluxResult = anyhow::Result<Vec<u8>>;
 
+/// The operations that tags can run, with the names of their options in the order that tags pass
+/// them in. Update this together with the jobs below.
+const TAG_OPERATIONS: &[(&str, &[&str])] = &[
+    ("ah-shit", &[]),
+    ("april-fools", &[]),
+    ("back-tattoo", &[]),
+    ("billboard", &[]),
+    ("blur", &["strength"]),
+    ("book", &[]),
+    ("caption", &["text"]),
+    ("circuitboard", &[]),
+    ("deepfry", &[]),
+    ("drip", &[]),
+    ("femurbreaker", &[]),
+    ("fisheye", &[]),
+    ("flag", &[]),
+    ("flag2", &[]),
+    ("flip", &[]),
+    ("flop", &[]),
+    ("fortune-cookie", &[]),
+    ("frame-shift", &[]),
+    ("ghost", &["depth"]),
+    ("gif", &[]),
+    ("gif-magik", &[]),
+    ("globe", &[]),
+    ("grayscale", &[]),
+    ("heart-locket", &["text"]),
+    ("invert", &[]),
+    ("jpeg", &["quality"]),
+    ("magik", &[]),
+    ("meme", &["top", "bottom"]),
+    ("motivate", &["top", "bottom"]),
+    ("neon", &[]),
+    ("paint", &[]),
+    ("ping-pong", &[]),
+    ("pixelate", &["strength"]),
+    ("rainbow", &[]),
+    ("reverse", &[]),
+    ("rotate", &["degrees"]),
+    ("rubiks", &[]),
+    ("scramble", &[]),
+    ("siren", &[]),
+    ("speed", &["multiplier"]),
+    ("spin", &[]),
+    ("spread", &["strength"]),
+    ("sweden", &[]),
+    ("swirl", &["strength"]),
+    ("terraria", &[]),
+    ("toaster", &[]),
+    ("valentine", &[]),
+    ("wormhole", &[]),
+    ("zoom", &[]),
+    ("zoom-blur", &["power"]),
+];
+
+/// Returns the names of the options of an operation that tags can run, in the order that tags pass
+/// them in, or `None` if tags cannot run the operation
+#[must_use]
+pub fn tag_operation_options(operation: &str) -> Option<&'static [&'static str]> {
+    TAG_OPERATIONS
+        .iter()
+        .find(|(name, _)| *name == operation)
+        .map(|(_, options)| *options)
+}
+
 impl FluxHandler {
     pub async fn ahshit(&self, media: Vec<u8>, user_id: u64, guild_id: Option<u64>) -> FluxResult {
         let limits = self.get_request_limits(user_id, guild_id).await?;
@@ -0,0 +1,396 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Ident, Lit, LitStr, Meta, PathArguments, Type,
+};
+
+/// What kind of slash command option a flag becomes, derived from the type of its field
+#[derive(Clone, Copy, PartialEq)]
+enum FlagKind {
+    Bool,
+    Integer,
+    String,
+}
+
+/// A field of a `#[derive(Flags)]` struct
+struct Flag {
+    ident: Ident,
+    /// The name of the flag, as in `--name`
+    name: String,
+    description: String,
+    kind: FlagKind,
+    /// The type of the value of the flag, without the `Option`
+    ty: Type,
+    optional: bool,
+    default: Option<Expr>,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    choices: Vec<Expr>,
+}
+
+impl Flag {
+    fn from_field(field: &syn::Field) -> Self {
+        let ident = field.ident.clone().expect("#[derive(Flags)] requires named fields");
+
+        let (ty, optional) = match option_inner(&field.ty) {
+            Some(inner) => (inner.clone(), true),
+            None => (field.ty.clone(), false),
+        };
+
+        let kind = match type_name(&ty).as_deref() {
+            Some("bool") => FlagKind::Bool,
+            Some("u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize") => FlagKind::Integer,
+            Some("String") => FlagKind::String,
+            _ => panic!("flag `{ident}`: unsupported type, expected a bool, an integer or a String"),
+        };
+
+        assert!(
+            !(kind == FlagKind::Bool && optional),
+            "flag `{ident}`: boolean flags cannot be optional"
+        );
+
+        let mut flag = Flag {
+            name: ident.to_string().replace('_', "-"),
+            description: doc_string(&field.attrs)
+                .unwrap_or_else(|| panic!("flag `{ident}`: missing doc comment, which is used as its description")),
+            ident,
+            kind,
+            ty,
+            optional,
+            default: None,
+            min: None,
+            max: None,
+            choices: Vec::new(),
+        };
+
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("flag")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    flag.name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("default") {
+                    flag.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min") {
+                    flag.min = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max") {
+                    flag.max = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("choices") {
+                    let Expr::Array(choices) = meta.value()?.parse()? else {
+                        return Err(meta.error("expected an array of choices"));
+                    };
+                    flag.choices = choices.elems.into_iter().collect();
+                } else {
+                    return Err(meta.error("unknown flag attribute"));
+                }
+
+                Ok(())
+            })
+            .unwrap_or_else(|e| panic!("flag `{}`: {e}", flag.ident));
+        }
+
+        let ident = &flag.ident;
+        assert!(
+            flag.default.is_none() || (flag.kind != FlagKind::Bool && !flag.optional),
+            "flag `{ident}`: only required integer and string flags can have a default"
+        );
+        assert!(
+            (flag.min.is_none() && flag.max.is_none()) || flag.kind == FlagKind::Integer,
+            "flag `{ident}`: only integer flags can have a min or max"
+        );
+        assert!(
+            flag.choices.is_empty() || flag.kind != FlagKind::Bool,
+            "flag `{ident}`: boolean flags cannot have choices"
+        );
+
+        flag
+    }
+
+    /// Converts the `Option` of the value of this flag, named after the field, into the value of
+    /// the field, checking that it is in range and one of the choices. `map_err` is appended to
+    /// every fallible expression.
+    fn finish(&self, map_err: &TokenStream) -> TokenStream {
+        let Flag { ident, name, .. } = self;
+
+        if self.kind == FlagKind::Bool {
+            return TokenStream::new();
+        }
+
+        let mut checks = TokenStream::new();
+        if self.min.is_some() || self.max.is_some() {
+            let min = option_expr(self.min.as_ref());
+            let max = option_expr(self.max.as_ref());
+            checks.extend(quote! {
+                crate::command::flags::check_flag_range(#name, #ident.as_ref(), #min, #max)#map_err?;
+            });
+        }
+        if !self.choices.is_empty() {
+            let choices = &self.choices;
+            checks.extend(quote! {
+                crate::command::flags::check_flag_choice(#name, #ident.as_ref(), &[#(#choices),*])#map_err?;
+            });
+        }
+
+        let value = if self.optional {
+            quote!(#ident)
+        } else {
+            match (&self.default, self.kind) {
+                (Some(default), FlagKind::String) => quote!(#ident.unwrap_or_else(|| String::from(#default))),
+                (Some(default), _) => quote!(#ident.unwrap_or(#default)),
+                (None, _) => quote!(#ident.unwrap_or_default()),
+            }
+        };
+
+        quote! {
+            #checks
+            let #ident = #value;
+        }
+    }
+
+    /// Expression decoding this flag from the output of `flags_from_str`, named `raw_decode`
+    fn raw_decode_value(&self) -> TokenStream {
+        let Flag { name, ty, .. } = self;
+
+        match self.kind {
+            FlagKind::Bool => quote!(raw_decode.contains_key(#name)),
+            _ => quote!(crate::command::flags::flag_value::<#ty>(&raw_decode, #name)?),
+        }
+    }
+
+    /// Expression extracting this flag from the options of an interaction command
+    fn command_option_value(&self) -> TokenStream {
+        let Flag { name, ty, .. } = self;
+
+        let (variant, value) = match self.kind {
+            FlagKind::Bool => (quote!(Boolean), quote!(*value)),
+            // the option is an i64, which may not fit the type of the flag
+            FlagKind::Integer => (
+                quote!(Integer),
+                quote! {
+                    <#ty>::try_from(*value).map_err(|_| {
+                        crate::command::errors::TagParseError::FlagParseError(anyhow::anyhow!(concat!(
+                            "Flag ", #name, " is out of range"
+                        )))
+                    })?
+                },
+            ),
+            FlagKind::String => (quote!(String), quote!(value.clone())),
+        };
+
+        let value = quote! {
+            match ctxt.option_by_name(#name) {
+                Ok(option) => match &option.value {
+                    twilight_model::application::interaction::application_command::CommandOptionValue::#variant(value) => Some(#value),
+                    other => {
+                        return Err(crate::command::errors::TagParseError::MismatchedCommandOptionType((
+                            stringify!(#ty).to_owned(),
+                            other.clone(),
+                        )))
+                    },
+                },
+                Err(_) => None,
+            }
+        };
+
+        if self.kind == FlagKind::Bool {
+            quote!(#value.unwrap_or(false))
+        } else {
+            value
+        }
+    }
+
+    /// How `help` lists this flag, e.g. `quality <144|240|360>`
+    fn help_name(&self) -> String {
+        if self.kind == FlagKind::Bool {
+            return self.name.clone();
+        }
+
+        let value = if self.choices.is_empty() {
+            self.name.clone()
+        } else {
+            self.choices
+                .iter()
+                .map(|choice| match choice {
+                    Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => s.value(),
+                    _ => choice.to_token_stream().to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+
+        format!("{} <{value}>", self.name)
+    }
+
+    /// Expression building the slash command option of this flag
+    fn command_option(&self) -> TokenStream {
+        let Flag { name, description, .. } = self;
+
+        let builder = match self.kind {
+            FlagKind::Bool => format_ident!("BooleanBuilder"),
+            FlagKind::Integer => format_ident!("IntegerBuilder"),
+            FlagKind::String => format_ident!("StringBuilder"),
+        };
+
+        let mut option = quote! {
+            twilight_util::builder::command::#builder::new(#name, #description).required(false)
+        };
+        if let Some(min) = &self.min {
+            option.extend(quote!(.min_value(#min)));
+        }
+        if let Some(max) = &self.max {
+            option.extend(quote!(.max_value(#max)));
+        }
+        if !self.choices.is_empty() {
+            let choices = self.choices.iter().map(|choice| match choice {
+                Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => quote!((#s, #s)),
+                _ => quote!((stringify!(#choice), #choice)),
+            });
+            option.extend(quote!(.choices(vec![#(#choices),*])));
+        }
+
+        quote!(#option.build())
+    }
+}
+
+fn option_expr(expr: Option<&Expr>) -> TokenStream {
+    match expr {
+        Some(expr) => quote!(Some(#expr)),
+        None => quote!(None),
+    }
+}
+
+/// Returns `T` if the type is `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    let Type::Path(path) = ty else { return None };
+    path.path.segments.last().map(|segment| segment.ident.to_string())
+}
+
+/// Joins the lines of the doc comments of an item
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value().trim().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+pub fn derive_flags(input: DeriveInput) -> TokenStream {
+    let ident = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        panic!("#[derive(Flags)] applied to non-struct")
+    };
+    let Fields::Named(fields) = &data.fields else {
+        panic!("#[derive(Flags)] requires named fields")
+    };
+
+    let mut validate = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("flags")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("validate") {
+                validate = Some(meta.value()?.parse::<LitStr>()?.parse::<syn::Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown flags attribute"))
+            }
+        })
+        .unwrap_or_else(|e| panic!("#[flags]: {e}"));
+    }
+
+    let flags = fields.named.iter().map(Flag::from_field).collect::<Vec<_>>();
+
+    let idents = flags.iter().map(|flag| &flag.ident).collect::<Vec<_>>();
+    let names = flags.iter().map(|flag| &flag.name);
+    let flag_types = flags.iter().map(|flag| match flag.kind {
+        FlagKind::Bool => quote!(crate::command::flags::FlagType::NoValue),
+        _ => quote!(crate::command::flags::FlagType::WithValue),
+    });
+    let raw_decodes = flags.iter().map(Flag::raw_decode_value);
+    let command_option_values = flags.iter().map(Flag::command_option_value);
+    let command_options = flags.iter().map(Flag::command_option);
+    let help_names = flags.iter().map(Flag::help_name);
+    let descriptions = flags.iter().map(|flag| &flag.description);
+
+    let map_err = quote!(.map_err(crate::command::errors::TagParseError::FlagParseError));
+    let finish_raw = flags.iter().map(|flag| flag.finish(&TokenStream::new()));
+    let finish_command_option = flags.iter().map(|flag| flag.finish(&map_err));
+    let validate_raw = validate.as_ref().map(|path| quote!(#path(&result)?;));
+    let validate_command_option = validate.as_ref().map(|path| quote!(#path(&result)#map_err?;));
+
+    quote! {
+        impl crate::command::flags::FlagDecode for #ident {
+            fn from_str(input: &str) -> anyhow::Result<Self> {
+                let mut valid_flags = std::collections::HashMap::new();
+                #(
+                    valid_flags.insert(#names, #flag_types);
+                )*
+
+                let raw_decode = crate::command::flags::flags_from_str(input, valid_flags)?;
+                #(
+                    let #idents = #raw_decodes;
+                )*
+                #(#finish_raw)*
+
+                let result = Self { #(#idents),* };
+                #validate_raw
+
+                Ok(result)
+            }
+        }
+
+        impl crate::command::arguments::ParseArgument for #ident {
+            fn as_command_options(_: &str) -> Vec<twilight_model::application::command::CommandOption> {
+                vec![#(#command_options),*]
+            }
+
+            fn flag_descriptions() -> Vec<(&'static str, &'static str)> {
+                vec![#((#help_names, #descriptions)),*]
+            }
+
+            async fn parse_raw_message(
+                ctxt: &mut crate::command::RawMessageParseCtxt<'_>,
+                label: crate::command::Label,
+            ) -> Result<Self, crate::command::errors::TagParseError> {
+                let args = ctxt.rest_all(label);
+                let parsed = <Self as crate::command::flags::FlagDecode>::from_str(&args)#map_err?;
+                Ok(parsed)
+            }
+
+            async fn parse_command_option(
+                ctxt: &mut crate::command::InteractionCommandParseCtxt<'_>,
+                _: crate::command::Label,
+            ) -> Result<Self, crate::command::errors::TagParseError> {
+                #(
+                    let #idents = #command_option_values;
+                )*
+                #(#finish_command_option)*
+
+                let result = Self { #(#idents),* };
+                #validate_command_option
+
+                Ok(result)
+            }
+        }
+    }
+}
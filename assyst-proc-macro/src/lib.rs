@@ -12,6 +12,8 @@ use syn::{
     PatType, Token, Type,
 };
 
+mod flags;
+
 struct CommandAttributes(syn::punctuated::Punctuated<syn::Meta, Token![,]>);
 
 impl syn::parse::Parse for CommandAttributes {
@@ -75,6 +77,7 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
     let mut parse_attrs = Vec::new();
     let mut interaction_parse_exprs = Vec::new();
     let mut command_option_exprs = Vec::new();
+    let mut flag_description_exprs = Vec::new();
 
     // sanity check that the first parameter is the `ctxt`, and exclude it from the list of
     // arguments it wouldn't compile anyway since `CommandCtxt` can't be parsed as an argument
@@ -100,6 +103,8 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
                 parse_idents.push(Ident::new(&format!("p{index}"), Span::call_site()));
                 parse_exprs.push(quote!(<#ty>::parse_raw_message(&mut ctxt, Some((stringify!(#pat).to_string(), stringify!(#ty).to_string()))).await));
                 parse_usage.push(quote!(<#ty as crate::command::arguments::ParseArgument>::usage(stringify!(#pat))));
+                flag_description_exprs
+                    .push(quote!(<#ty as crate::command::arguments::ParseArgument>::flag_descriptions()));
                 interaction_parse_exprs.push(quote!(<#ty>::parse_command_option(&mut ctxt, Some((stringify!(#pat).to_string(), stringify!(#ty).to_string()))).await));
             },
         }
@@ -210,6 +215,10 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
                 for (k, v) in #flag_descriptions {
                     descriptions.insert(k, v);
                 }
+                // flags of `#[derive(Flags)]` arguments describe themselves
+                #(
+                    descriptions.extend(#flag_description_exprs);
+                )*
 
                 static META: std::sync::OnceLock<crate::command::CommandMetadata> = std::sync::OnceLock::new();
                 META.get_or_init(|| crate::command::CommandMetadata {
@@ -368,6 +377,36 @@ pub fn command(attrs: TokenStream, func: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// A derive macro for flag structs, implementing `FlagDecode` and `ParseArgument` for them. Every
+/// field becomes a flag named after the field (with `_` replaced by `-`), and its doc comment is
+/// used as the description of its slash command option and in `help`:
+///
+/// ```ignore
+/// #[derive(Default, Flags)]
+/// #[flags(validate = "ChargeFlags::validate")]
+/// pub struct ChargeFlags {
+///     /// increase verbosity
+///     pub verbose: bool,
+///     /// optimisation level
+///     #[flag(default = 0, choices = [0, 1, 2, 3])]
+///     pub opt: u64,
+///     /// amount of runs
+///     #[flag(min = 1, max = 10)]
+///     pub runs: Option<u64>,
+/// }
+/// ```
+///
+/// `bool` fields are flags without a value, integer and `String` fields are flags with a value.
+/// Values that are not provided are `None` for `Option` fields, or otherwise the `default` (or
+/// `Default::default()`). The values are checked against `min`, `max` and `choices` for both
+/// sources, and `validate` is called with the parsed flags for checks involving several flags.
+#[proc_macro_derive(Flags, attributes(flag, flags))]
+pub fn derive_flags(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+
+    flags::derive_flags(input).into()
+}
+
 fn verify_input_is_ctxt(inputs: &Punctuated<FnArg, Token![,]>) {
     if let Some(FnArg::Typed(PatType { ty, .. })) = inputs.first()
         && let Type::Path(path) = &**ty
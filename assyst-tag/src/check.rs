@@ -0,0 +1,316 @@
+//! Static analysis of tag sources.
+//!
+//! The checker walks the source the same way the parser does, but never evaluates anything, so it
+//! can run on tags that would otherwise issue HTTP requests, touch storage or hit limits.
+
+use std::ops::Range;
+
+use crate::parser::{is_identifier, limits};
+use crate::registry;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    UnknownSubtag(String),
+    EmptySubtag,
+    MissingClosingBrace {
+        tag_start: usize,
+    },
+    TooFewArguments {
+        subtag: String,
+        expected: usize,
+        got: usize,
+    },
+    /// Extra arguments are silently ignored by the subtag
+    TooManyArguments {
+        subtag: String,
+        expected: usize,
+        got: usize,
+    },
+    InvalidComparison(String),
+    /// A branch of an `{if}` that can never be taken, because the condition only consists of
+    /// literal text
+    UnreachableBranch {
+        condition: bool,
+    },
+    /// Subtags are nested deeper than the parser can run, see [`MAX_NESTING`]. Nothing after this
+    /// is checked
+    NestingLimit,
+}
+
+/// Maximum nesting of subtags. Every level costs the parser at least one iteration, so a tag that
+/// is nested deeper than [`limits::MAX_ITERATIONS`] can never run. This also bounds the recursion
+/// of the checker, which would otherwise overflow the stack on deeply nested input
+pub const MAX_NESTING: u32 = limits::MAX_ITERATIONS;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub span: Range<usize>,
+}
+
+/// Tags whose arguments are skipped without being parsed
+fn is_raw(name: &str) -> bool {
+    matches!(name, "note" | "ignore")
+}
+
+/// Evaluates an `{if}` comparison of two literals. Returns `None` for invalid comparators, and for
+/// comparisons that would fail at runtime.
+fn compare_literals(stmt: &str, comparison: &str, value: &str) -> Option<bool> {
+    let numbers = || Some((stmt.parse::<i32>().ok()?, value.parse::<i32>().ok()?));
+
+    match comparison {
+        "=" => Some(stmt == value),
+        "~" => Some(stmt.eq_ignore_ascii_case(value)),
+        ">" => numbers().map(|(a, b)| a > b),
+        ">=" => numbers().map(|(a, b)| a >= b),
+        "<" => numbers().map(|(a, b)| a < b),
+        "<=" => numbers().map(|(a, b)| a <= b),
+        _ => None,
+    }
+}
+
+fn is_valid_comparison(comparison: &str) -> bool {
+    matches!(comparison, "=" | "~" | ">" | ">=" | "<" | "<=")
+}
+
+/// The source could not be walked any further, e.g. because of a missing closing brace
+struct Abort;
+
+/// An argument of a subtag
+struct Segment {
+    span: Range<usize>,
+    /// The text of this segment if it does not contain any subtags
+    literal: Option<String>,
+}
+
+struct Checker<'a> {
+    src: &'a str,
+    input: &'a [u8],
+    idx: usize,
+    lints: Vec<Lint>,
+    /// How many subtags the current position is nested in
+    depth: u32,
+}
+
+impl Checker<'_> {
+    fn lint(&mut self, kind: LintKind, span: Range<usize>) {
+        self.lints.push(Lint { kind, span });
+    }
+
+    /// Span of the character at `idx`, or a one byte wide span if `idx` is the end of the input
+    fn char_span(&self, idx: usize) -> Range<usize> {
+        let lo = self.src.floor_char_boundary(idx);
+        let len = self.src[lo..].chars().next().map_or(1, char::len_utf8);
+        lo..lo + len
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.input.get(self.idx) == Some(&byte) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_separator(&mut self) -> bool {
+        self.eat(b'|') || self.eat(b':')
+    }
+
+    fn is_escaped(&self) -> bool {
+        self.idx > 0 && self.input[self.idx - 1] == b'\\'
+    }
+
+    fn expect_closing_brace(&mut self, tag_start: usize) -> Result<(), Abort> {
+        if self.eat(b'}') {
+            Ok(())
+        } else {
+            self.lint(LintKind::MissingClosingBrace { tag_start }, self.char_span(self.idx));
+            Err(Abort)
+        }
+    }
+
+    fn segment(&mut self, in_tag: bool) -> Result<Segment, Abort> {
+        let start = self.idx;
+        let mut literal = Some(Vec::new());
+
+        while let Some(&byte) = self.input.get(self.idx) {
+            match byte {
+                b'{' => {
+                    self.tag()?;
+                    literal = None;
+                },
+                b'|' | b'}' if in_tag => break,
+                b'\\' if let Some(&next @ (b'|' | b'}' | b'{')) = self.input.get(self.idx + 1) => {
+                    if let Some(literal) = &mut literal {
+                        literal.push(next);
+                    }
+                    self.idx += 2;
+                },
+                _ => {
+                    if let Some(literal) = &mut literal {
+                        literal.push(byte);
+                    }
+                    self.idx += 1;
+                },
+            }
+        }
+
+        Ok(Segment {
+            span: start..self.idx,
+            literal: literal.map(|literal| String::from_utf8_lossy(&literal).into_owned()),
+        })
+    }
+
+    /// Skips a segment without looking at its contents, like `Parser::parse_segment(false)`
+    fn skip_raw_segment(&mut self) {
+        let mut depth = 1;
+
+        while let Some(&byte) = self.input.get(self.idx) {
+            match byte {
+                b'{' if !self.is_escaped() => depth += 1,
+                b'}' if !self.is_escaped() => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                },
+                b'|' if !self.is_escaped() && depth <= 1 => break,
+                _ => {},
+            }
+            self.idx += 1;
+        }
+    }
+
+    fn tag(&mut self) -> Result<(), Abort> {
+        if self.depth >= MAX_NESTING {
+            self.lint(LintKind::NestingLimit, self.char_span(self.idx));
+            return Err(Abort);
+        }
+
+        self.depth += 1;
+        let result = self.tag_inner();
+        self.depth -= 1;
+        result
+    }
+
+    fn tag_inner(&mut self) -> Result<(), Abort> {
+        let tag_start = self.idx;
+        // skip {
+        self.idx += 1;
+
+        while self.input.get(self.idx).is_some_and(u8::is_ascii_whitespace) {
+            self.idx += 1;
+        }
+
+        let is_meta_tag = self.eat(b'!');
+
+        let name_start = self.idx;
+        while self.input.get(self.idx).is_some_and(|&b| is_identifier(b)) {
+            self.idx += 1;
+        }
+        let name_span = name_start..self.idx;
+        let name = &self.src[name_span.clone()];
+
+        if is_meta_tag && name == "ignore_parse_errors" {
+            return self.expect_closing_brace(tag_start);
+        }
+
+        if self.idx == self.input.len() {
+            self.lint(LintKind::MissingClosingBrace { tag_start }, self.idx..self.idx + 1);
+            return Err(Abort);
+        }
+
+        if name.is_empty() {
+            self.lint(LintKind::EmptySubtag, tag_start..self.char_span(self.idx).end);
+            return Err(Abort);
+        }
+
+        let mut args = Vec::new();
+        if is_raw(name) {
+            if self.eat_separator() {
+                let start = self.idx;
+                self.skip_raw_segment();
+                args.push(Segment {
+                    span: start..self.idx,
+                    literal: None,
+                });
+            }
+        } else {
+            while self.eat_separator() {
+                args.push(self.segment(true)?);
+            }
+        }
+
+        self.expect_closing_brace(tag_start)?;
+        self.check_tag(name, name_span, tag_start..self.idx, &args);
+
+        Ok(())
+    }
+
+    fn check_tag(&mut self, name: &str, name_span: Range<usize>, span: Range<usize>, args: &[Segment]) {
+        let Some(subtag) = registry::get(name) else {
+            self.lint(LintKind::UnknownSubtag(name.to_owned()), name_span);
+            return;
+        };
+        let (min, max) = (subtag.min_args, subtag.max_args);
+
+        if args.len() < min {
+            self.lint(
+                LintKind::TooFewArguments {
+                    subtag: name.to_owned(),
+                    expected: min,
+                    got: args.len(),
+                },
+                span,
+            );
+        } else if let Some(max) = max.filter(|&max| args.len() > max) {
+            self.lint(
+                LintKind::TooManyArguments {
+                    subtag: name.to_owned(),
+                    expected: max,
+                    got: args.len(),
+                },
+                args[max].span.start..args[args.len() - 1].span.end,
+            );
+        }
+
+        if let ("if", [stmt, comparison, value, then, otherwise, ..]) = (name, args) {
+            let Some(cmp) = &comparison.literal else {
+                return;
+            };
+
+            if !is_valid_comparison(cmp) {
+                self.lint(LintKind::InvalidComparison(cmp.clone()), comparison.span.clone());
+                return;
+            }
+
+            let condition = match (&stmt.literal, &value.literal) {
+                (Some(stmt), Some(value)) => compare_literals(stmt, cmp, value),
+                _ => None,
+            };
+
+            if let Some(condition) = condition {
+                let unreachable = if condition { otherwise } else { then };
+                self.lint(LintKind::UnreachableBranch { condition }, unreachable.span.clone());
+            }
+        }
+    }
+}
+
+/// Walks the tag source without evaluating it and returns everything that looks wrong
+pub fn check(input: &str) -> Vec<Lint> {
+    let mut checker = Checker {
+        src: input,
+        input: input.as_bytes(),
+        idx: 0,
+        lints: Vec::new(),
+        depth: 0,
+    };
+
+    // an abort means that the rest of the source cannot be interpreted, but everything found up to
+    // that point is still useful
+    let _ = checker.segment(false);
+
+    checker.lints
+}
@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use anyhow::anyhow;
 use assyst_common::eval::FakeEvalImageResponse;
 
+use crate::parser::limits;
+
 /// A "no-op" context, which returns an error for any of the methods
 ///
 /// This is useful for testing the parser, when you need to provide a Context but
@@ -11,6 +15,65 @@ fn not_implemented<T>() -> anyhow::Result<T> {
     Err(anyhow!("Not implemented"))
 }
 
+/// The scope of a persistent variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageScope {
+    /// Only visible to the tag that was invoked
+    Tag,
+    /// Shared by every tag in the guild
+    Guild,
+}
+
+/// Calls `$callback` with every method of [`Context`] that makes a request to the caller. This is
+/// only used for code that has to handle every method the same way: [`Context`] forwarding impls,
+/// and [`crate::resumable::Request`]. It has to be kept in sync with [`Context`] and
+/// [`crate::AsyncContext`].
+///
+/// Every entry names its variant of `Request`, whether its response stays the same for the whole
+/// run of a tag (`constant`) or not (`varying`), and its signature. Borrowed arguments are followed
+/// by the owned type that `Request` stores them as.
+macro_rules! with_requests {
+    ($callback:ident) => {
+        $callback! {
+            ExecuteJavascript(varying) fn execute_javascript(code: &str as String, args: Vec<String>) -> FakeEvalImageResponse;
+            LastAttachment(constant) fn get_last_attachment() -> String;
+            Avatar(constant) fn get_avatar(user_id: Option<u64>) -> String;
+            Download(varying) fn download(url: &str as String) -> String;
+            ChannelId(constant) fn channel_id() -> u64;
+            GuildId(constant) fn guild_id() -> u64;
+            UserId(constant) fn user_id() -> u64;
+            UserTag(constant) fn user_tag(id: Option<u64>) -> String;
+            TagContents(constant) fn get_tag_contents(tag: &str as String) -> String;
+            GetPersistent(varying) fn get_persistent(scope: StorageScope, key: &str as String) -> Option<String>;
+            SetPersistent(varying) fn set_persistent(scope: StorageScope, key: &str as String, value: &str as String) -> bool;
+            DeletePersistent(varying) fn delete_persistent(scope: StorageScope, key: &str as String) -> ();
+            RunFlux(varying) fn run_flux(operation: &str as String, args: Vec<String>, input_url: &str as String) -> Vec<u8>;
+            GuildName(constant) fn guild_name() -> String;
+            GuildMemberCount(constant) fn guild_member_count() -> u64;
+            ChannelName(constant) fn channel_name() -> String;
+            ChannelTopic(constant) fn channel_topic() -> Option<String>;
+            Nickname(constant) fn nickname() -> Option<String>;
+            RoleNames(constant) fn role_names() -> Vec<String>;
+            JoinedAt(constant) fn joined_at() -> i64;
+            RepliedMessageContent(constant) fn replied_message_content() -> Option<String>;
+            MessageId(constant) fn message_id() -> u64;
+        }
+    };
+}
+pub(crate) use with_requests;
+
+macro_rules! forward_methods {
+    ($(
+        $variant:ident($kind:ident) fn $name:ident($($arg:ident: $ty:ty $(as $owned:ty)?),*) -> $ret:ty;
+    )*) => {
+        $(
+            fn $name(&self, $($arg: $ty),*) -> anyhow::Result<$ret> {
+                (**self).$name($($arg),*)
+            }
+        )*
+    };
+}
+
 /// External context for the parser
 ///
 /// It contains methods that can be provided by the caller (normally the bot crate).
@@ -33,6 +96,44 @@ pub trait Context {
     fn user_tag(&self, id: Option<u64>) -> anyhow::Result<String>;
     /// Loads the contents of a tag
     fn get_tag_contents(&self, tag: &str) -> anyhow::Result<String>;
+    /// Loads a persistent variable, or `None` if it does not exist
+    fn get_persistent(&self, scope: StorageScope, key: &str) -> anyhow::Result<Option<String>>;
+    /// Stores a persistent variable, returning `false` if the scope already holds
+    /// `limits::MAX_PERSISTENT_VARIABLES` variables and `key` is not one of them
+    fn set_persistent(&self, scope: StorageScope, key: &str, value: &str) -> anyhow::Result<bool>;
+    /// Deletes a persistent variable
+    fn delete_persistent(&self, scope: StorageScope, key: &str) -> anyhow::Result<()>;
+    /// Runs a Flux operation on the image at `input_url` and returns the output image. `args` are
+    /// the options of the operation in order, the context knows which operations exist and which
+    /// options they take
+    fn run_flux(&self, operation: &str, args: Vec<String>, input_url: &str) -> anyhow::Result<Vec<u8>>;
+    /// Returns the name of the guild
+    fn guild_name(&self) -> anyhow::Result<String>;
+    /// Returns the approximate number of members in the guild
+    fn guild_member_count(&self) -> anyhow::Result<u64>;
+    /// Returns the name of the channel where this message was sent
+    fn channel_name(&self) -> anyhow::Result<String>;
+    /// Returns the topic of the channel where this message was sent, if it has one
+    fn channel_topic(&self) -> anyhow::Result<Option<String>>;
+    /// Returns the guild nickname of the message author, if they have one
+    fn nickname(&self) -> anyhow::Result<Option<String>>;
+    /// Returns the names of the roles of the message author, highest role first
+    fn role_names(&self) -> anyhow::Result<Vec<String>>;
+    /// Returns when the message author joined the guild, as a unix timestamp in seconds
+    fn joined_at(&self) -> anyhow::Result<i64>;
+    /// Returns the content of the message that this message replied to, if any
+    fn replied_message_content(&self) -> anyhow::Result<Option<String>>;
+    /// Returns the ID of this message
+    fn message_id(&self) -> anyhow::Result<u64>;
+
+    /// Returns how long the tag may run for before it is aborted
+    fn time_budget(&self) -> Duration;
+
+    /// Whether the tag has to stop at the next subtag, because the context cannot answer it anymore
+    /// (see [`crate::resumable`])
+    fn is_suspended(&self) -> bool {
+        false
+    }
 }
 
 impl Context for NopContext {
@@ -71,42 +172,72 @@ impl Context for NopContext {
     fn get_tag_contents(&self, _: &str) -> anyhow::Result<String> {
         not_implemented()
     }
-}
 
-impl Context for &dyn Context {
-    fn execute_javascript(&self, code: &str, args: Vec<String>) -> anyhow::Result<FakeEvalImageResponse> {
-        (**self).execute_javascript(code, args)
+    fn get_persistent(&self, _scope: StorageScope, _key: &str) -> anyhow::Result<Option<String>> {
+        not_implemented()
     }
 
-    fn get_last_attachment(&self) -> anyhow::Result<String> {
-        (**self).get_last_attachment()
+    fn set_persistent(&self, _scope: StorageScope, _key: &str, _value: &str) -> anyhow::Result<bool> {
+        not_implemented()
     }
 
-    fn get_avatar(&self, user_id: Option<u64>) -> anyhow::Result<String> {
-        (**self).get_avatar(user_id)
+    fn delete_persistent(&self, _scope: StorageScope, _key: &str) -> anyhow::Result<()> {
+        not_implemented()
     }
 
-    fn download(&self, url: &str) -> anyhow::Result<String> {
-        (**self).download(url)
+    fn run_flux(&self, _operation: &str, _args: Vec<String>, _input_url: &str) -> anyhow::Result<Vec<u8>> {
+        not_implemented()
     }
 
-    fn channel_id(&self) -> anyhow::Result<u64> {
-        (**self).channel_id()
+    fn guild_name(&self) -> anyhow::Result<String> {
+        not_implemented()
     }
 
-    fn guild_id(&self) -> anyhow::Result<u64> {
-        (**self).guild_id()
+    fn guild_member_count(&self) -> anyhow::Result<u64> {
+        not_implemented()
     }
 
-    fn user_id(&self) -> anyhow::Result<u64> {
-        (**self).user_id()
+    fn channel_name(&self) -> anyhow::Result<String> {
+        not_implemented()
     }
 
-    fn user_tag(&self, user_id: Option<u64>) -> anyhow::Result<String> {
-        (**self).user_tag(user_id)
+    fn channel_topic(&self) -> anyhow::Result<Option<String>> {
+        not_implemented()
+    }
+
+    fn nickname(&self) -> anyhow::Result<Option<String>> {
+        not_implemented()
+    }
+
+    fn role_names(&self) -> anyhow::Result<Vec<String>> {
+        not_implemented()
+    }
+
+    fn joined_at(&self) -> anyhow::Result<i64> {
+        not_implemented()
+    }
+
+    fn replied_message_content(&self) -> anyhow::Result<Option<String>> {
+        not_implemented()
+    }
+
+    fn message_id(&self) -> anyhow::Result<u64> {
+        not_implemented()
+    }
+
+    fn time_budget(&self) -> Duration {
+        limits::DEFAULT_TIME_BUDGET
+    }
+}
+
+impl Context for &dyn Context {
+    with_requests!(forward_methods);
+
+    fn time_budget(&self) -> Duration {
+        (**self).time_budget()
     }
 
-    fn get_tag_contents(&self, tag: &str) -> anyhow::Result<String> {
-        (**self).get_tag_contents(tag)
+    fn is_suspended(&self) -> bool {
+        (**self).is_suspended()
     }
 }
@@ -5,9 +5,11 @@ use std::ops::Range;
 use assyst_string_fmt::Ansi;
 use memchr::memmem::rfind;
 
+use crate::check::{Lint, LintKind};
+use crate::math::MathErrorKind;
 use crate::parser::limits;
-use crate::subtags;
 use crate::subtags::ParseError;
+use crate::{output, subtags};
 
 pub type BytePos = usize;
 
@@ -25,6 +27,16 @@ pub enum ErrorKind {
         /// Position at which the limit was exceeded
         pos: BytePos,
     },
+    /// The tag ran for longer than its time budget
+    Timeout {
+        /// Position at which the deadline was noticed
+        pos: BytePos,
+    },
+    /// The context suspended the tag to wait for a response, see [`crate::resumable`]
+    Suspended {
+        /// Position at which the suspension was noticed
+        pos: BytePos,
+    },
     EmptySubtag {
         span: Range<usize>,
     },
@@ -53,6 +65,36 @@ pub enum ErrorKind {
     RequestLimit {
         span: Range<usize>,
     },
+    /// Too many persistent storage operations in a single run
+    StorageLimit {
+        span: Range<usize>,
+    },
+    /// Too many guild, channel and member lookups in a single run
+    ContextLookupLimit {
+        span: Range<usize>,
+    },
+    /// The persistent variable quota of a scope is exhausted
+    PersistentVarLimit {
+        span: Range<usize>,
+    },
+    PersistentVarValueLengthLimit {
+        length: usize,
+        span: Range<usize>,
+    },
+    /// A part of the embed exceeds Discord's length limit for it
+    EmbedLengthLimit {
+        /// Which part of the embed, e.g. "title"
+        part: &'static str,
+        length: usize,
+        max: usize,
+        span: Range<usize>,
+    },
+    EmbedFieldLimit {
+        span: Range<usize>,
+    },
+    AttachmentLimit {
+        span: Range<usize>,
+    },
     ArgParseError {
         span: Range<usize>,
         err: subtags::ParseError,
@@ -88,6 +130,40 @@ pub enum ErrorKind {
         span: Range<usize>,
     },
 
+    /// Missing argument in a lazily evaluated tag ({foreach}, {for}, {while}, {func})
+    LazyMissingArgument {
+        subtag: &'static str,
+        argument: &'static str,
+        span: Range<usize>,
+    },
+
+    /// `{call}` of a function that was never defined with `{func}`
+    UnknownFunction {
+        name: String,
+        span: Range<usize>,
+    },
+    /// A function tried to call itself, directly or through other functions
+    RecursiveFunction {
+        name: String,
+        span: Range<usize>,
+    },
+    FunctionLimit {
+        span: Range<usize>,
+    },
+
+    /// A regex failed to compile or exceeded the compile limits
+    InvalidRegex {
+        message: String,
+        span: Range<usize>,
+    },
+
+    /// Invalid `{math}` expression. The span is relative to the expression, so this is always
+    /// wrapped in a [`ErrorKind::Nested`] error holding the expression as its source
+    MathError {
+        err: MathErrorKind,
+        span: Range<usize>,
+    },
+
     MissingClosingBrace {
         expected_position: BytePos,
         tag_start: BytePos,
@@ -397,6 +473,22 @@ pub fn format_error(src: &str, err: Error) -> String {
                 span: Some(char_index_to_span(src, pos)),
             });
         },
+        ErrorKind::Timeout { pos } => {
+            db.message = Some("tag took too long to run".into());
+            db.span_notes.push(Note {
+                kind: NoteKind::Error,
+                message: "ran out of time while processing this token".into(),
+                span: Some(char_index_to_span(src, pos)),
+            });
+        },
+        ErrorKind::Suspended { pos } => {
+            db.message = Some("tag was suspended".into());
+            db.span_notes.push(Note {
+                kind: NoteKind::Error,
+                message: "suspended while processing this token".into(),
+                span: Some(char_index_to_span(src, pos)),
+            });
+        },
         ErrorKind::MissingClosingBrace {
             expected_position,
             tag_start,
@@ -456,6 +548,59 @@ pub fn format_error(src: &str, err: Error) -> String {
             format_args!("maximum number of http requests ({}) reached", limits::MAX_REQUESTS),
             Some(span),
         ),
+        ErrorKind::StorageLimit { span } => simple_span_diag(
+            &mut db,
+            format_args!(
+                "maximum number of persistent storage operations ({}) reached",
+                limits::MAX_STORAGE_OPERATIONS
+            ),
+            Some(span),
+        ),
+        ErrorKind::ContextLookupLimit { span } => simple_span_diag(
+            &mut db,
+            format_args!(
+                "maximum number of server, channel and member lookups ({}) reached",
+                limits::MAX_CONTEXT_LOOKUPS
+            ),
+            Some(span),
+        ),
+        ErrorKind::PersistentVarLimit { span } => simple_span_diag(
+            &mut db,
+            format_args!(
+                "cannot store more than {} persistent variables",
+                limits::MAX_PERSISTENT_VARIABLES
+            ),
+            Some(span),
+        ),
+        ErrorKind::PersistentVarValueLengthLimit { span, length } => simple_span_diag(
+            &mut db,
+            format_args!(
+                "persistent variable value is too long ({}>{})",
+                length,
+                limits::MAX_PERSISTENT_VALUE_LENGTH,
+            ),
+            Some(span),
+        ),
+        ErrorKind::EmbedLengthLimit {
+            part,
+            length,
+            max,
+            span,
+        } => simple_span_diag(
+            &mut db,
+            format_args!("embed {part} is too long ({length}>{max})"),
+            Some(span),
+        ),
+        ErrorKind::EmbedFieldLimit { span } => simple_span_diag(
+            &mut db,
+            format_args!("embeds cannot have more than {} fields", output::limits::MAX_FIELDS),
+            Some(span),
+        ),
+        ErrorKind::AttachmentLimit { span } => simple_span_diag(
+            &mut db,
+            format_args!("cannot attach more than {} files", output::limits::MAX_ATTACHMENTS),
+            Some(span),
+        ),
         ErrorKind::IfMissingStmt { span } => simple_span_diag(
             &mut db,
             format_args!("`if` tag is missing a value to compare"),
@@ -480,6 +625,42 @@ pub fn format_error(src: &str, err: Error) -> String {
         ErrorKind::IfInvalidCmp { span } => {
             simple_span_diag(&mut db, format_args!("an invalid comparator was used"), Some(span))
         },
+        ErrorKind::LazyMissingArgument { subtag, argument, span } => simple_span_diag(
+            &mut db,
+            format_args!("`{subtag}` tag is missing its {argument}"),
+            Some(span),
+        ),
+        ErrorKind::UnknownFunction { name, span } => simple_span_diag(
+            &mut db,
+            format_args!("function '{name}' is not defined, use `{{func:{name}|...}}` to define it"),
+            Some(span),
+        ),
+        ErrorKind::RecursiveFunction { name, span } => simple_span_diag(
+            &mut db,
+            format_args!("function '{name}' cannot call itself"),
+            Some(span),
+        ),
+        ErrorKind::FunctionLimit { span } => simple_span_diag(
+            &mut db,
+            format_args!("cannot define more than {} functions", limits::MAX_FUNCTIONS),
+            Some(span),
+        ),
+        ErrorKind::InvalidRegex { message, span } => {
+            db.message = Some("invalid regex".into());
+            db.span_notes.push(Note {
+                kind: NoteKind::Error,
+                message: message.into(),
+                span: Some(span),
+            });
+        },
+        ErrorKind::MathError { err, span } => {
+            db.message = Some("failed to evaluate math expression".into());
+            db.span_notes.push(Note {
+                kind: NoteKind::Error,
+                message: err.to_string().into(),
+                span: Some(span),
+            });
+        },
         ErrorKind::Nested { .. } => unreachable!("nested tag errors are handled separately"),
         ErrorKind::Unknown { message, span } => {
             db.message = Some(message.into());
@@ -493,3 +674,57 @@ pub fn format_error(src: &str, err: Error) -> String {
 
     db.into_string()
 }
+
+pub fn format_lint(src: &str, lint: &Lint) -> String {
+    let mut db = DiagnosticBuilder {
+        src,
+        kind: DiagnosticKind::Warning,
+        message: None,
+        span_notes: Vec::new(),
+    };
+
+    let (message, note): (Cow<'static, str>, Cow<'static, str>) = match &lint.kind {
+        LintKind::UnknownSubtag(name) => (format!("subtag '{name}' does not exist").into(), "".into()),
+        LintKind::EmptySubtag => ("empty subtag".into(), "".into()),
+        LintKind::MissingClosingBrace { .. } => {
+            ("missing closing brace".into(), "expected a closing brace here".into())
+        },
+        LintKind::TooFewArguments { subtag, expected, got } => (
+            format!("`{subtag}` expects at least {expected} argument(s), but got {got}").into(),
+            "".into(),
+        ),
+        LintKind::TooManyArguments { subtag, expected, got } => (
+            format!("`{subtag}` expects at most {expected} argument(s), but got {got}").into(),
+            "these arguments are ignored".into(),
+        ),
+        LintKind::InvalidComparison(cmp) => (
+            format!("'{cmp}' is not a valid comparison").into(),
+            "expected one of =, ~, >, >=, <, <=".into(),
+        ),
+        LintKind::UnreachableBranch { condition } => (
+            format!("this condition is always {condition}").into(),
+            "this branch can never be taken".into(),
+        ),
+        LintKind::NestingLimit => (
+            "subtags are nested too deeply".into(),
+            "the rest of the tag was not checked".into(),
+        ),
+    };
+
+    db.message = Some(message);
+    db.span_notes.push(Note {
+        kind: NoteKind::Warning,
+        message: note,
+        span: Some(lint.span.clone()),
+    });
+
+    if let LintKind::MissingClosingBrace { tag_start } = lint.kind {
+        db.span_notes.push(Note {
+            kind: NoteKind::Help,
+            message: "tag parsing begins here".into(),
+            span: Some(tag_start..tag_start + 1),
+        });
+    }
+
+    db.into_string()
+}
@@ -3,37 +3,92 @@
 
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::time::Instant;
 
-use assyst_common::util::filetype::Type;
-pub use context::{Context, NopContext};
+pub use context::{Context, NopContext, StorageScope};
 use errors::TResult;
-use parser::{Counter, ParseMode, Parser, SharedState};
+use parser::{Counter, Functions, ParseMode, Parser, SharedState};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+pub use resumable::AsyncContext;
+use trace::Trace;
 
+pub mod check;
 mod context;
 pub mod errors;
+mod list;
+pub mod math;
+pub mod output;
 pub mod parser;
+pub mod registry;
+pub mod resumable;
 mod subtags;
+pub mod trace;
 
 #[derive(Debug)]
 pub struct ParseResult {
     pub output: String,
-    pub attachment: Option<(Vec<u8>, Type)>,
+    pub attachments: Vec<output::Attachment>,
+    pub embed: Option<output::Embed>,
 }
 
 pub fn parse<C: Context>(input: &str, args: &[&str], mode: ParseMode, cx: C) -> TResult<ParseResult> {
+    parse_inner(input, args, mode, &cx, None, None)
+}
+
+/// Same as [`parse`], but seeds the random number generator with `seed`, so that subtags like
+/// `{choose}` and `{range}` produce the same output for the same input.
+pub fn parse_with_seed<C: Context>(
+    input: &str,
+    args: &[&str],
+    mode: ParseMode,
+    cx: C,
+    seed: u64,
+) -> TResult<ParseResult> {
+    parse_inner(input, args, mode, &cx, None, Some(seed))
+}
+
+/// Same as [`parse`], but also records every subtag invocation. The trace is returned even if
+/// parsing fails, so it can be used to find out where things went wrong.
+pub fn parse_traced<C: Context>(input: &str, args: &[&str], mode: ParseMode, cx: C) -> (TResult<ParseResult>, Trace) {
+    let trace = RefCell::new(Trace::default());
+    let result = parse_inner(input, args, mode, &cx, Some(&trace), None);
+    (result, trace.into_inner())
+}
+
+fn parse_inner(
+    input: &str,
+    args: &[&str],
+    mode: ParseMode,
+    cx: &dyn Context,
+    trace: Option<&RefCell<Trace>>,
+    seed: Option<u64>,
+) -> TResult<ParseResult> {
     let variables = RefCell::new(HashMap::new());
-    let counter = Counter::default();
-    let attachment = RefCell::new(None);
-    let state = SharedState::new(&variables, &counter, &attachment);
+    let counter = Counter::with_deadline(Instant::now() + cx.time_budget());
+    let attachments = RefCell::new(Vec::new());
+    let embed = RefCell::new(None);
+    let functions = RefCell::new(Functions::default());
+    let rng = RefCell::new(seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64));
+    let mut state = SharedState::new(&variables, &counter, &attachments, &embed, &functions, &rng);
+    if let Some(trace) = trace {
+        state = state.with_trace(trace);
+    }
 
-    let output = Parser::new(input.as_bytes(), args, state, mode, &cx).parse_segment(true)?;
+    let output = Parser::new(input.as_bytes(), args, state, mode, cx).parse_segment(true)?;
 
     Ok(ParseResult {
         output,
-        attachment: attachment.into_inner(),
+        attachments: attachments.into_inner(),
+        embed: embed.into_inner(),
     })
 }
 
+/// Checks a tag for mistakes without running it, see [`check::check`]
+pub fn check(input: &str) -> Vec<check::Lint> {
+    check::check(input)
+}
+
 /// NOTE: be careful when bubbling up potential errors -- you most likely want to wrap them in
 /// `ErrorKind::Nested`
 pub fn parse_with_parent(input: &str, parent: &Parser<'_>, side_effects: bool) -> TResult<String> {
@@ -43,6 +98,7 @@ pub fn parse_with_parent(input: &str, parent: &Parser<'_>, side_effects: bool) -
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::check::LintKind;
     use crate::errors::ErrorKind;
 
     macro_rules! test {
@@ -80,8 +136,921 @@ mod tests {
         spoiler2: "a||b||" => Ok("a||b||"),
         spoiler_in_subparser: "{eval:a||b||c}" => Ok("a"),
         spoiler_in_subtag: "{note:a||b||c}" => Err(ErrorKind::MissingClosingBrace { .. }),
+        foreach_works: "{foreach:x|a b c|{get:x}-}" => Ok("a-b-c-"),
+        foreach_restores_variable: "{set:x|1}{foreach:x|a b|{get:x}}{get:x}" => Ok("ab1"),
+        for_works: "{for:i|1|3|{get:i}}" => Ok("123"),
+        for_counts_down: "{for:i|3|1|{get:i}}" => Ok("321"),
+        for_body_is_lazy: "{for:i|3|1|{if:{get:i}|=|2|{arg:5}|}}" => Err(ErrorKind::Nested { .. }),
+        while_works: "{set:i|a}{while:{get:i}|=|a|b{set:i|c}}" => Ok("b"),
+        while_iter_limit: "{while:a|=|a|b}" => Err(ErrorKind::Nested { .. }),
+        while_missing_body: "{while:a|=|a}" => Err(ErrorKind::LazyMissingArgument { .. }),
+        pset_key_too_long: &format!("{{pset:{}|1}}", "k".repeat(101)) => Err(ErrorKind::VarKeyLengthLimit { .. }),
+        math_precedence: "{math:1 + 2 * 3 ^ 2}" => Ok("19"),
+        math_parentheses: "{math:(1 + 2) * -3}" => Ok("-9"),
+        math_power_right_assoc: "{math:2 ^ 3 ^ 2}" => Ok("512"),
+        math_inexact_division: "{math:7 / 2}" => Ok("3.5"),
+        math_exact_division: "{math:8 / 2}" => Ok("4"),
+        math_functions: "{math:max(1, sqrt(16), 3) + abs(-2)}" => Ok("6"),
+        math_comparison: "{math:2 * 2 >= 4}" => Ok("1"),
+        math_overflow_falls_back_to_float: "{math:9223372036854775807 + 1}" => Ok("9223372036854776000"),
+        math_min_remainder: "{math:-9223372036854775808 % -1}" => Ok("0"),
+        math_with_subtags: "{math:{argslen} + 1}" => Ok("1"),
+        math_division_by_zero: "{math:1 / 0}" => Err(ErrorKind::Nested { .. }),
+        math_unknown_function: "{math:foo(1)}" => Err(ErrorKind::Nested { .. }),
+        math_unclosed_parenthesis: "{math:(1 + 2}" => Err(ErrorKind::Nested { .. }),
+        math_trailing_token: "{math:1 2}" => Err(ErrorKind::Nested { .. }),
+        math_too_deep: &format!("{{math:{}1}}", "-".repeat(100)) => Err(ErrorKind::Nested { .. }),
+        substring_range: "{substring:1|3|héllo}" => Ok("él"),
+        substring_open_end: "{substring:2|12345}" => Ok("345"),
+        substring_negative_start: "{substring:-2|12345}" => Ok("45"),
+        substring_negative_range: "{substring:-4|-1|héllo}" => Ok("éll"),
+        substring_negative_past_start: "{substring:-9|2|abc}" => Ok("ab"),
+        indexof_found: "{indexof:l|héllo}" => Ok("2"),
+        indexof_missing: "{indexof:z|hello}" => Ok("-1"),
+        split_works: "{split:,|a,b,c}" => Ok(r#"["a","b","c"]"#),
+        split_characters: "{split:|héy}" => Ok(r#"["h","é","y"]"#),
+        split_index: "{index:{split:,|a,b,c}|1}" => Ok("b"),
+        split_index_out_of_bounds: "{index:{split:,|a,b,c}|3}" => Err(ErrorKind::IndexOutOfBounds { .. }),
+        join_works: "{join:-|a|b|c}" => Ok("a-b-c"),
+        trim_works: "{trim:  a b  }" => Ok("a b"),
+        padstart_works: "{padstart:5|0|42}" => Ok("00042"),
+        padend_works: "{padend:4|ab|x}" => Ok("xaba"),
+        pad_too_long: "{padstart:300000|0|1}" => Err(ErrorKind::StringLengthLimit { .. }),
+        repeat_too_long: "{repeat:100000|abc}" => Err(ErrorKind::StringLengthLimit { .. }),
+        replace_empty_pattern_too_long: &format!("{{replace:|{}|{}}}", "a".repeat(1000), "b".repeat(1000)) => Err(ErrorKind::StringLengthLimit { .. }),
+        urlencode_works: "{urlencode:a b&c}" => Ok("a%20b%26c"),
+        urldecode_works: "{urldecode:a%20b}" => Ok("a b"),
+        match_works: "{match:[0-9]+|abc123def}" => Ok("123"),
+        match_invalid_regex: "{match:(|abc}" => Err(ErrorKind::InvalidRegex { .. }),
+        match_regex_too_big: "{match:\\w\\{1000\\}\\{1000\\}|abc}" => Err(ErrorKind::InvalidRegex { .. }),
+        regexreplace_works: "{regexreplace:(\\w+)@(\\w+)|$2 at $1|me@host}" => Ok("host at me"),
+        regexreplace_expansion_too_long: "{regexreplace:.+|{repeat:80000|$0}|{repeat:200000|a}}" => Err(ErrorKind::StringLengthLimit { .. }),
+        iequals_works: "{iequals:ÄbC|äBc}" => Ok("1"),
+        list_serialises: "{list:a|b c|\"}" => Ok(r#"["a","b c","\""]"#),
+        list_index: "{index:{list:a|b|c}|1}" => Ok("b"),
+        list_index_out_of_bounds: "{index:{list:a}|1}" => Err(ErrorKind::IndexOutOfBounds { .. }),
+        list_not_a_list: "{listlen:abc}" => Err(ErrorKind::ArgParseError { .. }),
+        list_push_pop: "{push:x|a|b}{push:x|c}{pop:x}{listlen:{get:x}}" => Ok("c2"),
+        list_pop_empty: "{pop:x}" => Ok(""),
+        list_slice: "{slice:{list:a|b|c|d}|1|3}" => Ok(r#"["b","c"]"#),
+        list_sort_numeric: "{sort:[10,9,\"100\"]}" => Ok(r#"["9","10","100"]"#),
+        list_sort_strings: "{sort:{list:b|c|a}}" => Ok(r#"["a","b","c"]"#),
+        list_shuffle_keeps_items: "{listlen:{shuffle:{list:a|b|c}}}" => Ok("3"),
+        list_join: "{join:, |{list:a|b|c}}" => Ok("a, b, c"),
+        list_choose: "{choose:{list:a}}" => Ok("a"),
+        foreach_list: "{foreach:x|{list:a b|c}|[{get:x}]}" => Ok("[a b][c]"),
+        func_call: "{func:greet|hi {arg:0}!}{call:greet|a}{call:greet|b}" => Ok("hi a!hi b!"),
+        func_args_are_scoped: "{func:f|{argslen}}{call:f|a|b}{argslen}" => Ok("20"),
+        func_shares_variables: "{func:f|{set:x|1}}{call:f}{get:x}" => Ok("1"),
+        func_body_is_lazy: "{func:f|{arg:0}}" => Ok(""),
+        func_redefine: "{func:f|a}{func:f|b}{call:f}" => Ok("b"),
+        func_missing_body: "{func:f}" => Err(ErrorKind::LazyMissingArgument { .. }),
+        call_unknown_function: "{call:f}" => Err(ErrorKind::UnknownFunction { .. }),
+        call_recursion: "{func:f|{call:f}}{call:f}" => Err(ErrorKind::Nested { .. }),
+        pset_value_too_long: &format!("{{pset:k|{}}}", "v".repeat(2001)) => Err(ErrorKind::PersistentVarValueLengthLimit { .. }),
+        embedcolour_invalid: "{embedcolour:red}" => Err(ErrorKind::ArgParseError { .. }),
+        embedcolour_sign: "{embedcolour:+ff}" => Err(ErrorKind::ArgParseError { .. }),
+        embedcolour_prefixed_sign: "{embedcolour:#+1}" => Err(ErrorKind::ArgParseError { .. }),
+        embedtitle_too_long: &format!("{{embedtitle:{}}}", "t".repeat(257)) => Err(ErrorKind::EmbedLengthLimit { .. }),
+        embedfield_limit: &"{embedfield:a|b}".repeat(26) => Err(ErrorKind::EmbedFieldLimit { .. }),
+        attach_limit: &"{attach:a.txt|a}".repeat(11) => Err(ErrorKind::AttachmentLimit { .. }),
+        image_calls_context: "{image:caption|https://example.com/a.png|hi}" => Err(ErrorKind::Unknown { .. }),
+    );
+
+    #[test]
+    fn embed_subtags() {
+        let res = parse(
+            "{embedtitle:hi}{embedcolour:#ff8000}{embedfield:a|b|inline}{embedfield:c|d}text",
+            &[],
+            ParseMode::StopOnError,
+            NopContext,
+        )
+        .unwrap();
+        assert_eq!(res.output, "text");
+
+        let embed = res.embed.unwrap();
+        assert_eq!(embed.title.as_deref(), Some("hi"));
+        assert_eq!(embed.colour, Some(0xff8000));
+        assert_eq!(embed.description, None);
+        assert_eq!(
+            embed.fields.iter().map(|field| field.inline).collect::<Vec<_>>(),
+            [true, false]
+        );
+    }
+
+    #[test]
+    fn attach_subtag() {
+        let res = parse(
+            "{attach:a.txt|hello}{attach:b.txt|world}",
+            &[],
+            ParseMode::StopOnError,
+            NopContext,
+        )
+        .unwrap();
+        assert!(res.embed.is_none());
+        assert_eq!(
+            res.attachments,
+            [
+                output::Attachment {
+                    name: "a.txt".into(),
+                    data: b"hello".to_vec()
+                },
+                output::Attachment {
+                    name: "b.txt".into(),
+                    data: b"world".to_vec()
+                }
+            ]
+        );
+    }
+
+    fn lint_kinds(input: &str) -> Vec<LintKind> {
+        let lints = check(input);
+        for lint in &lints {
+            // try formatting it to find any potential panic bugs
+            errors::format_lint(input, lint);
+        }
+        lints.into_iter().map(|lint| lint.kind).collect()
+    }
+
+    #[test]
+    fn check_valid_tag() {
+        assert_eq!(
+            lint_kinds("{if:{arg:0}|=|a|{set:x|1}|{get:x}} {note:{anything|goes}}"),
+            []
+        );
+    }
+
+    #[test]
+    fn check_unknown_subtag() {
+        assert_eq!(
+            lint_kinds("{foo:{bar}}"),
+            [
+                LintKind::UnknownSubtag("bar".into()),
+                LintKind::UnknownSubtag("foo".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn check_arity() {
+        assert!(matches!(
+            &lint_kinds("{set:x}")[..],
+            [LintKind::TooFewArguments { got: 1, .. }]
+        ));
+        assert!(matches!(
+            &lint_kinds("{argslen:x}")[..],
+            [LintKind::TooManyArguments { got: 1, .. }]
+        ));
+    }
+
+    /// The arity of eager subtags comes from their handlers, but lazy subtags parse their own
+    /// arguments, so check that the arity in the registry matches what they accept.
+    #[test]
+    fn lazy_subtag_arity() {
+        let is_missing_argument = |kind: &ErrorKind| {
+            matches!(
+                kind,
+                ErrorKind::LazyMissingArgument { .. }
+                    | ErrorKind::IfMissingStmt { .. }
+                    | ErrorKind::IfMissingCmp { .. }
+                    | ErrorKind::IfMissingValue { .. }
+                    | ErrorKind::IfMissingThen { .. }
+                    | ErrorKind::IfMissingElse { .. }
+            )
+        };
+        let run = |name: &str, args: usize| {
+            let input = match args {
+                0 => format!("{{{name}}}"),
+                _ => format!("{{{name}:{}}}", vec!["="; args].join("|")),
+            };
+            parse(&input, &[], ParseMode::StopOnError, NopContext)
+        };
+
+        for subtag in registry::SUBTAGS {
+            if !matches!(subtag.handler, registry::Handler::Lazy(_)) {
+                continue;
+            }
+
+            if subtag.min_args > 0 {
+                let err = run(subtag.name, subtag.min_args - 1).err();
+                assert!(
+                    err.is_some_and(|err| is_missing_argument(&err.kind)),
+                    "{{{}}} accepts fewer than {} arguments",
+                    subtag.name,
+                    subtag.min_args
+                );
+            }
+
+            let max = subtag
+                .max_args
+                .expect("lazy subtags take a bounded number of arguments");
+            if let Err(err) = run(subtag.name, max) {
+                assert!(
+                    !is_missing_argument(&err.kind),
+                    "{{{}}} requires more than {max} arguments",
+                    subtag.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn check_missing_closing_brace() {
+        assert!(matches!(
+            &lint_kinds("{arg:0")[..],
+            [LintKind::MissingClosingBrace { tag_start: 0 }]
+        ));
+        assert!(matches!(
+            &lint_kinds("{arg:0|ü")[..],
+            [LintKind::MissingClosingBrace { .. }]
+        ));
+    }
+
+    #[test]
+    fn check_deeply_nested() {
+        let input = "{a:".repeat(2000);
+        assert_eq!(lint_kinds(&input), [LintKind::NestingLimit]);
+
+        let depth = check::MAX_NESTING as usize;
+        let input = format!("{}{}", "{argslen:".repeat(depth), "}".repeat(depth));
+        assert!(
+            lint_kinds(&input)
+                .iter()
+                .all(|kind| matches!(kind, LintKind::TooManyArguments { .. }))
+        );
+    }
+
+    #[test]
+    fn check_if() {
+        assert_eq!(
+            lint_kinds("{if:a|=|a|yes|no}"),
+            [LintKind::UnreachableBranch { condition: true }]
+        );
+        assert_eq!(
+            lint_kinds("{if:1|>|2|yes|no}"),
+            [LintKind::UnreachableBranch { condition: false }]
+        );
+        assert_eq!(
+            lint_kinds("{if:a|==|{arg:0}|yes|no}"),
+            [LintKind::InvalidComparison("==".into())]
+        );
+    }
+
+    #[test]
+    fn trace_records_invocations() {
+        let (res, trace) = parse_traced(
+            "{upper:{arg:0}}{if:a|=|a|{argslen}|}",
+            &["x"],
+            ParseMode::StopOnError,
+            NopContext,
+        );
+        assert_eq!(res.unwrap().output, "X1");
+
+        let events = trace
+            .events()
+            .iter()
+            .map(|event| (event.depth, &*event.name))
+            .collect::<Vec<_>>();
+        assert_eq!(events, [(0, "upper"), (1, "arg"), (0, "if"), (1, "argslen")]);
+        assert_eq!(trace.events()[0].args, ["x"]);
+        assert_eq!(trace.events()[0].source, "{upper:{arg:0}}");
+        assert_eq!(trace.events()[0].span, 0..15);
+        assert_eq!(trace.events()[1].span, 7..14);
+        assert_eq!(trace.events()[3].span, 25..34);
+        assert!(matches!(&trace.events()[0].outcome, trace::TraceOutcome::Ok(output) if output == "X"));
+
+        trace.render(true);
+    }
+
+    #[test]
+    fn trace_records_errors() {
+        let (res, trace) = parse_traced("{upper:{arg:5}}", &[], ParseMode::StopOnError, NopContext);
+        assert!(res.is_err());
+        assert!(matches!(trace.events()[0].outcome, trace::TraceOutcome::Unfinished));
+        assert_eq!(trace.events()[0].span, 0..0);
+        assert!(matches!(trace.events()[1].outcome, trace::TraceOutcome::Error));
+    }
+
+    #[test]
+    fn deadline_aborts_tag() {
+        let variables = RefCell::new(HashMap::new());
+        let counter = Counter::with_deadline(Instant::now());
+        let attachments = RefCell::new(Vec::new());
+        let embed = RefCell::new(None);
+        let functions = RefCell::new(Functions::default());
+        let rng = RefCell::new(StdRng::seed_from_u64(0));
+        let state = SharedState::new(&variables, &counter, &attachments, &embed, &functions, &rng);
+
+        let res = Parser::new(b"a{argslen}", &[], state, ParseMode::IgnoreOnError, &NopContext).parse_segment(true);
+        assert!(matches!(res.map_err(|err| *err.kind), Err(ErrorKind::Timeout { .. })));
+    }
+
+    #[test]
+    fn registry_names_are_unique() {
+        let mut names = std::collections::HashSet::new();
+        for subtag in registry::SUBTAGS {
+            for name in std::iter::once(subtag.name).chain(subtag.aliases.iter().copied()) {
+                assert!(names.insert(name), "duplicate subtag name {name}");
+            }
+            assert!(subtag.signature.starts_with(&format!("{{{}", subtag.name)));
+            assert!(!subtag.description.is_empty());
+        }
+    }
+
+    #[test]
+    fn registry_lookup() {
+        assert_eq!(registry::get("javascript").map(|subtag| subtag.name), Some("js"));
+        assert!(registry::get("foo").is_none());
+
+        let names = registry::search("embed")
+            .iter()
+            .map(|subtag| subtag.name)
+            .collect::<Vec<_>>();
+        assert!(names.iter().all(|name| name.starts_with("embed")));
+        assert!(names.contains(&"embedcolour"));
+
+        // prefix matches come before other matches
+        let names = registry::search("len")
+            .iter()
+            .map(|subtag| subtag.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names[0], "length");
+        assert!(names.contains(&"argslen"));
+    }
+
+    /// A [`Context`] with canned responses, for the golden tests
+    #[derive(Default)]
+    struct MockContext {
+        /// Makes downloads wait for a response from it, if set
+        io: Option<Io>,
+        tags: HashMap<&'static str, &'static str>,
+        downloads: HashMap<&'static str, &'static str>,
+        javascript: HashMap<&'static str, &'static str>,
+        persistent: RefCell<HashMap<String, String>>,
+    }
+
+    impl MockContext {
+        fn tag(mut self, name: &'static str, content: &'static str) -> Self {
+            self.tags.insert(name, content);
+            self
+        }
+
+        fn download(mut self, url: &'static str, content: &'static str) -> Self {
+            self.downloads.insert(url, content);
+            self
+        }
+
+        fn javascript(mut self, code: &'static str, output: &'static str) -> Self {
+            self.javascript.insert(code, output);
+            self
+        }
+
+        fn persistent(self, scope: StorageScope, key: &str, value: &str) -> Self {
+            self.persistent
+                .borrow_mut()
+                .insert(format!("{scope:?}:{key}"), value.to_owned());
+            self
+        }
+    }
+
+    fn missing<T>(what: &str) -> anyhow::Result<T> {
+        Err(anyhow::anyhow!("no mocked response for {what}"))
+    }
+
+    impl Context for MockContext {
+        fn execute_javascript(
+            &self,
+            code: &str,
+            _: Vec<String>,
+        ) -> anyhow::Result<assyst_common::eval::FakeEvalImageResponse> {
+            match self.javascript.get(code) {
+                Some(output) => Ok(assyst_common::eval::FakeEvalImageResponse::Text(
+                    assyst_common::eval::FakeEvalResponse {
+                        message: (*output).to_owned(),
+                    },
+                )),
+                None => missing(code),
+            }
+        }
+
+        fn get_last_attachment(&self) -> anyhow::Result<String> {
+            Ok("https://example.com/last.png".into())
+        }
+
+        fn get_avatar(&self, user_id: Option<u64>) -> anyhow::Result<String> {
+            Ok(format!("https://example.com/avatars/{}.png", user_id.unwrap_or(1)))
+        }
+
+        fn download(&self, url: &str) -> anyhow::Result<String> {
+            if let Some(io) = &self.io {
+                let (tx, rx) = std::sync::mpsc::channel();
+                io.submit(move || tx.send(()).unwrap());
+                rx.recv().unwrap();
+            }
+            self.downloads
+                .get(url)
+                .map_or_else(|| missing(url), |content| Ok((*content).to_owned()))
+        }
+
+        fn channel_id(&self) -> anyhow::Result<u64> {
+            Ok(2)
+        }
+
+        fn guild_id(&self) -> anyhow::Result<u64> {
+            Ok(3)
+        }
+
+        fn user_id(&self) -> anyhow::Result<u64> {
+            Ok(1)
+        }
+
+        fn user_tag(&self, id: Option<u64>) -> anyhow::Result<String> {
+            Ok(format!("user{}", id.unwrap_or(1)))
+        }
+
+        fn get_tag_contents(&self, tag: &str) -> anyhow::Result<String> {
+            self.tags
+                .get(tag)
+                .map_or_else(|| missing(tag), |content| Ok((*content).to_owned()))
+        }
+
+        fn get_persistent(&self, scope: StorageScope, key: &str) -> anyhow::Result<Option<String>> {
+            Ok(self.persistent.borrow().get(&format!("{scope:?}:{key}")).cloned())
+        }
+
+        fn set_persistent(&self, scope: StorageScope, key: &str, value: &str) -> anyhow::Result<bool> {
+            self.persistent
+                .borrow_mut()
+                .insert(format!("{scope:?}:{key}"), value.to_owned());
+            Ok(true)
+        }
+
+        fn delete_persistent(&self, scope: StorageScope, key: &str) -> anyhow::Result<()> {
+            self.persistent.borrow_mut().remove(&format!("{scope:?}:{key}"));
+            Ok(())
+        }
+
+        fn time_budget(&self) -> std::time::Duration {
+            parser::limits::DEFAULT_TIME_BUDGET
+        }
+
+        fn run_flux(&self, operation: &str, _: Vec<String>, _: &str) -> anyhow::Result<Vec<u8>> {
+            missing(operation)
+        }
+
+        fn guild_name(&self) -> anyhow::Result<String> {
+            Ok("Test Server".into())
+        }
+
+        fn guild_member_count(&self) -> anyhow::Result<u64> {
+            Ok(42)
+        }
+
+        fn channel_name(&self) -> anyhow::Result<String> {
+            Ok("general".into())
+        }
+
+        fn channel_topic(&self) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        fn nickname(&self) -> anyhow::Result<Option<String>> {
+            Ok(Some("nick".into()))
+        }
+
+        fn role_names(&self) -> anyhow::Result<Vec<String>> {
+            Ok(vec!["Admin".into(), "Member".into()])
+        }
+
+        fn joined_at(&self) -> anyhow::Result<i64> {
+            Ok(1_600_000_000)
+        }
+
+        fn replied_message_content(&self) -> anyhow::Result<Option<String>> {
+            Ok(Some("replied".into()))
+        }
+
+        fn message_id(&self) -> anyhow::Result<u64> {
+            Ok(4)
+        }
+    }
+
+    /// The seed of the random number generator in the golden tests
+    const GOLDEN_SEED: u64 = 42;
+
+    /// Golden tests pin the exact output of a tag for the given arguments and context. Randomness
+    /// is seeded with [`GOLDEN_SEED`], so changing how a random subtag uses the random number
+    /// generator changes their output.
+    macro_rules! golden {
+        ($( $name:ident: $input:expr, [$($arg:expr),*], $cx:expr => $expected:expr ),+ $(,)?) => {
+            $(
+                #[test]
+                fn $name() {
+                    let input = $input;
+                    let res = parse_with_seed(input, &[$($arg),*], ParseMode::StopOnError, $cx, GOLDEN_SEED);
+                    assert_eq!(
+                        res.map(|res| res.output).map_err(|err| errors::format_error(input, err)),
+                        Ok($expected.to_owned())
+                    );
+                }
+            )*
+        };
+    }
+
+    golden!(
+        golden_choose: "{choose:a|b|c|d|e}{choose:a|b|c|d|e}{choose:a|b|c|d|e}", [], MockContext::default() => "dca",
+        golden_range: "{range:1|100} {range:1|100}", [], MockContext::default() => "53 55",
+        golden_shuffle: "{shuffle:{list:a|b|c|d}}", [], MockContext::default() => r#"["d","b","a","c"]"#,
+        golden_random_in_subparser: "{eval:{range:1|100}} {range:1|100}", [], MockContext::default() => "53 55",
+        golden_seed: "{seed:7}{range:1|1000000}", [], MockContext::default() => "30318",
+        golden_args: "{arg:1} {args} {argslen} {tryarg:5}.", ["a", "b"], MockContext::default() => "b a b 2 .",
+        golden_download: "{length:{download:https://example.com}}", [], MockContext::default().download("https://example.com", "hello") => "5",
+        golden_tag: "{tag:greet|world}!", [], MockContext::default().tag("greet", "hello {arg:0}") => "hello world!",
+        golden_javascript: "{js:1 + 1}", [], MockContext::default().javascript("1 + 1", "2") => "2",
+        golden_persistent: "{pset:x|1}{pget:x} {gget:counter}", [], MockContext::default().persistent(StorageScope::Guild, "counter", "5") => "1 5",
+        golden_discord: "{mention} {usertag:5} {servername} #{channelname} {membercount} {index:{roles}|0}", [], MockContext::default() => "<@1> user5 Test Server #general 42 Admin",
+        golden_context_lookups_are_not_requests: "{servername}{membercount}{channelname}{channeltopic}{nickname}{length:{download:https://example.com}}", [], MockContext::default().download("https://example.com", "hello") => "Test Server42generalnick5",
     );
 
+    #[test]
+    fn context_lookup_limit() {
+        let input = "{servername}".repeat(parser::limits::MAX_CONTEXT_LOOKUPS as usize + 1);
+        let res = parse(&input, &[], ParseMode::StopOnError, MockContext::default());
+        assert!(matches!(
+            res.map_err(|err| *err.kind),
+            Err(ErrorKind::ContextLookupLimit { .. })
+        ));
+    }
+
+    #[test]
+    fn seed_makes_output_reproducible() {
+        let input = "{seed:123}{range:1|1000000} {choose:a|b|c|d|e|f} {shuffle:{list:a|b|c|d|e}}";
+        let outputs = (0..3)
+            .map(|seed| {
+                parse_with_seed(input, &[], ParseMode::StopOnError, NopContext, seed)
+                    .unwrap()
+                    .output
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outputs[0], outputs[1]);
+        assert_eq!(outputs[0], outputs[2]);
+
+        let output = parse(input, &[], ParseMode::StopOnError, NopContext).unwrap().output;
+        assert_eq!(output, outputs[0]);
+    }
+
+    /// Runs a tag with [`resumable::ResumableParse`], returning its output and how often it was
+    /// suspended
+    fn run_resumable(input: &str, cx: &MockContext) -> (TResult<ParseResult>, usize) {
+        let mut parse = resumable::ResumableParse::new(
+            input.to_owned(),
+            Vec::new(),
+            ParseMode::StopOnError,
+            parser::limits::DEFAULT_TIME_BUDGET,
+        )
+        .with_seed(GOLDEN_SEED);
+
+        let mut suspensions = 0;
+        loop {
+            match parse.step() {
+                resumable::Step::Done(result) => return (result, suspensions),
+                resumable::Step::Suspended(request) => {
+                    suspensions += 1;
+                    parse.respond(request.perform_blocking(cx));
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn resumable_matches_sync() {
+        let cx = || {
+            MockContext::default()
+                .download("https://example.com", "hello")
+                .tag("greet", "hello {arg:0} from {servername}")
+                .persistent(StorageScope::Tag, "x", "1")
+        };
+        for input in [
+            "{range:1|100} {download:https://example.com} {range:1|100}",
+            "{tag:greet|world} {pget:x} {pset:x|2}{pget:x} {choose:a|b|c}",
+            "{mention} {nickname} {roles} {joindate}",
+            "{download:https://example.com/missing}",
+            "{if:{userid}|=|1|{channelname}|{arg:0}}",
+        ] {
+            let expected = parse_with_seed(input, &[], ParseMode::StopOnError, cx(), GOLDEN_SEED);
+            let (result, _) = run_resumable(input, &cx());
+            assert_eq!(
+                result
+                    .map(|res| res.output)
+                    .map_err(|err| errors::format_error(input, err)),
+                expected
+                    .map(|res| res.output)
+                    .map_err(|err| errors::format_error(input, err)),
+            );
+        }
+    }
+
+    #[test]
+    fn resumable_does_not_repeat_requests() {
+        let cx = MockContext::default().persistent(StorageScope::Guild, "counter", "5");
+        let (result, suspensions) = run_resumable(
+            "{gset:counter|{math:{gget:counter} + 1}}{gget:counter} {userid}{userid}{userid}",
+            &cx,
+        );
+        assert_eq!(result.unwrap().output, "6 111");
+        assert_eq!(
+            cx.get_persistent(StorageScope::Guild, "counter").unwrap().as_deref(),
+            Some("6")
+        );
+        // the user ID is only requested once
+        assert_eq!(suspensions, 4);
+    }
+
+    #[test]
+    fn resumable_stops_at_suspension() {
+        let mut parse = resumable::ResumableParse::new(
+            "{download:https://example.com}{userid}{range:1|100}".to_owned(),
+            Vec::new(),
+            ParseMode::StopOnError,
+            parser::limits::DEFAULT_TIME_BUDGET,
+        )
+        .traced();
+
+        let resumable::Step::Suspended(request) = parse.step() else {
+            panic!("the tag was not suspended");
+        };
+        assert_eq!(
+            request,
+            resumable::Request::Download {
+                url: "https://example.com".to_owned()
+            }
+        );
+        let trace = parse.into_trace();
+        let names = trace
+            .events()
+            .iter()
+            .map(|event| event.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["download"]);
+    }
+
+    /// A tag that includes `count` different tags, each of which suspends it once
+    fn many_suspensions(count: u32) -> (String, MockContext) {
+        let mut cx = MockContext::default();
+        let mut input = String::new();
+        for i in 0..count {
+            let name: &'static str = Box::leak(format!("t{i}").into_boxed_str());
+            cx = cx.tag(name, "x");
+            input += &format!("{{tag:{name}}}");
+        }
+        (input, cx)
+    }
+
+    #[test]
+    fn resumable_suspension_limit() {
+        let max = parser::limits::MAX_SUSPENSIONS;
+
+        let (input, cx) = many_suspensions(max);
+        let (result, suspensions) = run_resumable(&input, &cx);
+        assert_eq!(result.unwrap().output, "x".repeat(max as usize));
+        assert_eq!(suspensions, max as usize);
+
+        let (input, cx) = many_suspensions(max + 1);
+        let (result, suspensions) = run_resumable(&input, &cx);
+        assert!(matches!(
+            *result.unwrap_err().kind,
+            ErrorKind::Unknown { ref message, .. } if message == "The tag waited for too many responses"
+        ));
+        assert_eq!(suspensions, max as usize);
+    }
+
+    #[test]
+    fn resumable_replay_work() {
+        let max = parser::limits::MAX_SUSPENSIONS as usize;
+        let (input, cx) = many_suspensions(max as u32);
+        let mut parse = resumable::ResumableParse::new(
+            input,
+            Vec::new(),
+            ParseMode::StopOnError,
+            parser::limits::DEFAULT_TIME_BUDGET,
+        )
+        .traced();
+
+        let mut subtags = 0;
+        loop {
+            let step = parse.step();
+            subtags += parse.trace().unwrap().events().len();
+            match step {
+                resumable::Step::Done(result) => {
+                    assert!(result.is_ok());
+                    break;
+                },
+                resumable::Step::Suspended(request) => parse.respond(request.perform_blocking(&cx)),
+            }
+        }
+        // the k-th replay runs the k subtags before its suspension and the one it is suspended at,
+        // and the last run runs all of them. This is the number in the module documentation of
+        // `resumable`
+        assert_eq!(subtags, max * (max + 1) / 2 + max);
+        assert_eq!(subtags, 2144);
+    }
+
+    #[test]
+    #[ignore = "sleeps to wait longer than the time budget"]
+    fn resumable_budget_excludes_waiting() {
+        use std::time::Duration;
+
+        let cx = MockContext::default().download("https://example.com", "a");
+        let mut parse = resumable::ResumableParse::new(
+            "{download:https://example.com}".to_owned(),
+            Vec::new(),
+            ParseMode::StopOnError,
+            Duration::from_millis(100),
+        );
+
+        let resumable::Step::Suspended(request) = parse.step() else {
+            panic!("the tag was not suspended");
+        };
+        std::thread::sleep(Duration::from_millis(200));
+        parse.respond(request.perform_blocking(&cx));
+        let resumable::Step::Done(result) = parse.step() else {
+            panic!("the tag was suspended again");
+        };
+        assert_eq!(result.unwrap().output, "a");
+    }
+
+    #[test]
+    fn resumable_timeout_points_at_suspension() {
+        let mut parse = resumable::ResumableParse::new(
+            "ab{download:https://example.com}".to_owned(),
+            Vec::new(),
+            ParseMode::StopOnError,
+            parser::limits::DEFAULT_TIME_BUDGET,
+        );
+
+        let resumable::Step::Suspended(_) = parse.step() else {
+            panic!("the tag was not suspended");
+        };
+        assert!(matches!(*parse.timeout().kind, ErrorKind::Timeout { pos } if pos > 2));
+    }
+
+    /// A tag does not hold a thread while it waits for a response, so a single thread can start
+    /// every tag before any of them gets its first response, and then serve them all in each round
+    #[test]
+    fn resumable_tags_wait_concurrently() {
+        const TAGS: usize = 64;
+        let requests = parser::limits::MAX_REQUESTS as usize;
+        let input = "{download:https://example.com}".repeat(requests);
+        let cx = MockContext::default().download("https://example.com", "a");
+
+        let mut waiting = (0..TAGS)
+            .map(|_| {
+                let mut parse = resumable::ResumableParse::new(
+                    input.clone(),
+                    Vec::new(),
+                    ParseMode::StopOnError,
+                    parser::limits::DEFAULT_TIME_BUDGET,
+                );
+                let resumable::Step::Suspended(request) = parse.step() else {
+                    panic!("the tag was not suspended");
+                };
+                (parse, request)
+            })
+            .collect::<Vec<_>>();
+
+        let mut rounds = 0;
+        while !waiting.is_empty() {
+            assert_eq!(waiting.len(), TAGS);
+            rounds += 1;
+            let mut next = Vec::new();
+            for (mut parse, request) in waiting {
+                parse.respond(request.perform_blocking(&cx));
+                match parse.step() {
+                    resumable::Step::Suspended(request) => next.push((parse, request)),
+                    resumable::Step::Done(result) => assert_eq!(result.unwrap().output, "a".repeat(requests)),
+                }
+            }
+            waiting = next;
+        }
+        assert_eq!(rounds, requests);
+    }
+
+    /// Stands in for the I/O driver of an async runtime: runs every submitted job a fixed latency
+    /// after it was submitted, on a single thread
+    #[derive(Clone)]
+    struct Io {
+        jobs: std::sync::mpsc::Sender<(Instant, Box<dyn FnOnce() + Send>)>,
+    }
+
+    impl Io {
+        fn new(latency: std::time::Duration) -> Self {
+            let (jobs, rx) = std::sync::mpsc::channel::<(Instant, Box<dyn FnOnce() + Send>)>();
+            std::thread::spawn(move || {
+                for (submitted, job) in rx {
+                    std::thread::sleep((submitted + latency).saturating_duration_since(Instant::now()));
+                    job();
+                }
+            });
+            Self { jobs }
+        }
+
+        fn submit(&self, job: impl FnOnce() + Send + 'static) {
+            self.jobs.send((Instant::now(), Box::new(job))).unwrap();
+        }
+    }
+
+    /// Compares the throughput of tags that download several URLs with the blocking `Context`,
+    /// which holds a worker thread while it waits for a download, and with `ResumableParse`, which
+    /// hands the tag to the I/O driver instead. Both run on the same number of workers and wait for
+    /// the same [`Io`]. The blocking run takes at least TAGS * MAX_REQUESTS * LATENCY / WORKERS =
+    /// 800ms, while the resumable run only waits for MAX_REQUESTS rounds of I/O. The numbers depend
+    /// on the machine, so this only measures; run with `--ignored --nocapture` to see them
+    #[test]
+    #[ignore = "benchmark that depends on timing"]
+    fn download_throughput() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::{mpsc, Arc, Mutex};
+        use std::time::Duration;
+
+        const TAGS: usize = 64;
+        const WORKERS: usize = 4;
+        const LATENCY: Duration = Duration::from_millis(10);
+        let input = "{download:https://example.com}".repeat(parser::limits::MAX_REQUESTS as usize);
+        let io = Io::new(LATENCY);
+        let cx = || MockContext::default().download("https://example.com", "a");
+
+        let start = Instant::now();
+        let next = Arc::new(Mutex::new(0..TAGS));
+        let workers = (0..WORKERS)
+            .map(|_| {
+                let mut cx = cx();
+                cx.io = Some(io.clone());
+                let (next, input) = (next.clone(), input.clone());
+                std::thread::spawn(move || {
+                    while next.lock().unwrap().next().is_some() {
+                        parse(&input, &[], ParseMode::StopOnError, &cx as &dyn Context).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers.into_iter().for_each(|worker| worker.join().unwrap());
+        let blocking = start.elapsed();
+
+        let start = Instant::now();
+        let (job_tx, job_rx) = mpsc::channel::<resumable::ResumableParse>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let (done_tx, done_rx) = mpsc::channel();
+        let finished = Arc::new(AtomicBool::new(false));
+        for _ in 0..WORKERS {
+            let (job_tx, job_rx, done_tx, finished, io) = (
+                job_tx.clone(),
+                job_rx.clone(),
+                done_tx.clone(),
+                finished.clone(),
+                io.clone(),
+            );
+            std::thread::spawn(move || {
+                while !finished.load(Ordering::Relaxed) {
+                    let Ok(mut parse) = job_rx.lock().unwrap().recv_timeout(Duration::from_millis(5)) else {
+                        continue;
+                    };
+                    match parse.step() {
+                        resumable::Step::Done(result) => done_tx.send(result.map(|_| ())).unwrap(),
+                        resumable::Step::Suspended(request) => {
+                            let job_tx = job_tx.clone();
+                            io.submit(move || {
+                                parse.respond(request.perform_blocking(&cx()));
+                                job_tx.send(parse).unwrap();
+                            });
+                        },
+                    }
+                }
+            });
+        }
+        for _ in 0..TAGS {
+            job_tx
+                .send(resumable::ResumableParse::new(
+                    input.clone(),
+                    Vec::new(),
+                    ParseMode::StopOnError,
+                    parser::limits::MAX_TIME_BUDGET,
+                ))
+                .unwrap();
+        }
+        for _ in 0..TAGS {
+            done_rx.recv().unwrap().unwrap();
+        }
+        finished.store(true, Ordering::Relaxed);
+        let resumable = start.elapsed();
+
+        println!(
+            "{TAGS} tags with {} downloads of {LATENCY:?} each on {WORKERS} workers: blocking {:.0} tags/s, resumable {:.0} tags/s",
+            parser::limits::MAX_REQUESTS,
+            TAGS as f64 / blocking.as_secs_f64(),
+            TAGS as f64 / resumable.as_secs_f64()
+        );
+    }
+
     test!(ParseMode::IgnoreOnError;
         recover1: "{foo!:42" => Ok("{foo!:42"),
         recover2: "{foo!:42}" => Ok("{foo!:42}"),
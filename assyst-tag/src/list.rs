@@ -0,0 +1,29 @@
+//! Lists are not a separate type in the tag language, they are regular string values that hold a
+//! JSON array of strings, e.g. `["a","b c"]`. This means that they can be stored in variables,
+//! passed to subtags and printed like any other value.
+
+/// Parses a list value. Non-string elements are converted to their JSON representation, so
+/// `[1,true]` is the same as `["1","true"]`.
+///
+/// Returns `None` if the value is not a list.
+pub fn parse(value: &str) -> Option<Vec<String>> {
+    if !value.trim_start().starts_with('[') {
+        return None;
+    }
+
+    let items = serde_json::from_str::<Vec<serde_json::Value>>(value).ok()?;
+    Some(
+        items
+            .into_iter()
+            .map(|item| match item {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            })
+            .collect(),
+    )
+}
+
+/// Serializes a list into its string representation
+pub fn serialize(items: &[String]) -> String {
+    serde_json::to_string(items).expect("a list of strings is always serializable")
+}
@@ -0,0 +1,465 @@
+//! A small, bounded arithmetic expression evaluator used by the `{math}` subtag
+//!
+//! Supported syntax, from lowest to highest precedence:
+//! - comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=` (evaluate to 1 or 0)
+//! - `+`, `-`
+//! - `*`, `/`, `%`
+//! - unary `-` and `+`
+//! - `^` (right associative)
+//! - numbers, parenthesized expressions, constants (`pi`, `e`) and function calls such as `max(1,
+//!   2, 3)`
+//!
+//! Integer arithmetic is used as long as both operands are integers and the result fits, otherwise
+//! the evaluator falls back to floats.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::parser::limits::{MAX_MATH_DEPTH, MAX_MATH_EXPRESSION_LENGTH};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Int(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        self.as_f64() == 0.0
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Int(i) => i.fmt(f),
+            Number::Float(x) => x.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MathErrorKind {
+    ExpressionTooLong(usize),
+    TooDeeplyNested,
+    UnexpectedCharacter(char),
+    UnexpectedToken,
+    UnexpectedEnd,
+    UnclosedParenthesis,
+    InvalidNumber,
+    UnknownIdentifier(String),
+    WrongArgumentCount { name: String, expected: &'static str },
+    DivisionByZero,
+}
+
+impl fmt::Display for MathErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExpressionTooLong(len) => write!(f, "expression is too long ({len}>{MAX_MATH_EXPRESSION_LENGTH})"),
+            Self::TooDeeplyNested => write!(f, "expression is nested too deeply (max {MAX_MATH_DEPTH})"),
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`"),
+            Self::UnexpectedToken => f.write_str("unexpected token"),
+            Self::UnexpectedEnd => f.write_str("unexpected end of expression"),
+            Self::UnclosedParenthesis => f.write_str("this parenthesis is never closed"),
+            Self::InvalidNumber => f.write_str("invalid number"),
+            Self::UnknownIdentifier(name) => write!(f, "unknown function or constant `{name}`"),
+            Self::WrongArgumentCount { name, expected } => write!(f, "`{name}` expects {expected} argument(s)"),
+            Self::DivisionByZero => f.write_str("division by zero"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MathError {
+    pub kind: MathErrorKind,
+    /// Span of the offending token, relative to the expression
+    pub span: Range<usize>,
+}
+
+type MResult<T> = Result<T, MathError>;
+
+fn math_err<T>(kind: MathErrorKind, span: Range<usize>) -> MResult<T> {
+    Err(MathError { kind, span })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Number(Number),
+    Ident(&'a str),
+    Op(Op),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> MResult<Vec<(Token<'_>, Range<usize>)>> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let start = idx;
+        let b = bytes[idx];
+
+        let token = match b {
+            b if b.is_ascii_whitespace() => {
+                idx += 1;
+                continue;
+            },
+            b'0'..=b'9' | b'.' => {
+                while idx < bytes.len() && (bytes[idx].is_ascii_digit() || bytes[idx] == b'.') {
+                    idx += 1;
+                }
+                let text = &input[start..idx];
+                let number = if text.contains('.') {
+                    text.parse().map(Number::Float).ok()
+                } else {
+                    // integers that don't fit in an i64 are still valid floats
+                    text.parse()
+                        .map(Number::Int)
+                        .or_else(|_| text.parse().map(Number::Float))
+                        .ok()
+                };
+                match number {
+                    Some(number) => Token::Number(number),
+                    None => return math_err(MathErrorKind::InvalidNumber, start..idx),
+                }
+            },
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                while idx < bytes.len() && (bytes[idx].is_ascii_alphanumeric() || bytes[idx] == b'_') {
+                    idx += 1;
+                }
+                Token::Ident(&input[start..idx])
+            },
+            _ => {
+                let next = bytes.get(idx + 1).copied();
+                let (token, len) = match (b, next) {
+                    (b'=', Some(b'=')) => (Token::Op(Op::Eq), 2),
+                    (b'!', Some(b'=')) => (Token::Op(Op::Ne), 2),
+                    (b'<', Some(b'=')) => (Token::Op(Op::Le), 2),
+                    (b'>', Some(b'=')) => (Token::Op(Op::Ge), 2),
+                    (b'*', Some(b'*')) => (Token::Op(Op::Pow), 2),
+                    (b'<', _) => (Token::Op(Op::Lt), 1),
+                    (b'>', _) => (Token::Op(Op::Gt), 1),
+                    (b'+', _) => (Token::Op(Op::Add), 1),
+                    (b'-', _) => (Token::Op(Op::Sub), 1),
+                    (b'*', _) => (Token::Op(Op::Mul), 1),
+                    (b'/', _) => (Token::Op(Op::Div), 1),
+                    (b'%', _) => (Token::Op(Op::Rem), 1),
+                    (b'^', _) => (Token::Op(Op::Pow), 1),
+                    (b'(', _) => (Token::LParen, 1),
+                    (b')', _) => (Token::RParen, 1),
+                    (b',', _) => (Token::Comma, 1),
+                    _ => {
+                        let c = input[start..].chars().next().expect("idx is in bounds");
+                        return math_err(MathErrorKind::UnexpectedCharacter(c), start..start + c.len_utf8());
+                    },
+                };
+                idx += len;
+                token
+            },
+        };
+
+        tokens.push((token, start..idx));
+    }
+
+    Ok(tokens)
+}
+
+struct MathParser<'a> {
+    tokens: Vec<(Token<'a>, Range<usize>)>,
+    idx: usize,
+    depth: u32,
+    /// Length of the input, used for errors pointing at the end of the expression
+    len: usize,
+}
+
+impl<'a> MathParser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.idx).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> MResult<(Token<'a>, Range<usize>)> {
+        match self.tokens.get(self.idx) {
+            Some(token) => {
+                self.idx += 1;
+                Ok(token.clone())
+            },
+            None => math_err(MathErrorKind::UnexpectedEnd, self.len..self.len + 1),
+        }
+    }
+
+    fn eat(&mut self, token: &Token<'_>) -> bool {
+        if self.peek() == Some(token) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_op(&mut self, ops: &[Op]) -> Option<(Op, Range<usize>)> {
+        match self.tokens.get(self.idx) {
+            Some((Token::Op(op), span)) if ops.contains(op) => {
+                self.idx += 1;
+                Some((*op, span.clone()))
+            },
+            _ => None,
+        }
+    }
+
+    /// Guards against stack overflows from deeply nested input
+    fn enter(&mut self, span: Range<usize>) -> MResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_MATH_DEPTH {
+            return math_err(MathErrorKind::TooDeeplyNested, span);
+        }
+        Ok(())
+    }
+
+    fn parse_expr(&mut self) -> MResult<Number> {
+        let mut lhs = self.parse_additive()?;
+        while let Some((op, _)) = self.next_op(&[Op::Eq, Op::Ne, Op::Lt, Op::Le, Op::Gt, Op::Ge]) {
+            let rhs = self.parse_additive()?;
+            lhs = compare(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_additive(&mut self) -> MResult<Number> {
+        let mut lhs = self.parse_multiplicative()?;
+        while let Some((op, span)) = self.next_op(&[Op::Add, Op::Sub]) {
+            let rhs = self.parse_multiplicative()?;
+            lhs = apply(op, lhs, rhs, span)?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_multiplicative(&mut self) -> MResult<Number> {
+        let mut lhs = self.parse_unary()?;
+        while let Some((op, span)) = self.next_op(&[Op::Mul, Op::Div, Op::Rem]) {
+            let rhs = self.parse_unary()?;
+            lhs = apply(op, lhs, rhs, span)?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> MResult<Number> {
+        if let Some((op, span)) = self.next_op(&[Op::Add, Op::Sub]) {
+            self.enter(span.clone())?;
+            let value = self.parse_unary()?;
+            self.depth -= 1;
+
+            return match (op, value) {
+                // i64::MIN is written as the negation of 2^63, which does not fit in an i64 by itself
+                (Op::Sub, Number::Float(f)) if f == -(i64::MIN as f64) => Ok(Number::Int(i64::MIN)),
+                (Op::Sub, _) => apply(Op::Sub, Number::Int(0), value, span),
+                _ => Ok(value),
+            };
+        }
+
+        self.parse_power()
+    }
+
+    fn parse_power(&mut self) -> MResult<Number> {
+        let base = self.parse_primary()?;
+        if let Some((op, span)) = self.next_op(&[Op::Pow]) {
+            self.enter(span.clone())?;
+            // right associative, and allows a sign in the exponent (2^-1)
+            let exponent = self.parse_unary()?;
+            self.depth -= 1;
+            return apply(op, base, exponent, span);
+        }
+        Ok(base)
+    }
+
+    fn parse_primary(&mut self) -> MResult<Number> {
+        let (token, span) = self.next()?;
+        match token {
+            Token::Number(number) => Ok(number),
+            Token::LParen => {
+                self.enter(span.clone())?;
+                let value = self.parse_expr()?;
+                if !self.eat(&Token::RParen) {
+                    return math_err(MathErrorKind::UnclosedParenthesis, span);
+                }
+                self.depth -= 1;
+                Ok(value)
+            },
+            Token::Ident(name) if self.eat(&Token::LParen) => {
+                self.enter(span.clone())?;
+                let mut args = Vec::new();
+                if !self.eat(&Token::RParen) {
+                    loop {
+                        args.push(self.parse_expr()?);
+                        if self.eat(&Token::Comma) {
+                            continue;
+                        }
+                        if self.eat(&Token::RParen) {
+                            break;
+                        }
+                        return math_err(MathErrorKind::UnclosedParenthesis, span);
+                    }
+                }
+                self.depth -= 1;
+                call(name, &args, span)
+            },
+            Token::Ident("pi") => Ok(Number::Float(std::f64::consts::PI)),
+            Token::Ident("e") => Ok(Number::Float(std::f64::consts::E)),
+            Token::Ident(name) => math_err(MathErrorKind::UnknownIdentifier(name.to_owned()), span),
+            _ => math_err(MathErrorKind::UnexpectedToken, span),
+        }
+    }
+}
+
+fn compare(op: Op, lhs: Number, rhs: Number) -> Number {
+    let ordering = match (lhs, rhs) {
+        (Number::Int(a), Number::Int(b)) => Some(a.cmp(&b)),
+        (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+    };
+
+    let result = match ordering {
+        Some(ordering) => match op {
+            Op::Eq => ordering.is_eq(),
+            Op::Ne => ordering.is_ne(),
+            Op::Lt => ordering.is_lt(),
+            Op::Le => ordering.is_le(),
+            Op::Gt => ordering.is_gt(),
+            Op::Ge => ordering.is_ge(),
+            _ => unreachable!("not a comparison operator"),
+        },
+        // comparisons involving NaN are always false, except for !=
+        None => op == Op::Ne,
+    };
+
+    Number::Int(result as i64)
+}
+
+fn apply(op: Op, lhs: Number, rhs: Number, span: Range<usize>) -> MResult<Number> {
+    if matches!(op, Op::Div | Op::Rem) && rhs.is_zero() {
+        return math_err(MathErrorKind::DivisionByZero, span);
+    }
+
+    if let (Number::Int(a), Number::Int(b)) = (lhs, rhs) {
+        let result = match op {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mul => a.checked_mul(b),
+            // only stay an integer if the division is exact
+            Op::Div if a.checked_rem(b) == Some(0) => a.checked_div(b),
+            Op::Div => None,
+            // only i64::MIN % -1 overflows, and its remainder is 0
+            Op::Rem => Some(a.checked_rem(b).unwrap_or(0)),
+            Op::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            _ => unreachable!("not an arithmetic operator"),
+        };
+
+        if let Some(result) = result {
+            return Ok(Number::Int(result));
+        }
+    }
+
+    let (a, b) = (lhs.as_f64(), rhs.as_f64());
+    Ok(Number::Float(match op {
+        Op::Add => a + b,
+        Op::Sub => a - b,
+        Op::Mul => a * b,
+        Op::Div => a / b,
+        Op::Rem => a % b,
+        Op::Pow => a.powf(b),
+        _ => unreachable!("not an arithmetic operator"),
+    }))
+}
+
+fn call(name: &str, args: &[Number], span: Range<usize>) -> MResult<Number> {
+    let unary = |f: fn(f64) -> f64| match args {
+        [x] => Ok(Number::Float(f(x.as_f64()))),
+        _ => math_err(
+            MathErrorKind::WrongArgumentCount {
+                name: name.to_owned(),
+                expected: "1",
+            },
+            span.clone(),
+        ),
+    };
+
+    match name {
+        "abs" => match args {
+            [Number::Int(i)] if let Some(abs) = i.checked_abs() => Ok(Number::Int(abs)),
+            _ => unary(f64::abs),
+        },
+        "sqrt" => unary(f64::sqrt),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "max" | "min" => {
+            let Some((&first, rest)) = args.split_first() else {
+                return math_err(
+                    MathErrorKind::WrongArgumentCount {
+                        name: name.to_owned(),
+                        expected: "at least 1",
+                    },
+                    span,
+                );
+            };
+
+            let pick = if name == "max" { Op::Gt } else { Op::Lt };
+            Ok(rest.iter().fold(first, |best, &next| {
+                if compare(pick, next, best) == Number::Int(1) {
+                    next
+                } else {
+                    best
+                }
+            }))
+        },
+        _ => math_err(MathErrorKind::UnknownIdentifier(name.to_owned()), span),
+    }
+}
+
+/// Evaluates an arithmetic expression
+pub fn evaluate(input: &str) -> MResult<Number> {
+    if input.len() > MAX_MATH_EXPRESSION_LENGTH {
+        return math_err(MathErrorKind::ExpressionTooLong(input.len()), 0..input.len());
+    }
+
+    let mut parser = MathParser {
+        tokens: tokenize(input)?,
+        idx: 0,
+        depth: 0,
+        len: input.len(),
+    };
+
+    let value = parser.parse_expr()?;
+
+    if let Some((_, span)) = parser.tokens.get(parser.idx) {
+        return math_err(MathErrorKind::UnexpectedToken, span.clone());
+    }
+
+    Ok(value)
+}
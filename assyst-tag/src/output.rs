@@ -0,0 +1,40 @@
+//! Everything a tag can respond with besides its text output: an embed and files.
+
+/// Discord's limits for the various embed components, in characters
+pub mod limits {
+    pub const MAX_TITLE_LENGTH: usize = 256;
+    pub const MAX_DESCRIPTION_LENGTH: usize = 4096;
+    pub const MAX_FIELDS: usize = 25;
+    pub const MAX_FIELD_NAME_LENGTH: usize = 256;
+    pub const MAX_FIELD_VALUE_LENGTH: usize = 1024;
+    pub const MAX_FOOTER_LENGTH: usize = 2048;
+    /// Maximum number of files in a single message
+    pub const MAX_ATTACHMENTS: usize = 10;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+/// An embed built with the `{embed...}` subtags. All parts are optional, the caller is responsible
+/// for not sending embeds without any content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Embed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub colour: Option<u32>,
+    /// URL of the image
+    pub image: Option<String>,
+    pub fields: Vec<EmbedField>,
+    pub footer: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// File name, including the extension
+    pub name: String,
+    pub data: Vec<u8>,
+}
@@ -2,17 +2,20 @@ use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::error::Error as StdError;
 use std::ops::Range;
+use std::time::Instant;
 
-use assyst_common::util::filetype::Type;
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
 
 use crate::context::Context;
 use crate::errors::{err_res, BytePos, ErrorKind, TResult};
-use crate::subtags;
+use crate::output::{self, Attachment, Embed};
+use crate::registry::{self, Handler};
+use crate::trace::{Trace, TraceToken};
 
 /// Constants and helper functions for tag parser limits
 pub mod limits {
     use std::cell::Cell;
+    use std::time::Duration;
 
     pub const MAX_REQUESTS: u32 = 5;
     pub const MAX_VARIABLES: usize = 100;
@@ -21,6 +24,29 @@ pub mod limits {
     pub const MAX_ITERATIONS: u32 = 500;
     pub const MAX_DEPTH: u32 = 15;
     pub const MAX_STRING_LENGTH: usize = 256_000;
+    /// Maximum number of persistent storage reads/writes in a single tag run
+    pub const MAX_STORAGE_OPERATIONS: u32 = 25;
+    /// Maximum number of guild, channel and member lookups in a single tag run. These are cached,
+    /// so they have their own limit instead of counting towards [`MAX_REQUESTS`]
+    pub const MAX_CONTEXT_LOOKUPS: u32 = 25;
+    /// Maximum number of responses a [`crate::resumable::ResumableParse`] may wait for. The tag is
+    /// parsed again after every response, so this bounds how often it is parsed
+    pub const MAX_SUSPENSIONS: u32 = 64;
+    /// Maximum number of persistent variables in a single scope (one tag, or the guild)
+    pub const MAX_PERSISTENT_VARIABLES: usize = 50;
+    pub const MAX_PERSISTENT_VALUE_LENGTH: usize = 2_000;
+    pub const MAX_FUNCTIONS: usize = 50;
+    pub const MAX_REGEX_PATTERN_LENGTH: usize = 1_000;
+    /// Maximum size of a compiled regex program (and its lazy DFA cache), in bytes
+    pub const MAX_REGEX_SIZE: usize = 1 << 20;
+    pub const MAX_REGEX_NEST: u32 = 50;
+    pub const MAX_MATH_EXPRESSION_LENGTH: usize = 2_000;
+    /// Maximum nesting of parentheses, function calls and unary operators in a `{math}` expression
+    pub const MAX_MATH_DEPTH: u32 = 64;
+    /// Time a tag may spend parsing, unless the guild configured a different budget
+    pub const DEFAULT_TIME_BUDGET: Duration = Duration::from_secs(5);
+    pub const MIN_TIME_BUDGET: Duration = Duration::from_millis(500);
+    pub const MAX_TIME_BUDGET: Duration = Duration::from_secs(15);
 
     pub fn try_increment(field_cell: &Cell<u32>, limit: u32) -> bool {
         let field = field_cell.get();
@@ -42,20 +68,60 @@ pub struct SharedState<'a> {
     variables: &'a RefCell<HashMap<String, String>>,
     /// Counter for various limits
     counter: &'a Counter,
-    /// The attachment to be responded with, if set
-    attachment: &'a RefCell<Option<(Vec<u8>, Type)>>,
+    /// The files to be responded with
+    attachments: &'a RefCell<Vec<Attachment>>,
+    /// The embed to be responded with, if any of the embed subtags were used
+    embed: &'a RefCell<Option<Embed>>,
+    /// User defined functions
+    functions: &'a RefCell<Functions>,
+    /// Random number generator, shared so that a seeded tag produces the same output on every run
+    rng: &'a RefCell<StdRng>,
+    /// Execution trace, if tracing is enabled
+    trace: Option<&'a RefCell<Trace>>,
 }
 
 impl<'a> SharedState<'a> {
     pub fn new(
         variables: &'a RefCell<HashMap<String, String>>,
         counter: &'a Counter,
-        attachment: &'a RefCell<Option<(Vec<u8>, Type)>>,
+        attachments: &'a RefCell<Vec<Attachment>>,
+        embed: &'a RefCell<Option<Embed>>,
+        functions: &'a RefCell<Functions>,
+        rng: &'a RefCell<StdRng>,
     ) -> Self {
         Self {
             variables,
             counter,
-            attachment,
+            attachments,
+            embed,
+            functions,
+            rng,
+            trace: None,
+        }
+    }
+
+    /// Enables tracing, recording every subtag invocation into `trace`
+    pub fn with_trace(mut self, trace: &'a RefCell<Trace>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Records the start of a subtag invocation, if tracing is enabled
+    fn trace_start(&self, name: &str, start: usize) -> Option<TraceToken> {
+        self.trace.map(|trace| trace.borrow_mut().start(name, start))
+    }
+
+    /// Records the evaluated arguments of a subtag invocation, if tracing is enabled
+    fn trace_args(&self, token: &Option<TraceToken>, args: &[String]) {
+        if let (Some(trace), Some(token)) = (self.trace, token) {
+            trace.borrow_mut().args(token, args);
+        }
+    }
+
+    /// Records the end of a subtag invocation, if tracing is enabled
+    fn trace_end(&self, token: Option<TraceToken>, input: &[u8], span: Range<usize>, result: &TResult<String>) {
+        if let (Some(trace), Some(token)) = (self.trace, token) {
+            trace.borrow_mut().end(token, input, span, result);
         }
     }
 
@@ -77,17 +143,81 @@ impl<'a> SharedState<'a> {
         f(&variables)
     }
 
+    /// Calls `f` with a mutable reference to the random number generator
+    pub fn with_rng<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut StdRng) -> T,
+    {
+        let mut rng = self.rng.borrow_mut();
+        f(&mut rng)
+    }
+
     /// Returns a reference to the counter
     pub fn counter(&self) -> &Counter {
         self.counter
     }
 
-    /// Sets the attachment to be responded with
-    pub fn set_attachment(&self, buf: Vec<u8>, ty: Type) {
-        *self.attachment.borrow_mut() = Some((buf, ty));
+    /// Adds a file to be responded with. Returns false if the attachment limit has been reached
+    pub fn add_attachment(&self, attachment: Attachment) -> bool {
+        let mut attachments = self.attachments.borrow_mut();
+        if attachments.len() >= output::limits::MAX_ATTACHMENTS {
+            return false;
+        }
+        attachments.push(attachment);
+        true
+    }
+
+    /// Calls `f` with a mutable reference to the embed to be responded with, creating an empty one
+    /// if none exists yet
+    pub fn with_embed_mut<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut Embed) -> T,
+    {
+        let mut embed = self.embed.borrow_mut();
+        f(embed.get_or_insert_with(Embed::default))
+    }
+
+    /// Defines (or redefines) a function. Returns false if the function limit has been reached
+    pub fn define_function(&self, name: String, body: String) -> bool {
+        let mut functions = self.functions.borrow_mut();
+        if !functions.definitions.contains_key(&name) && functions.definitions.len() >= limits::MAX_FUNCTIONS {
+            return false;
+        }
+        functions.definitions.insert(name, body);
+        true
+    }
+
+    /// Returns the unparsed body of a function
+    pub fn function(&self, name: &str) -> Option<String> {
+        self.functions.borrow().definitions.get(name).cloned()
+    }
+
+    /// Marks a function as being executed. Returns false if it is already executing, i.e. the call
+    /// would recurse
+    pub fn enter_function(&self, name: &str) -> bool {
+        let mut functions = self.functions.borrow_mut();
+        if functions.call_stack.iter().any(|active| active == name) {
+            return false;
+        }
+        functions.call_stack.push(name.to_owned());
+        true
+    }
+
+    /// Marks the most recently entered function as done
+    pub fn exit_function(&self) {
+        self.functions.borrow_mut().call_stack.pop();
     }
 }
 
+/// User defined functions, see `{func}` and `{call}`
+#[derive(Default)]
+pub struct Functions {
+    /// Function name to unparsed body
+    definitions: HashMap<String, String>,
+    /// Functions that are currently executing
+    call_stack: Vec<String>,
+}
+
 /// Counter for various limits
 #[derive(Default)]
 pub struct Counter {
@@ -95,9 +225,28 @@ pub struct Counter {
     requests: Cell<u32>,
     /// Number of parser iterations
     iterations: Cell<u32>,
+    /// Number of persistent storage operations
+    storage_operations: Cell<u32>,
+    /// Number of guild, channel and member lookups
+    context_lookups: Cell<u32>,
+    /// Point in time after which the tag is aborted, if any
+    deadline: Option<Instant>,
 }
 
 impl Counter {
+    /// Creates a counter that times out at `deadline`
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..Self::default()
+        }
+    }
+
+    /// Checks whether the deadline has passed
+    pub fn timed_out(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
     /// Tries to increment the requests field if it's not already at the limit
     pub fn try_request(&self) -> bool {
         limits::try_increment(&self.requests, limits::MAX_REQUESTS)
@@ -107,6 +256,16 @@ impl Counter {
     pub fn try_iterate(&self) -> bool {
         limits::try_increment(&self.iterations, limits::MAX_ITERATIONS)
     }
+
+    /// Tries to increment the storage operations field if it's not already at the limit
+    pub fn try_storage_operation(&self) -> bool {
+        limits::try_increment(&self.storage_operations, limits::MAX_STORAGE_OPERATIONS)
+    }
+
+    /// Tries to increment the context lookups field if it's not already at the limit
+    pub fn try_context_lookup(&self) -> bool {
+        limits::try_increment(&self.context_lookups, limits::MAX_CONTEXT_LOOKUPS)
+    }
 }
 
 #[derive(Default, Copy, Clone, Debug)]
@@ -132,8 +291,6 @@ pub struct Parser<'a> {
     idx: usize,
     /// Shared parser state across multiple parsers
     state: SharedState<'a>,
-    /// A cached `ThreadRng`, used to generate random numbers
-    rng: ThreadRng,
     /// Context for this parser
     cx: &'a dyn Context,
     /// The current depth of this subparser. This exists to avoid stack overflow in {eval} calls
@@ -145,7 +302,7 @@ pub struct Parser<'a> {
 }
 
 /// Checks if a given byte is in the a..z A..Z range
-fn is_identifier(b: u8) -> bool {
+pub(crate) fn is_identifier(b: u8) -> bool {
     b.is_ascii_alphabetic() || b == b'_'
 }
 
@@ -168,7 +325,6 @@ impl<'a> Parser<'a> {
             args,
             idx: 0,
             state: other.state.clone(),
-            rng: rand::thread_rng(),
             cx: other.cx,
             subparser_depth: other.subparser_depth + 1,
             tag_start_positions: Vec::new(),
@@ -190,7 +346,6 @@ impl<'a> Parser<'a> {
             mode,
             idx: 0,
             state,
-            rng: rand::thread_rng(),
             subparser_depth: 0,
             tag_start_positions: Vec::new(),
         }
@@ -285,6 +440,10 @@ impl<'a> Parser<'a> {
 
             match byte {
                 b'{' => {
+                    // subtags can take a while (e.g. regexes or requests), so also check the deadline
+                    // between them and not just when entering a segment
+                    self.check_interrupted()?;
+
                     *self.tag_start_positions.last_mut().unwrap() = self.idx;
                     // skip {
                     self.idx += 1;
@@ -336,9 +495,12 @@ impl<'a> Parser<'a> {
                         }
                     }
 
+                    let trace = self.state.trace_start(name, self.last_tag_start_pos());
+
                     // lazy tags need to be evaluated before the args are parsed
                     // see comment in `handle_lazy_tag` for what it means for a tag to be lazy
                     if let Some(re) = self.handle_lazy_tag(name) {
+                        self.state.trace_end(trace, self.input, self.span(), &re);
                         output.append(&mut re?.into_bytes());
                         continue;
                     }
@@ -371,7 +533,11 @@ impl<'a> Parser<'a> {
                     }
 
                     let result = if side_effects {
-                        match (self.handle_tag(name, name_span, args), self.mode) {
+                        self.state.trace_args(&trace, &args);
+                        let result = self.handle_tag(name, name_span, args);
+                        self.state.trace_end(trace, self.input, self.span(), &result);
+
+                        match (result, self.mode) {
                             (Ok(res), _) => res,
                             (Err(err), ParseMode::IgnoreOnError) if let ErrorKind::UnknownSubtag { .. } = *err.kind => {
                                 // we allow recovering only from unknown subtags specifically
@@ -450,6 +616,18 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    /// Stops the tag if it ran out of time, or if its context suspended it (see
+    /// [`Context::is_suspended`])
+    fn check_interrupted(&self) -> TResult<()> {
+        if self.state.counter.timed_out() {
+            return err_res(ErrorKind::Timeout { pos: self.idx });
+        }
+        if self.cx.is_suspended() {
+            return err_res(ErrorKind::Suspended { pos: self.idx });
+        }
+        Ok(())
+    }
+
     /// Parses a single "segment" of the input
     ///
     /// A segment can be a single argument of a tag, or the entire input itself.
@@ -463,6 +641,7 @@ impl<'a> Parser<'a> {
         if !self.state.counter.try_iterate() {
             return err_res(ErrorKind::IterLimit { pos: self.idx });
         }
+        self.check_interrupted()?;
         self.tag_start_positions.push(self.idx);
         #[allow(deprecated)]
         let res = self.parse_segment_inner_untracked(side_effects);
@@ -476,52 +655,16 @@ impl<'a> Parser<'a> {
     /// beforehand. This is needed for special subtags like if, which needs to decide whether to
     /// parse `then` or else` only after it compared two arguments
     pub fn handle_lazy_tag(&mut self, name: &str) -> Option<TResult<String>> {
-        match name {
-            "if" => Some(subtags::r#if(self)),
-            "note" => Some(subtags::note(self)),
-            "ignore" => Some(subtags::ignore(self)),
-            _ => None,
+        match registry::get(name)?.handler {
+            Handler::Lazy(f) => Some(f(self)),
+            Handler::Eager(_) => None,
         }
     }
 
     /// Handles a regular tag
     pub fn handle_tag(&mut self, name: &str, name_span: Range<usize>, args: Vec<String>) -> TResult<String> {
-        match name {
-            "repeat" => subtags::exec(self, &args, subtags::repeat),
-            "range" => subtags::exec(self, &args, subtags::range),
-            "eval" => subtags::exec(self, &args, subtags::eval),
-            "tryarg" => subtags::exec(self, &args, subtags::tryarg),
-            "arg" => subtags::exec(self, &args, subtags::arg),
-            "args" => subtags::exec(self, &args, subtags::args),
-            "set" => subtags::exec(self, &args, subtags::set),
-            "get" => subtags::exec(self, &args, subtags::get),
-            "delete" => subtags::exec(self, &args, subtags::delete),
-            "argslen" => subtags::exec(self, &args, subtags::argslen),
-            "abs" => subtags::exec(self, &args, subtags::abs),
-            "cos" => subtags::exec(self, &args, subtags::cos),
-            "sin" => subtags::exec(self, &args, subtags::sin),
-            "tan" => subtags::exec(self, &args, subtags::tan),
-            "sqrt" => subtags::exec(self, &args, subtags::sqrt),
-            "e" => subtags::exec(self, &args, subtags::e),
-            "pi" => subtags::exec(self, &args, subtags::pi),
-            "max" => subtags::exec(self, &args, subtags::max),
-            "min" => subtags::exec(self, &args, subtags::min),
-            "choose" => subtags::exec(self, &args, subtags::choose),
-            "length" => subtags::exec(self, &args, subtags::length),
-            "lower" => subtags::exec(self, &args, subtags::lower),
-            "upper" => subtags::exec(self, &args, subtags::upper),
-            "replace" => subtags::exec(self, &args, subtags::replace),
-            "reverse" => subtags::exec(self, &args, subtags::reverse),
-            "channelid" => subtags::exec(self, &args, subtags::channelid),
-            "usertag" => subtags::exec(self, &args, subtags::usertag),
-            "js" | "javascript" => subtags::exec(self, &args, subtags::javascript),
-            "lastattachment" => subtags::exec(self, &args, subtags::attachment_last),
-            "avatar" => subtags::exec(self, &args, subtags::avatar),
-            "download" => subtags::exec(self, &args, subtags::download),
-            "mention" => subtags::exec(self, &args, subtags::mention),
-            "idof" => subtags::exec(self, &args, subtags::idof),
-            "userid" => subtags::exec(self, &args, subtags::userid),
-            "tag" => subtags::exec(self, &args, subtags::tag),
+        match registry::get(name).map(|subtag| subtag.handler) {
+            Some(Handler::Eager(f)) => f(self, &args),
             _ => err_res(ErrorKind::UnknownSubtag {
                 name: name.to_owned(),
                 span: name_span,
@@ -533,10 +676,6 @@ impl<'a> Parser<'a> {
         self.args
     }
 
-    pub fn rng(&mut self) -> &mut ThreadRng {
-        &mut self.rng
-    }
-
     pub fn state(&self) -> &SharedState<'a> {
         &self.state
     }
@@ -0,0 +1,193 @@
+//! Metadata of every subtag, which drives dispatch in the [`Parser`] and documents the subtags for
+//! users.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::errors::TResult;
+use crate::parser::Parser;
+use crate::subtags::{self, Arity, ParseTagArgument};
+
+#[derive(Clone, Copy)]
+pub(crate) enum Handler {
+    /// The arguments are parsed before the subtag is invoked
+    Eager(fn(&mut Parser<'_>, &[String]) -> TResult<String>),
+    /// The subtag parses its own arguments, see [`Parser::handle_lazy_tag`]
+    Lazy(fn(&mut Parser<'_>) -> TResult<String>),
+}
+
+pub struct SubtagInfo {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// How the subtag is invoked, e.g. `{repeat:count|text}`. Optional arguments are in angle
+    /// brackets and repeated arguments end with `...`
+    pub signature: &'static str,
+    pub description: &'static str,
+    pub min_args: usize,
+    /// `None` if the subtag accepts any number of arguments
+    pub max_args: Option<usize>,
+    pub(crate) handler: Handler,
+}
+
+macro_rules! subtag {
+    (@info [$name:literal $(, $alias:literal)*], $signature:literal, $description:literal, $arity:expr, $handler:expr) => {{
+        let (min_args, max_args) = $arity;
+        SubtagInfo {
+            name: $name,
+            aliases: &[$($alias),*],
+            signature: $signature,
+            description: $description,
+            min_args,
+            max_args,
+            handler: $handler,
+        }
+    }};
+    // the arity of eager subtags is that of the arguments of their handler
+    ($name:literal $(| $alias:literal)*, $signature:literal, $description:literal, eager!($f:path)) => {
+        subtag!(
+            @info [$name $(, $alias)*], $signature, $description,
+            arity($f),
+            Handler::Eager(|parser, args| subtags::exec(parser, args, $f))
+        )
+    };
+    // lazy subtags parse their own arguments, so their arity has to be given
+    ($name:literal $(| $alias:literal)*, $signature:literal, $description:literal, ($min:expr, $max:expr), lazy!($f:path)) => {
+        subtag!(@info [$name $(, $alias)*], $signature, $description, ($min, $max), Handler::Lazy($f))
+    };
+}
+
+/// The arity of an eager subtag, see [`ParseTagArgument::ARITY`]
+const fn arity<A: ParseTagArgument>(_: fn(&mut Parser<'_>, A) -> TResult<String>) -> Arity {
+    A::ARITY
+}
+
+#[rustfmt::skip]
+pub static SUBTAGS: &[SubtagInfo] = &[
+    // control flow
+    subtag!("if", "{if:value|comparator|condition|then|else}", "evaluates `then` if the comparison holds and `else` otherwise. comparators are `=`, `~` (case insensitive), `>`, `>=`, `<` and `<=`", (5, Some(5)), lazy!(subtags::r#if)),
+    subtag!("note", "{note:<text>}", "a comment, its text is never evaluated", (0, Some(1)), lazy!(subtags::note)),
+    subtag!("ignore", "{ignore:<text>}", "outputs its text without evaluating the subtags inside of it", (0, Some(1)), lazy!(subtags::ignore)),
+    subtag!("foreach", "{foreach:variable|list|body}", "evaluates `body` once per item of `list`, a list value or whitespace separated words", (3, Some(3)), lazy!(subtags::foreach)),
+    subtag!("for", "{for:variable|start|end|body}", "evaluates `body` once per number from `start` to `end` (inclusive)", (4, Some(4)), lazy!(subtags::r#for)),
+    subtag!("while", "{while:value|comparator|condition|body}", "evaluates `body` for as long as the comparison (see `{if}`) holds", (4, Some(4)), lazy!(subtags::r#while)),
+    subtag!("func", "{func:name|body}", "defines a function that can be executed with `{call}`", (2, Some(2)), lazy!(subtags::func)),
+    subtag!("call", "{call:name|<args...>}", "executes a function defined with `{func}`, with its own `{arg}`s", eager!(subtags::call)),
+    subtag!("eval", "{eval:text}", "evaluates the subtags in `text`", eager!(subtags::eval)),
+    subtag!("tag", "{tag:name|<args...>}", "runs another tag of this server with the given arguments", eager!(subtags::tag)),
+    // arguments
+    subtag!("arg", "{arg:index}", "the argument at `index`, starting at 0. fails if there is none", eager!(subtags::arg)),
+    subtag!("tryarg", "{tryarg:index}", "the argument at `index`, starting at 0, or nothing if there is none", eager!(subtags::tryarg)),
+    subtag!("args", "{args}", "all arguments, separated by spaces", eager!(subtags::args)),
+    subtag!("argslen", "{argslen}", "the number of arguments", eager!(subtags::argslen)),
+    // variables
+    subtag!("set", "{set:name|value}", "stores a variable for the rest of this run", eager!(subtags::set)),
+    subtag!("get", "{get:name}", "the value of a variable, or nothing if it is not set", eager!(subtags::get)),
+    subtag!("delete", "{delete:name}", "deletes a variable", eager!(subtags::delete)),
+    subtag!("pset", "{pset:name|value}", "stores a value that persists between runs of this tag", eager!(subtags::pset)),
+    subtag!("pget", "{pget:name}", "a value stored with `{pset}`", eager!(subtags::pget)),
+    subtag!("pdelete", "{pdelete:name}", "deletes a value stored with `{pset}`", eager!(subtags::pdelete)),
+    subtag!("gset", "{gset:name|value}", "stores a value that is shared by every tag of this server", eager!(subtags::gset)),
+    subtag!("gget", "{gget:name}", "a value stored with `{gset}`", eager!(subtags::gget)),
+    subtag!("gdelete", "{gdelete:name}", "deletes a value stored with `{gset}`", eager!(subtags::gdelete)),
+    // math
+    subtag!("math", "{math:expression}", "evaluates a math expression, like `2 * (3 + 4)`", eager!(subtags::math)),
+    subtag!("range", "{range:lower|upper}", "a random number from `lower` to `upper` (inclusive)", eager!(subtags::range)),
+    subtag!("seed", "{seed:number}", "seeds the random number generator, so that the random subtags after it give the same results on every run", eager!(subtags::seed)),
+    subtag!("abs", "{abs:number}", "the absolute value of a number", eager!(subtags::abs)),
+    subtag!("cos", "{cos:number}", "the cosine of a number", eager!(subtags::cos)),
+    subtag!("sin", "{sin:number}", "the sine of a number", eager!(subtags::sin)),
+    subtag!("tan", "{tan:number}", "the tangent of a number", eager!(subtags::tan)),
+    subtag!("sqrt", "{sqrt:number}", "the square root of a number", eager!(subtags::sqrt)),
+    subtag!("e", "{e}", "euler's number", eager!(subtags::e)),
+    subtag!("pi", "{pi}", "the number pi", eager!(subtags::pi)),
+    subtag!("max", "{max:numbers...}", "the largest of the given numbers", eager!(subtags::max)),
+    subtag!("min", "{min:numbers...}", "the smallest of the given numbers", eager!(subtags::min)),
+    // text
+    subtag!("choose", "{choose:items...}", "a random item, or a random element if a single list is given", eager!(subtags::choose)),
+    subtag!("repeat", "{repeat:count|text}", "repeats `text` `count` times", eager!(subtags::repeat)),
+    subtag!("length", "{length:text}", "the number of characters in `text`", eager!(subtags::length)),
+    subtag!("lower", "{lower:text}", "`text` in lowercase", eager!(subtags::lower)),
+    subtag!("upper", "{upper:text}", "`text` in uppercase", eager!(subtags::upper)),
+    subtag!("replace", "{replace:what|with|text}", "replaces every occurrence of `what` in `text`", eager!(subtags::replace)),
+    subtag!("reverse", "{reverse:text}", "`text` reversed", eager!(subtags::reverse)),
+    subtag!("substring", "{substring:start|<end>|text}", "the characters of `text` from `start` up to `end`, negative indices count from the end", eager!(subtags::substring)),
+    subtag!("indexof", "{indexof:needle|text}", "the index of the first occurrence of `needle` in `text`, or -1", eager!(subtags::indexof)),
+    subtag!("split", "{split:separator|text}", "splits `text` by `separator` into a list", eager!(subtags::split)),
+    subtag!("join", "{join:separator|items...}", "joins the items, or the elements of a single list, with `separator`", eager!(subtags::join)),
+    subtag!("trim", "{trim:text}", "`text` without leading and trailing whitespace", eager!(subtags::trim)),
+    subtag!("padstart", "{padstart:width|fill|text}", "pads the start of `text` with `fill` up to `width` characters", eager!(subtags::padstart)),
+    subtag!("padend", "{padend:width|fill|text}", "pads the end of `text` with `fill` up to `width` characters", eager!(subtags::padend)),
+    subtag!("urlencode", "{urlencode:text}", "percent-encodes `text` for use in a URL", eager!(subtags::urlencode)),
+    subtag!("urldecode", "{urldecode:text}", "decodes percent-encoded `text`", eager!(subtags::urldecode)),
+    subtag!("match", "{match:pattern|text}", "the first match of a regex in `text`, or nothing", eager!(subtags::r#match)),
+    subtag!("regexreplace", "{regexreplace:pattern|replacement|text}", "replaces every match of a regex, `$1` refers to the first capture group", eager!(subtags::regexreplace)),
+    subtag!("iequals", "{iequals:a|b}", "1 if `a` and `b` are equal ignoring case, 0 otherwise", eager!(subtags::iequals)),
+    // lists
+    subtag!("list", "{list:<items...>}", "creates a list of the given items", eager!(subtags::list)),
+    subtag!("index", "{index:list|index}", "the element of a list at `index`, starting at 0", eager!(subtags::index)),
+    subtag!("push", "{push:variable|items...}", "appends items to the list stored in a variable", eager!(subtags::push)),
+    subtag!("pop", "{pop:variable}", "removes and returns the last element of the list stored in a variable", eager!(subtags::pop)),
+    subtag!("slice", "{slice:list|start|<end>}", "the elements of a list from `start` up to `end`", eager!(subtags::slice)),
+    subtag!("sort", "{sort:list}", "sorts a list, numerically if every element is a number", eager!(subtags::sort)),
+    subtag!("shuffle", "{shuffle:list}", "shuffles a list", eager!(subtags::shuffle)),
+    subtag!("listlen", "{listlen:list}", "the number of elements in a list", eager!(subtags::listlen)),
+    // output
+    subtag!("js" | "javascript", "{js:code}", "evaluates JavaScript code", eager!(subtags::javascript)),
+    subtag!("attach", "{attach:name|content}", "attaches a text file to the output", eager!(subtags::attach)),
+    subtag!("image", "{image:operation|url|<options...>}", "runs an image command on an image and attaches the result", eager!(subtags::image)),
+    subtag!("download", "{download:url}", "the content of a web page", eager!(subtags::download)),
+    subtag!("embedtitle", "{embedtitle:title}", "sets the title of the output embed", eager!(subtags::embedtitle)),
+    subtag!("embeddescription", "{embeddescription:text}", "sets the description of the output embed", eager!(subtags::embeddescription)),
+    subtag!("embedcolour" | "embedcolor", "{embedcolour:hex}", "sets the colour of the output embed, like `#ff0000`", eager!(subtags::embedcolour)),
+    subtag!("embedimage", "{embedimage:url}", "sets the image of the output embed", eager!(subtags::embedimage)),
+    subtag!("embedfield", "{embedfield:name|value|<inline>}", "adds a field to the output embed, which is inline if the third argument is `inline`", eager!(subtags::embedfield)),
+    subtag!("embedfooter", "{embedfooter:text}", "sets the footer of the output embed", eager!(subtags::embedfooter)),
+    // discord
+    subtag!("lastattachment", "{lastattachment}", "the URL of the most recent attachment in this channel", eager!(subtags::attachment_last)),
+    subtag!("avatar", "{avatar:<user id>}", "the avatar URL of a user, or of the invoker", eager!(subtags::avatar)),
+    subtag!("mention", "{mention:<user id>}", "mentions a user, or the invoker", eager!(subtags::mention)),
+    subtag!("usertag", "{usertag:<user id>}", "the username of a user, or of the invoker", eager!(subtags::usertag)),
+    subtag!("idof", "{idof:mention}", "the ID in a mention", eager!(subtags::idof)),
+    subtag!("userid", "{userid}", "the ID of the invoker", eager!(subtags::userid)),
+    subtag!("nickname", "{nickname}", "the nickname of the invoker in this server", eager!(subtags::nickname)),
+    subtag!("roles", "{roles}", "a list of the role names of the invoker", eager!(subtags::roles)),
+    subtag!("joindate", "{joindate}", "when the invoker joined this server", eager!(subtags::joindate)),
+    subtag!("channelid", "{channelid}", "the ID of this channel", eager!(subtags::channelid)),
+    subtag!("channelname", "{channelname}", "the name of this channel", eager!(subtags::channelname)),
+    subtag!("channeltopic", "{channeltopic}", "the topic of this channel", eager!(subtags::channeltopic)),
+    subtag!("servername", "{servername}", "the name of this server", eager!(subtags::servername)),
+    subtag!("membercount", "{membercount}", "the number of members in this server", eager!(subtags::membercount)),
+    subtag!("messageid", "{messageid}", "the ID of the message that ran the tag", eager!(subtags::messageid)),
+    subtag!("replycontent", "{replycontent}", "the content of the message that the invoking message replies to", eager!(subtags::replycontent)),
+];
+
+static SUBTAGS_BY_NAME: LazyLock<HashMap<&'static str, &'static SubtagInfo>> = LazyLock::new(|| {
+    SUBTAGS
+        .iter()
+        .flat_map(|subtag| {
+            std::iter::once(subtag.name)
+                .chain(subtag.aliases.iter().copied())
+                .map(move |name| (name, subtag))
+        })
+        .collect()
+});
+
+/// Looks up a subtag by its name or one of its aliases
+pub fn get(name: &str) -> Option<&'static SubtagInfo> {
+    SUBTAGS_BY_NAME.get(name).copied()
+}
+
+/// Subtags whose name or one of whose aliases contains `query`, ignoring case. Subtags with a name
+/// or alias starting with `query` come first.
+pub fn search(query: &str) -> Vec<&'static SubtagInfo> {
+    let query = query.trim().trim_start_matches('{').to_ascii_lowercase();
+    let names = |subtag: &'static SubtagInfo| std::iter::once(subtag.name).chain(subtag.aliases.iter().copied());
+
+    let mut matches = SUBTAGS
+        .iter()
+        .filter(|subtag| names(subtag).any(|name| name.contains(&query)))
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|subtag| !names(subtag).any(|name| name.starts_with(&query)));
+
+    matches
+}
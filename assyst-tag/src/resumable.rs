@@ -0,0 +1,463 @@
+//! Running tags without blocking on I/O.
+//!
+//! The parser itself is synchronous, and calls its [`Context`] whenever a subtag needs something
+//! from the outside. [`ResumableParse`] lets the caller make those calls asynchronously instead:
+//! it runs the tag against the responses collected so far and suspends it at the first call that
+//! has no response yet. Once the caller has responded, the tag is run again from the start.
+//!
+//! These replays produce the same calls in the same order, as the random number generator is seeded
+//! with the same seed on every run and every call gets the response it got before. The output of
+//! the final run is therefore the same as if the calls were made synchronously. Each response is
+//! stored with its request, and a replay that makes a different call than before fails instead of
+//! getting the response to another call.
+//!
+//! A replay stops at the call it is suspended at (see [`Context::is_suspended`]), so it only parses
+//! the tag up to there. Still, a tag that waits for N responses is parsed N + 1 times, so the total
+//! work is quadratic in N. [`limits::MAX_SUSPENSIONS`] bounds N: a tag whose calls are as cheap as
+//! possible runs at most 64 * 65 / 2 + 64 = 2144 subtags instead of 64, which the tests check.
+//! Parsing the tag only once would need a parser that can suspend in the middle of a subtag.
+//!
+//! Only the time spent parsing, including replays, counts towards the time budget of the tag. The
+//! time spent waiting for responses does not, so callers should bound it themselves (e.g. with a
+//! deadline, finishing the tag with [`ResumableParse::timeout`] once it passes). As
+//! [`ResumableParse::step`] parses synchronously, async callers should run it on a thread that may
+//! block, such as the blocking thread pool of their runtime.
+
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use assyst_common::eval::FakeEvalImageResponse;
+
+use crate::context::{with_requests, Context, StorageScope};
+use crate::errors::{self, BytePos, ErrorKind, TResult};
+use crate::parser::{limits, ParseMode};
+use crate::trace::Trace;
+use crate::{parse_inner, ParseResult};
+
+/// The type that [`Request`] stores an argument as
+macro_rules! owned_type {
+    ($ty:ty) => {
+        $ty
+    };
+    ($ty:ty, $owned:ty) => {
+        $owned
+    };
+}
+
+/// Converts an argument into the type that [`Request`] stores it as
+macro_rules! to_owned {
+    ($arg:ident) => {
+        $arg
+    };
+    ($arg:ident, $owned:ty) => {
+        <$owned>::from($arg)
+    };
+}
+
+/// Borrows an argument stored in a [`Request`] for the method it came from
+macro_rules! to_borrowed {
+    ($arg:ident) => {
+        $arg
+    };
+    ($arg:ident, $owned:ty) => {
+        &$arg
+    };
+}
+
+macro_rules! is_constant {
+    (constant) => {
+        true
+    };
+    (varying) => {
+        false
+    };
+}
+
+macro_rules! define_requests {
+    ($(
+        $variant:ident($kind:ident) fn $name:ident($($arg:ident: $ty:ty $(as $owned:ty)?),*) -> $ret:ty;
+    )*) => {
+        /// A call of a tag to its context, see the methods of [`Context`]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum Request {
+            $(
+                #[doc = concat!("See [`Context::", stringify!($name), "`]")]
+                $variant { $($arg: owned_type!($ty $(, $owned)?)),* },
+            )*
+        }
+
+        impl Request {
+            /// Whether the response to this request does not change during a run of a tag, so that it
+            /// can be reused for equal requests instead of suspending the tag again
+            fn is_constant(&self) -> bool {
+                match self {
+                    $(Request::$variant { .. } => is_constant!($kind),)*
+                }
+            }
+
+            /// Makes the call to `cx`
+            pub async fn perform<C: AsyncContext>(&self, cx: &C) -> anyhow::Result<Response> {
+                match self.clone() {
+                    $(
+                        Request::$variant { $($arg),* } => {
+                            Ok(cx.$name($(to_borrowed!($arg $(, $owned)?)),*).await?.into_response())
+                        },
+                    )*
+                }
+            }
+
+            /// Makes the call to a synchronous `cx`
+            #[cfg(test)]
+            pub(crate) fn perform_blocking(&self, cx: &dyn Context) -> anyhow::Result<Response> {
+                match self.clone() {
+                    $(
+                        Request::$variant { $($arg),* } => {
+                            Ok(cx.$name($(to_borrowed!($arg $(, $owned)?)),*)?.into_response())
+                        },
+                    )*
+                }
+            }
+        }
+
+        impl Context for ReplayContext<'_> {
+            $(
+                fn $name(&self, $($arg: $ty),*) -> anyhow::Result<$ret> {
+                    ResponseValue::from_response(self.call(Request::$variant {
+                        $($arg: to_owned!($arg $(, $owned)?)),*
+                    })?)
+                }
+            )*
+
+            fn time_budget(&self) -> Duration {
+                self.budget
+            }
+
+            fn is_suspended(&self) -> bool {
+                self.pending.borrow().is_some() || self.diverged.get()
+            }
+        }
+    };
+}
+
+/// The asynchronous counterpart of [`Context`], see the module documentation and the methods of
+/// [`Context`]
+pub trait AsyncContext {
+    fn execute_javascript(
+        &self,
+        code: &str,
+        args: Vec<String>,
+    ) -> impl Future<Output = anyhow::Result<FakeEvalImageResponse>> + Send;
+    fn get_last_attachment(&self) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn get_avatar(&self, user_id: Option<u64>) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn download(&self, url: &str) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn channel_id(&self) -> impl Future<Output = anyhow::Result<u64>> + Send;
+    fn guild_id(&self) -> impl Future<Output = anyhow::Result<u64>> + Send;
+    fn user_id(&self) -> impl Future<Output = anyhow::Result<u64>> + Send;
+    fn user_tag(&self, id: Option<u64>) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn get_tag_contents(&self, tag: &str) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn get_persistent(
+        &self,
+        scope: StorageScope,
+        key: &str,
+    ) -> impl Future<Output = anyhow::Result<Option<String>>> + Send;
+    fn set_persistent(
+        &self,
+        scope: StorageScope,
+        key: &str,
+        value: &str,
+    ) -> impl Future<Output = anyhow::Result<bool>> + Send;
+    fn delete_persistent(&self, scope: StorageScope, key: &str) -> impl Future<Output = anyhow::Result<()>> + Send;
+    fn run_flux(
+        &self,
+        operation: &str,
+        args: Vec<String>,
+        input_url: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<u8>>> + Send;
+    fn guild_name(&self) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn guild_member_count(&self) -> impl Future<Output = anyhow::Result<u64>> + Send;
+    fn channel_name(&self) -> impl Future<Output = anyhow::Result<String>> + Send;
+    fn channel_topic(&self) -> impl Future<Output = anyhow::Result<Option<String>>> + Send;
+    fn nickname(&self) -> impl Future<Output = anyhow::Result<Option<String>>> + Send;
+    fn role_names(&self) -> impl Future<Output = anyhow::Result<Vec<String>>> + Send;
+    fn joined_at(&self) -> impl Future<Output = anyhow::Result<i64>> + Send;
+    fn replied_message_content(&self) -> impl Future<Output = anyhow::Result<Option<String>>> + Send;
+    fn message_id(&self) -> impl Future<Output = anyhow::Result<u64>> + Send;
+}
+
+with_requests!(define_requests);
+
+/// The response to a [`Request`]
+#[derive(Clone)]
+pub enum Response {
+    Javascript(FakeEvalImageResponse),
+    Text(String),
+    OptionalText(Option<String>),
+    Number(u64),
+    Timestamp(i64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    List(Vec<String>),
+    Unit,
+}
+
+/// A value that a method of [`Context`] returns, and the variant of [`Response`] that holds it
+trait ResponseValue: Sized {
+    fn into_response(self) -> Response;
+
+    fn from_response(response: Response) -> anyhow::Result<Self>;
+}
+
+macro_rules! response_values {
+    ($($variant:ident($ty:ty)),*) => {
+        $(
+            impl ResponseValue for $ty {
+                fn into_response(self) -> Response {
+                    Response::$variant(self)
+                }
+
+                fn from_response(response: Response) -> anyhow::Result<Self> {
+                    match response {
+                        Response::$variant(value) => Ok(value),
+                        _ => mismatched_response(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+response_values!(
+    Javascript(FakeEvalImageResponse),
+    Text(String),
+    OptionalText(Option<String>),
+    Number(u64),
+    Timestamp(i64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    List(Vec<String>)
+);
+
+impl ResponseValue for () {
+    fn into_response(self) -> Response {
+        Response::Unit
+    }
+
+    fn from_response(response: Response) -> anyhow::Result<Self> {
+        match response {
+            Response::Unit => Ok(()),
+            _ => mismatched_response(),
+        }
+    }
+}
+
+fn mismatched_response<T>() -> anyhow::Result<T> {
+    Err(anyhow!("The response does not match the request"))
+}
+
+/// Errors are stored as their message, which is all the parser keeps of them (see
+/// [`crate::errors::wrap_anyhow`])
+type StoredResponse = Result<Response, String>;
+
+/// The responses to the requests of previous runs
+#[derive(Default)]
+struct ResponseLog {
+    /// Requests for which [`Request::is_constant`] does not hold, in the order they were made
+    ordered: Vec<(Request, StoredResponse)>,
+    /// Requests for which [`Request::is_constant`] holds
+    constant: Vec<(Request, StoredResponse)>,
+}
+
+impl ResponseLog {
+    /// The number of responses, which is how often the tag was suspended
+    fn len(&self) -> usize {
+        self.ordered.len() + self.constant.len()
+    }
+}
+
+/// Answers the calls of the parser from a [`ResponseLog`], and suspends the tag at the first call
+/// without a response
+struct ReplayContext<'a> {
+    log: &'a ResponseLog,
+    /// Index of the next response in `log.ordered`
+    cursor: Cell<usize>,
+    /// The request the tag was suspended at
+    pending: RefCell<Option<Request>>,
+    /// Whether the tag made a different request than it did in a previous run
+    diverged: Cell<bool>,
+    /// What is left of the time budget of the tag
+    budget: Duration,
+}
+
+impl ReplayContext<'_> {
+    fn call(&self, request: Request) -> anyhow::Result<Response> {
+        // the parser stops at the next subtag, but subtags that ignore errors of the context can
+        // still make calls until then
+        if self.is_suspended() {
+            return Err(anyhow!("The tag is suspended"));
+        }
+
+        let response = if request.is_constant() {
+            self.log.constant.iter().find(|(constant, _)| *constant == request)
+        } else {
+            let response = self.log.ordered.get(self.cursor.get());
+            if response.is_some() {
+                self.cursor.set(self.cursor.get() + 1);
+            }
+            response
+        };
+
+        match response {
+            Some((recorded, response)) if *recorded == request => response.clone().map_err(anyhow::Error::msg),
+            Some(_) => {
+                self.diverged.set(true);
+                Err(anyhow!("The tag is suspended"))
+            },
+            None if self.log.len() >= limits::MAX_SUSPENSIONS as usize => {
+                Err(anyhow!("The tag waited for too many responses"))
+            },
+            None => {
+                *self.pending.borrow_mut() = Some(request);
+                Err(anyhow!("The tag is suspended"))
+            },
+        }
+    }
+}
+
+/// The outcome of [`ResumableParse::step`]
+pub enum Step {
+    /// The tag finished
+    Done(TResult<ParseResult>),
+    /// The tag needs a response to this request before it can continue, see
+    /// [`ResumableParse::respond`]
+    Suspended(Request),
+}
+
+/// A tag that can be suspended while it waits for I/O, see the module documentation
+pub struct ResumableParse {
+    input: String,
+    args: Vec<String>,
+    mode: ParseMode,
+    seed: u64,
+    /// What is left of the time budget of the tag, see the module documentation
+    budget: Duration,
+    log: ResponseLog,
+    /// The request of the last step, if the tag was suspended
+    pending: Option<Request>,
+    /// Position at which the tag was last suspended
+    suspended_at: BytePos,
+    /// The trace of the last step, if tracing is enabled
+    trace: Option<Trace>,
+}
+
+impl ResumableParse {
+    /// Prepares a tag to be run. Only the time spent in [`ResumableParse::step`] counts towards
+    /// `time_budget`.
+    pub fn new(input: String, args: Vec<String>, mode: ParseMode, time_budget: Duration) -> Self {
+        Self {
+            input,
+            args,
+            mode,
+            seed: rand::random(),
+            budget: time_budget,
+            log: ResponseLog::default(),
+            pending: None,
+            suspended_at: 0,
+            trace: None,
+        }
+    }
+
+    /// Seeds the random number generator with `seed` instead of a random seed, like
+    /// [`crate::parse_with_seed`]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Records every subtag invocation, like [`crate::parse_traced`]. The trace is available
+    /// through [`ResumableParse::into_trace`].
+    pub fn traced(mut self) -> Self {
+        self.trace = Some(Trace::default());
+        self
+    }
+
+    /// Runs the tag until it finishes or makes a call without a response. This does not block on
+    /// I/O, but parsing can take up to what is left of the time budget of the tag.
+    pub fn step(&mut self) -> Step {
+        let start = Instant::now();
+        let cx = ReplayContext {
+            log: &self.log,
+            cursor: Cell::new(0),
+            pending: RefCell::new(None),
+            diverged: Cell::new(false),
+            budget: self.budget,
+        };
+        let args = self.args.iter().map(String::as_str).collect::<Vec<_>>();
+        let trace = self.trace.is_some().then(|| RefCell::new(Trace::default()));
+
+        let result = parse_inner(&self.input, &args, self.mode, &cx, trace.as_ref(), Some(self.seed));
+
+        self.budget = self.budget.saturating_sub(start.elapsed());
+        self.trace = trace.map(RefCell::into_inner);
+        if cx.diverged.get() {
+            return Step::Done(Err(errors::wrap_anyhow(
+                0..0,
+                anyhow!("The tag made different requests when it was resumed"),
+            )));
+        }
+        match cx.pending.into_inner() {
+            Some(request) => {
+                if let Err(err) = &result {
+                    if let ErrorKind::Suspended { pos } = *err.kind {
+                        self.suspended_at = pos;
+                    }
+                }
+                self.pending = Some(request.clone());
+                Step::Suspended(request)
+            },
+            None => Step::Done(result),
+        }
+    }
+
+    /// Provides the response to the request the tag was suspended at.
+    ///
+    /// # Panics
+    /// If the tag is not suspended.
+    pub fn respond(&mut self, response: anyhow::Result<Response>) {
+        let request = self.pending.take().expect("The tag is not suspended");
+        let response = response.map_err(|err| err.to_string());
+
+        if request.is_constant() {
+            self.log.constant.push((request, response));
+        } else {
+            self.log.ordered.push((request, response));
+        }
+    }
+
+    /// Makes the call the tag was suspended at to `cx`, and provides the response.
+    ///
+    /// # Panics
+    /// If the tag is not suspended.
+    pub async fn resume<C: AsyncContext>(&mut self, cx: &C) {
+        let request = self.pending.as_ref().expect("The tag is not suspended");
+        let response = request.perform(cx).await;
+        self.respond(response);
+    }
+
+    /// The error to finish the tag with if the caller stops waiting for the response to the request
+    /// it is suspended at, pointing at where the tag was suspended
+    pub fn timeout(&self) -> errors::Error {
+        errors::err(ErrorKind::Timeout { pos: self.suspended_at })
+    }
+
+    /// The trace of the last step, if tracing is enabled
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    /// The trace of the last step, or an empty trace if tracing is not enabled
+    pub fn into_trace(self) -> Trace {
+        self.trace.unwrap_or_default()
+    }
+}
@@ -2,12 +2,20 @@ use std::num::{ParseFloatError, ParseIntError};
 
 use assyst_common::eval::FakeEvalImageResponse;
 use assyst_common::util::discord::id_from_mention;
+use assyst_common::util::filetype::{get_sig, Type};
 use either::Either;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex::{Regex, RegexBuilder};
 
+use crate::context::StorageScope;
 use crate::errors::{err, err_res, wrap_anyhow, ErrorKind, TResult};
+use crate::list;
+use crate::output::{self, Attachment, EmbedField};
 use crate::parser::limits::{
-    MAX_DEPTH, MAX_STRING_LENGTH, MAX_VARIABLES, MAX_VARIABLE_KEY_LENGTH, MAX_VARIABLE_VALUE_LENGTH,
+    MAX_DEPTH, MAX_PERSISTENT_VALUE_LENGTH, MAX_REGEX_NEST, MAX_REGEX_PATTERN_LENGTH, MAX_REGEX_SIZE,
+    MAX_STRING_LENGTH, MAX_VARIABLES, MAX_VARIABLE_KEY_LENGTH, MAX_VARIABLE_VALUE_LENGTH,
 };
 use crate::parser::Parser;
 
@@ -24,6 +32,33 @@ macro_rules! ensure_request_limit {
     }};
 }
 
+/// Ensures that the persistent storage operation limit has not been hit yet
+///
+/// This should be called in tags that read from or write to persistent storage.
+/// Returns with an error if the limit is reached
+macro_rules! ensure_storage_limit {
+    ($parser:expr) => {{
+        let parser = &$parser;
+        if !parser.state().counter().try_storage_operation() {
+            return err_res(ErrorKind::StorageLimit { span: parser.span() });
+        }
+    }};
+}
+
+/// Ensures that the guild, channel and member lookup limit has not been hit yet
+///
+/// This should be called in tags that look up information about the guild, channel or member
+/// through the context. Information that comes with the invoking message, like its ID, is free.
+/// Returns with an error if the limit is reached
+macro_rules! ensure_context_lookup_limit {
+    ($parser:expr) => {{
+        let parser = &$parser;
+        if !parser.state().counter().try_context_lookup() {
+            return err_res(ErrorKind::ContextLookupLimit { span: parser.span() });
+        }
+    }};
+}
+
 fn try_eat_closing_brace(parser: &mut Parser<'_>) -> TResult<()> {
     if !parser.eat(b"}") {
         err_res(ErrorKind::MissingClosingBrace {
@@ -59,11 +94,27 @@ pub struct Rest<T: ParseTagArgument>(Vec<T>);
 /// Same as `Rest`, but requires at least N elements to be present.
 pub struct Atleast<const N: usize, T: ParseTagArgument>(Rest<T>);
 
+/// The least and the most number of arguments that a subtag or argument takes, the most is `None`
+/// if there is no upper bound
+pub type Arity = (usize, Option<usize>);
+
+const fn sum_max(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        _ => None,
+    }
+}
+
 pub trait ParseTagArgument: Sized {
+    /// How many arguments this consumes, which the static checker uses as the arity of subtags
+    const ARITY: Arity;
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError>;
 }
 
 impl<T: ParseTagArgument> ParseTagArgument for Rest<T> {
+    const ARITY: Arity = (0, None);
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let mut results = Vec::new();
         let mut args_consumed = 0;
@@ -80,6 +131,8 @@ impl<T: ParseTagArgument> ParseTagArgument for Rest<T> {
 }
 
 impl<const N: usize, T: ParseTagArgument> ParseTagArgument for Atleast<N, T> {
+    const ARITY: Arity = (N * T::ARITY.0, None);
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let rest = Rest::parse_from_args(parser, args)?;
         if rest.value.0.len() < N {
@@ -93,6 +146,8 @@ impl<const N: usize, T: ParseTagArgument> ParseTagArgument for Atleast<N, T> {
 }
 
 impl ParseTagArgument for () {
+    const ARITY: Arity = (0, Some(0));
+
     fn parse_from_args(_: &Parser<'_>, _: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         Ok(ParseSuccess {
             value: (),
@@ -102,6 +157,19 @@ impl ParseTagArgument for () {
 }
 
 impl<A: ParseTagArgument, B: ParseTagArgument> ParseTagArgument for Either<A, B> {
+    const ARITY: Arity = (
+        if A::ARITY.0 < B::ARITY.0 {
+            A::ARITY.0
+        } else {
+            B::ARITY.0
+        },
+        match (A::ARITY.1, B::ARITY.1) {
+            (Some(a), Some(b)) if a > b => Some(a),
+            (Some(_), Some(b)) => Some(b),
+            _ => None,
+        },
+    );
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         if let Ok(s) = A::parse_from_args(parser, args) {
             Ok(ParseSuccess {
@@ -120,6 +188,8 @@ impl<A: ParseTagArgument, B: ParseTagArgument> ParseTagArgument for Either<A, B>
 }
 
 impl<A: ParseTagArgument> ParseTagArgument for Option<A> {
+    const ARITY: Arity = (0, A::ARITY.1);
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         if let Ok(s) = A::parse_from_args(parser, args) {
             Ok(ParseSuccess {
@@ -137,6 +207,8 @@ impl<A: ParseTagArgument> ParseTagArgument for Option<A> {
 }
 
 impl ParseTagArgument for usize {
+    const ARITY: Arity = (1, Some(1));
+
     fn parse_from_args(_: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let [arg, ..] = args else {
             return Err(ParseError::MissingArgument);
@@ -153,6 +225,8 @@ impl ParseTagArgument for usize {
 pub struct Mention(u64);
 
 impl ParseTagArgument for Mention {
+    const ARITY: Arity = (1, Some(1));
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let [mention, ..] = args else {
             return Err(ParseError::MissingArgument);
@@ -168,7 +242,28 @@ impl ParseTagArgument for Mention {
     }
 }
 
+/// A list value, see [`crate::list`]
+pub struct List(Vec<String>);
+
+impl ParseTagArgument for List {
+    const ARITY: Arity = (1, Some(1));
+
+    fn parse_from_args(_: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
+        let [arg, ..] = args else {
+            return Err(ParseError::MissingArgument);
+        };
+        let items = list::parse(arg).ok_or_else(|| ParseError::Other(format!("'{arg}' is not a list")))?;
+
+        Ok(ParseSuccess {
+            value: List(items),
+            args_consumed: 1,
+        })
+    }
+}
+
 impl ParseTagArgument for u64 {
+    const ARITY: Arity = (1, Some(1));
+
     fn parse_from_args(_: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let [arg, ..] = args else {
             return Err(ParseError::MissingArgument);
@@ -183,6 +278,8 @@ impl ParseTagArgument for u64 {
 }
 
 impl ParseTagArgument for f64 {
+    const ARITY: Arity = (1, Some(1));
+
     fn parse_from_args(_: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let [arg, ..] = args else {
             return Err(ParseError::MissingArgument);
@@ -197,6 +294,8 @@ impl ParseTagArgument for f64 {
 }
 
 impl ParseTagArgument for i64 {
+    const ARITY: Arity = (1, Some(1));
+
     fn parse_from_args(_: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let [arg, ..] = args else {
             return Err(ParseError::MissingArgument);
@@ -211,6 +310,8 @@ impl ParseTagArgument for i64 {
 }
 
 impl ParseTagArgument for String {
+    const ARITY: Arity = (1, Some(1));
+
     fn parse_from_args(_: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let [arg, ..] = args else {
             return Err(ParseError::MissingArgument);
@@ -227,6 +328,8 @@ where
     A: ParseTagArgument,
     B: ParseTagArgument,
 {
+    const ARITY: Arity = (A::ARITY.0 + B::ARITY.0, sum_max(A::ARITY.1, B::ARITY.1));
+
     fn parse_from_args(parser: &Parser<'_>, args: &[String]) -> Result<ParseSuccess<Self>, ParseError> {
         let ParseSuccess {
             value: a,
@@ -268,23 +371,36 @@ pub fn exec<A: ParseTagArgument>(
     Subtag::exec(&f, p, args)
 }
 
-pub fn repeat(parser: &mut Parser<'_>, (count, input): (usize, String)) -> TResult<String> {
-    if input.len() + count > MAX_STRING_LENGTH {
+/// Ensures that a string of `size` bytes can be created without exceeding [`MAX_STRING_LENGTH`]
+fn ensure_string_length(parser: &Parser<'_>, size: usize) -> TResult<()> {
+    if size > MAX_STRING_LENGTH {
         return err_res(ErrorKind::StringLengthLimit {
             span: parser.span(),
-            attempted_size: input.len() + count,
+            attempted_size: size,
         });
     }
+    Ok(())
+}
+
+pub fn repeat(parser: &mut Parser<'_>, (count, input): (usize, String)) -> TResult<String> {
+    ensure_string_length(parser, input.len().saturating_mul(count))?;
 
     Ok(input.repeat(count))
 }
 
 pub fn range(parser: &mut Parser<'_>, (lower, upper): (usize, usize)) -> TResult<String> {
-    let out: usize = parser.rng().gen_range(lower..=upper);
+    let out: usize = parser.state().with_rng(|rng| rng.gen_range(lower..=upper));
 
     Ok(out.to_string())
 }
 
+/// `{seed:number}`: reseeds the random number generator, so that the random subtags after it give
+/// the same results on every run
+pub fn seed(parser: &mut Parser<'_>, seed: u64) -> TResult<String> {
+    parser.state().with_rng(|rng| *rng = StdRng::seed_from_u64(seed));
+    Ok(String::new())
+}
+
 pub fn eval(parser: &mut Parser<'_>, text: String) -> TResult<String> {
     if parser.depth() >= MAX_DEPTH {
         return err_res(ErrorKind::DepthLimit { span: parser.span() });
@@ -310,6 +426,26 @@ pub fn args(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
     Ok(parser.args().join(" "))
 }
 
+/// Stores a variable, overwriting any previous value. Unlike `{set}`, overwriting an existing
+/// variable is allowed even when the variable limit has been reached.
+fn store_variable(parser: &Parser<'_>, key: &str, value: String) -> TResult<()> {
+    parser.state().with_variables_mut(|vars| {
+        if !vars.contains_key(key) && vars.len() >= MAX_VARIABLES {
+            return err_res(ErrorKind::VarLimit { span: parser.span() });
+        }
+
+        if value.len() > MAX_VARIABLE_VALUE_LENGTH {
+            return err_res(ErrorKind::VarValueLengthLimit {
+                span: parser.span(),
+                length: value.len(),
+            });
+        }
+
+        vars.insert(key.to_owned(), value);
+        Ok(())
+    })
+}
+
 pub fn set(parser: &mut Parser<'_>, (key, value): (String, String)) -> TResult<String> {
     parser.state().with_variables_mut(|vars| -> TResult<String> {
         if vars.len() >= MAX_VARIABLES {
@@ -349,6 +485,80 @@ pub fn delete(parser: &mut Parser<'_>, key: String) -> TResult<String> {
     })
 }
 
+fn persistent_set(parser: &mut Parser<'_>, scope: StorageScope, key: String, value: String) -> TResult<String> {
+    if key.len() > MAX_VARIABLE_KEY_LENGTH {
+        return err_res(ErrorKind::VarKeyLengthLimit {
+            span: parser.span(),
+            length: key.len(),
+        });
+    }
+
+    if value.len() > MAX_PERSISTENT_VALUE_LENGTH {
+        return err_res(ErrorKind::PersistentVarValueLengthLimit {
+            span: parser.span(),
+            length: value.len(),
+        });
+    }
+
+    ensure_storage_limit!(parser);
+
+    let stored = parser
+        .context()
+        .set_persistent(scope, &key, &value)
+        .map_err(|err| wrap_anyhow(parser.span(), err))?;
+
+    if !stored {
+        return err_res(ErrorKind::PersistentVarLimit { span: parser.span() });
+    }
+
+    Ok(String::new())
+}
+
+fn persistent_get(parser: &mut Parser<'_>, scope: StorageScope, key: String) -> TResult<String> {
+    ensure_storage_limit!(parser);
+
+    Ok(parser
+        .context()
+        .get_persistent(scope, &key)
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .unwrap_or_default())
+}
+
+fn persistent_delete(parser: &mut Parser<'_>, scope: StorageScope, key: String) -> TResult<String> {
+    ensure_storage_limit!(parser);
+
+    parser
+        .context()
+        .delete_persistent(scope, &key)
+        .map_err(|err| wrap_anyhow(parser.span(), err))?;
+
+    Ok(String::new())
+}
+
+pub fn pset(parser: &mut Parser<'_>, (key, value): (String, String)) -> TResult<String> {
+    persistent_set(parser, StorageScope::Tag, key, value)
+}
+
+pub fn pget(parser: &mut Parser<'_>, key: String) -> TResult<String> {
+    persistent_get(parser, StorageScope::Tag, key)
+}
+
+pub fn pdelete(parser: &mut Parser<'_>, key: String) -> TResult<String> {
+    persistent_delete(parser, StorageScope::Tag, key)
+}
+
+pub fn gset(parser: &mut Parser<'_>, (key, value): (String, String)) -> TResult<String> {
+    persistent_set(parser, StorageScope::Guild, key, value)
+}
+
+pub fn gget(parser: &mut Parser<'_>, key: String) -> TResult<String> {
+    persistent_get(parser, StorageScope::Guild, key)
+}
+
+pub fn gdelete(parser: &mut Parser<'_>, key: String) -> TResult<String> {
+    persistent_delete(parser, StorageScope::Guild, key)
+}
+
 pub fn argslen(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
     Ok(parser.args().len().to_string())
 }
@@ -389,8 +599,32 @@ pub fn min(_: &mut Parser<'_>, (initial, Rest(args)): (i64, Rest<i64>)) -> TResu
     Ok(args.iter().fold(initial, |p, &c| p.min(c)).to_string())
 }
 
-pub fn choose(parser: &mut Parser<'_>, Atleast(Rest(args)): Atleast<1, String>) -> TResult<String> {
-    let idx = parser.rng().gen_range(0..args.len());
+pub fn math(_: &mut Parser<'_>, expr: String) -> TResult<String> {
+    crate::math::evaluate(&expr)
+        .map(|value| value.to_string())
+        .map_err(|math_err| {
+            err(ErrorKind::Nested {
+                error: err(ErrorKind::MathError {
+                    err: math_err.kind,
+                    span: math_err.span,
+                }),
+                source: expr,
+            })
+        })
+}
+
+/// `{choose:items...}`, or `{choose:list}` to pick an element of a list
+pub fn choose(parser: &mut Parser<'_>, Atleast(Rest(mut args)): Atleast<1, String>) -> TResult<String> {
+    if let [single] = args.as_slice() {
+        if let Some(list) = list::parse(single) {
+            if list.is_empty() {
+                return Ok(String::new());
+            }
+            args = list;
+        }
+    }
+
+    let idx = parser.state().with_rng(|rng| rng.gen_range(0..args.len()));
     Ok(args.get(idx).cloned().expect("0..len should always be inbounds"))
 }
 
@@ -408,7 +642,18 @@ pub fn upper(_: &mut Parser<'_>, mut arg: String) -> TResult<String> {
     Ok(arg)
 }
 
-pub fn replace(_: &mut Parser<'_>, (what, (with, text)): (String, (String, String))) -> TResult<String> {
+pub fn replace(parser: &mut Parser<'_>, (what, (with, text)): (String, (String, String))) -> TResult<String> {
+    // an empty pattern matches between every character
+    let matches = if what.is_empty() {
+        text.chars().count() + 1
+    } else {
+        text.matches(&what).count()
+    };
+    ensure_string_length(
+        parser,
+        (text.len() - matches * what.len()).saturating_add(matches.saturating_mul(with.len())),
+    )?;
+
     Ok(text.replace(&what, &with))
 }
 
@@ -418,11 +663,298 @@ pub fn reverse(_: &mut Parser<'_>, text: String) -> TResult<String> {
     Ok(String::from_utf8_lossy(&bytes).into_owned())
 }
 
+/// `{substring:start|end|text}` or `{substring:start|text}`, indices are in characters and
+/// negative indices count from the end of `text`
+pub fn substring(_: &mut Parser<'_>, args: Either<(i64, (i64, String)), (i64, String)>) -> TResult<String> {
+    let (start, end, text) = match args {
+        Either::Left((start, (end, text))) => (start, Some(end), text),
+        Either::Right((start, text)) => (start, None, text),
+    };
+
+    let chars = text.chars().count();
+    let index = |index: i64| match usize::try_from(index) {
+        Ok(index) => index,
+        Err(_) => usize::try_from(index.unsigned_abs()).map_or(0, |from_end| chars.saturating_sub(from_end)),
+    };
+    let start = index(start);
+    let len = end.map_or(usize::MAX, |end| index(end).saturating_sub(start));
+    Ok(text.chars().skip(start).take(len).collect())
+}
+
+/// Returns the character index of the first occurrence of `needle`, or -1
+pub fn indexof(_: &mut Parser<'_>, (needle, text): (String, String)) -> TResult<String> {
+    let index = text
+        .find(&needle)
+        .map_or(-1, |byte_index| text[..byte_index].chars().count() as i64);
+
+    Ok(index.to_string())
+}
+
+/// `{split:separator|text}`: splits `text` into a list, an empty separator splits it into
+/// characters
+pub fn split(parser: &mut Parser<'_>, (separator, text): (String, String)) -> TResult<String> {
+    let parts: Vec<String> = if separator.is_empty() {
+        text.chars().map(String::from).collect()
+    } else {
+        text.split(&separator).map(String::from).collect()
+    };
+
+    let list = list::serialize(&parts);
+    ensure_string_length(parser, list.len())?;
+
+    Ok(list)
+}
+
+/// `{join:separator|items...}`, or `{join:separator|list}` to join the elements of a list
+pub fn join(parser: &mut Parser<'_>, (separator, Rest(mut items)): (String, Rest<String>)) -> TResult<String> {
+    if let [single] = items.as_slice() {
+        if let Some(list) = list::parse(single) {
+            items = list;
+        }
+    }
+
+    let size =
+        items.iter().map(String::len).sum::<usize>() + separator.len().saturating_mul(items.len().saturating_sub(1));
+    ensure_string_length(parser, size)?;
+
+    Ok(items.join(&separator))
+}
+
+pub fn trim(_: &mut Parser<'_>, text: String) -> TResult<String> {
+    Ok(text.trim().to_owned())
+}
+
+fn pad(parser: &Parser<'_>, width: usize, fill: &str, text: &str, at_start: bool) -> TResult<String> {
+    let missing = width.saturating_sub(text.chars().count());
+    if missing == 0 {
+        return Ok(text.to_owned());
+    }
+
+    if fill.is_empty() {
+        return err_res(ErrorKind::ArgParseError {
+            span: parser.span(),
+            err: ParseError::Other("padding must not be empty".to_string()),
+        });
+    }
+
+    let padding = fill
+        .chars()
+        .cycle()
+        .take(missing.min(MAX_STRING_LENGTH + 1))
+        .collect::<String>();
+    ensure_string_length(parser, padding.len() + text.len())?;
+
+    Ok(if at_start {
+        padding + text
+    } else {
+        text.to_owned() + &padding
+    })
+}
+
+/// `{padstart:width|fill|text}`, the width is in characters
+pub fn padstart(parser: &mut Parser<'_>, (width, (fill, text)): (usize, (String, String))) -> TResult<String> {
+    pad(parser, width, &fill, &text, true)
+}
+
+/// `{padend:width|fill|text}`, the width is in characters
+pub fn padend(parser: &mut Parser<'_>, (width, (fill, text)): (usize, (String, String))) -> TResult<String> {
+    pad(parser, width, &fill, &text, false)
+}
+
+pub fn urlencode(parser: &mut Parser<'_>, text: String) -> TResult<String> {
+    let encoded = urlencoding::encode(&text);
+    ensure_string_length(parser, encoded.len())?;
+
+    Ok(encoded.into_owned())
+}
+
+pub fn urldecode(parser: &mut Parser<'_>, text: String) -> TResult<String> {
+    urlencoding::decode(&text)
+        .map(|decoded| decoded.into_owned())
+        .map_err(|error| {
+            err(ErrorKind::ArgParseError {
+                span: parser.span(),
+                err: ParseError::Other(format!("invalid url encoded string: {error}")),
+            })
+        })
+}
+
+/// Compiles a user provided regex, bounding the size of the compiled program so that malicious
+/// patterns cannot take up too much time or memory
+fn compile_regex(parser: &Parser<'_>, pattern: &str) -> TResult<Regex> {
+    if pattern.len() > MAX_REGEX_PATTERN_LENGTH {
+        return err_res(ErrorKind::InvalidRegex {
+            message: format!("pattern is too long ({}>{MAX_REGEX_PATTERN_LENGTH})", pattern.len()),
+            span: parser.span(),
+        });
+    }
+
+    RegexBuilder::new(pattern)
+        .size_limit(MAX_REGEX_SIZE)
+        .dfa_size_limit(MAX_REGEX_SIZE)
+        .nest_limit(MAX_REGEX_NEST)
+        .build()
+        .map_err(|error| {
+            err(ErrorKind::InvalidRegex {
+                message: error.to_string(),
+                span: parser.span(),
+            })
+        })
+}
+
+/// Returns the first match of the regex, or an empty string
+pub fn r#match(parser: &mut Parser<'_>, (pattern, text): (String, String)) -> TResult<String> {
+    let regex = compile_regex(parser, &pattern)?;
+
+    Ok(regex.find(&text).map(|m| m.as_str().to_owned()).unwrap_or_default())
+}
+
+/// `{regexreplace:pattern|replacement|text}`, the replacement may refer to capture groups with `$1`
+pub fn regexreplace(
+    parser: &mut Parser<'_>,
+    (pattern, (replacement, text)): (String, (String, String)),
+) -> TResult<String> {
+    let regex = compile_regex(parser, &pattern)?;
+
+    // every `$` can expand to at most the whole match, since capture groups are part of the match
+    let group_references = replacement.bytes().filter(|&b| b == b'$').count();
+
+    let mut output = String::new();
+    let mut last_end = 0;
+    for captures in regex.captures_iter(&text) {
+        let m = captures.get(0).expect("group 0 always participates in a match");
+        output.push_str(&text[last_end..m.start()]);
+
+        // bound the size of the expansion before doing it, a single match can otherwise expand to
+        // far more than the string limit
+        let max_expansion = replacement
+            .len()
+            .saturating_add(group_references.saturating_mul(m.len()));
+        ensure_string_length(parser, output.len().saturating_add(max_expansion))?;
+
+        captures.expand(&replacement, &mut output);
+        last_end = m.end();
+
+        ensure_string_length(parser, output.len())?;
+    }
+    output.push_str(&text[last_end..]);
+    ensure_string_length(parser, output.len())?;
+
+    Ok(output)
+}
+
+/// Case insensitive equality, returns 1 or 0
+pub fn iequals(_: &mut Parser<'_>, (a, b): (String, String)) -> TResult<String> {
+    let equal = a
+        .chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase));
+
+    Ok(u8::from(equal).to_string())
+}
+
+/// `{list:items...}`: creates a list value
+pub fn list(parser: &mut Parser<'_>, Rest(items): Rest<String>) -> TResult<String> {
+    let list = list::serialize(&items);
+    ensure_string_length(parser, list.len())?;
+
+    Ok(list)
+}
+
+/// `{index:list|index}`
+pub fn index(parser: &mut Parser<'_>, (List(items), idx): (List, usize)) -> TResult<String> {
+    let len = items.len();
+    items.into_iter().nth(idx).ok_or_else(|| {
+        err(ErrorKind::IndexOutOfBounds {
+            used_idx: idx,
+            len,
+            span: parser.span(),
+        })
+    })
+}
+
+/// Reads the list stored in a variable. A variable that does not exist is an empty list.
+fn get_list_variable(parser: &Parser<'_>, key: &str) -> TResult<Vec<String>> {
+    let Some(value) = parser.state().with_variables(|vars| vars.get(key).cloned()) else {
+        return Ok(Vec::new());
+    };
+
+    list::parse(&value).ok_or_else(|| {
+        err(ErrorKind::ArgParseError {
+            span: parser.span(),
+            err: ParseError::Other(format!("variable '{key}' does not hold a list")),
+        })
+    })
+}
+
+/// `{push:variable|items...}`: appends items to the list stored in a variable
+pub fn push(parser: &mut Parser<'_>, (key, Atleast(Rest(items))): (String, Atleast<1, String>)) -> TResult<String> {
+    if key.len() > MAX_VARIABLE_KEY_LENGTH {
+        return err_res(ErrorKind::VarKeyLengthLimit {
+            span: parser.span(),
+            length: key.len(),
+        });
+    }
+
+    let mut list = get_list_variable(parser, &key)?;
+    list.extend(items);
+    store_variable(parser, &key, list::serialize(&list))?;
+
+    Ok(String::new())
+}
+
+/// `{pop:variable}`: removes and returns the last item of the list stored in a variable
+pub fn pop(parser: &mut Parser<'_>, key: String) -> TResult<String> {
+    let mut list = get_list_variable(parser, &key)?;
+    let Some(item) = list.pop() else {
+        return Ok(String::new());
+    };
+    store_variable(parser, &key, list::serialize(&list))?;
+
+    Ok(item)
+}
+
+/// `{slice:list|start|end}` or `{slice:list|start}`
+pub fn slice(_: &mut Parser<'_>, (List(items), (start, end)): (List, (usize, Option<usize>))) -> TResult<String> {
+    let end = end.unwrap_or(items.len()).min(items.len());
+    let start = start.min(end);
+
+    Ok(list::serialize(&items[start..end]))
+}
+
+/// Sorts a list, numerically if every item is a number and lexicographically otherwise
+pub fn sort(_: &mut Parser<'_>, List(mut items): List) -> TResult<String> {
+    let numbers = items
+        .iter()
+        .map(|item| item.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>();
+
+    match numbers {
+        Ok(numbers) => {
+            let mut pairs = numbers.into_iter().zip(items).collect::<Vec<_>>();
+            pairs.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            items = pairs.into_iter().map(|(_, item)| item).collect();
+        },
+        Err(_) => items.sort(),
+    }
+
+    Ok(list::serialize(&items))
+}
+
+pub fn shuffle(parser: &mut Parser<'_>, List(mut items): List) -> TResult<String> {
+    parser.state().with_rng(|rng| items.shuffle(rng));
+    Ok(list::serialize(&items))
+}
+
+pub fn listlen(_: &mut Parser<'_>, List(items): List) -> TResult<String> {
+    Ok(items.len().to_string())
+}
+
 pub fn r#if(parser: &mut Parser<'_>) -> TResult<String> {
     if !parser.eat_separator() {
         return err_res(ErrorKind::IfMissingStmt { span: parser.span() });
     }
-    let mut stmt = parser.parse_segment(true)?;
+    let stmt = parser.parse_segment(true)?;
 
     if !parser.eat_separator() {
         return err_res(ErrorKind::IfMissingCmp { span: parser.span() });
@@ -432,7 +964,7 @@ pub fn r#if(parser: &mut Parser<'_>) -> TResult<String> {
     if !parser.eat_separator() {
         return err_res(ErrorKind::IfMissingValue { span: parser.span() });
     }
-    let mut value = parser.parse_segment(true)?;
+    let value = parser.parse_segment(true)?;
 
     if !parser.eat_separator() {
         return err_res(ErrorKind::IfMissingThen { span: parser.span() });
@@ -454,7 +986,16 @@ pub fn r#if(parser: &mut Parser<'_>) -> TResult<String> {
             parser.parse_segment(true)
         }
     }
-    fn eval_branch_with_i32s<F>(parser: &mut Parser<'_>, a: &str, b: &str, f: F) -> TResult<String>
+
+    let result = compare(parser, stmt, &comparison, value).and_then(|condition| eval_branch(parser, condition));
+
+    try_eat_closing_brace(parser)?;
+    result
+}
+
+/// Compares two values with one of the comparators supported by `{if}`
+fn compare(parser: &Parser<'_>, mut stmt: String, comparison: &str, mut value: String) -> TResult<bool> {
+    fn compare_i32s<F>(parser: &Parser<'_>, a: &str, b: &str, f: F) -> TResult<bool>
     where
         F: FnOnce(i32, i32) -> bool,
     {
@@ -472,25 +1013,22 @@ pub fn r#if(parser: &mut Parser<'_>) -> TResult<String> {
                 })
             })?,
         );
-        eval_branch(parser, f(a, b))
+        Ok(f(a, b))
     }
 
-    let result = match comparison.as_str() {
-        "=" => eval_branch(parser, stmt == value),
-        ">" => eval_branch_with_i32s(parser, &stmt, &value, |a, b| a > b),
-        ">=" => eval_branch_with_i32s(parser, &stmt, &value, |a, b| a >= b),
-        "<" => eval_branch_with_i32s(parser, &stmt, &value, |a, b| a < b),
-        "<=" => eval_branch_with_i32s(parser, &stmt, &value, |a, b| a <= b),
+    match comparison {
+        "=" => Ok(stmt == value),
+        ">" => compare_i32s(parser, &stmt, &value, |a, b| a > b),
+        ">=" => compare_i32s(parser, &stmt, &value, |a, b| a >= b),
+        "<" => compare_i32s(parser, &stmt, &value, |a, b| a < b),
+        "<=" => compare_i32s(parser, &stmt, &value, |a, b| a <= b),
         "~" => {
             stmt.make_ascii_lowercase();
             value.make_ascii_lowercase();
-            eval_branch(parser, stmt == value)
+            Ok(stmt == value)
         },
         _ => err_res(ErrorKind::IfInvalidCmp { span: parser.span() }),
-    };
-
-    try_eat_closing_brace(parser)?;
-    result
+    }
 }
 
 pub fn note(parser: &mut Parser<'_>) -> TResult<String> {
@@ -513,6 +1051,217 @@ pub fn ignore(parser: &mut Parser<'_>) -> TResult<String> {
     Ok(result)
 }
 
+/// Parses the next argument of a lazy loop tag, reporting which argument is missing if there is
+/// none. Pass `side_effects = false` for arguments that are re-evaluated per iteration; the
+/// unevaluated source is returned in that case.
+fn lazy_arg(
+    parser: &mut Parser<'_>,
+    subtag: &'static str,
+    argument: &'static str,
+    side_effects: bool,
+) -> TResult<String> {
+    if !parser.eat_separator() {
+        return err_res(ErrorKind::LazyMissingArgument {
+            subtag,
+            argument,
+            span: parser.span(),
+        });
+    }
+    parser.parse_segment(side_effects)
+}
+
+/// `{func:name|body}`: defines a function that can be executed with `{call}`. The body is stored
+/// unparsed and evaluated every time the function is called.
+pub fn func(parser: &mut Parser<'_>) -> TResult<String> {
+    let name = lazy_arg(parser, "func", "name", true)?;
+    let body = lazy_arg(parser, "func", "body", false)?;
+    try_eat_closing_brace(parser)?;
+
+    if name.len() > MAX_VARIABLE_KEY_LENGTH {
+        return err_res(ErrorKind::VarKeyLengthLimit {
+            span: parser.span(),
+            length: name.len(),
+        });
+    }
+
+    if !parser.state().define_function(name, body) {
+        return err_res(ErrorKind::FunctionLimit { span: parser.span() });
+    }
+
+    Ok(String::new())
+}
+
+/// `{call:name|args...}`: executes a function defined with `{func}`. Inside of the function,
+/// `{arg}` and `{args}` refer to the arguments of the call.
+pub fn call(parser: &mut Parser<'_>, (name, Rest(args)): (String, Rest<String>)) -> TResult<String> {
+    let Some(body) = parser.state().function(&name) else {
+        return err_res(ErrorKind::UnknownFunction {
+            name,
+            span: parser.span(),
+        });
+    };
+
+    if parser.depth() >= MAX_DEPTH {
+        return err_res(ErrorKind::DepthLimit { span: parser.span() });
+    }
+
+    if !parser.state().counter().try_iterate() {
+        return err_res(ErrorKind::IterLimit { pos: parser.pos() });
+    }
+
+    if !parser.state().enter_function(&name) {
+        return err_res(ErrorKind::RecursiveFunction {
+            name,
+            span: parser.span(),
+        });
+    }
+
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let result = Parser::from_parent_with_args(body.as_bytes(), parser, &args).parse_segment(true);
+    parser.state().exit_function();
+
+    result.map_err(|error| err(ErrorKind::Nested { source: body, error }))
+}
+
+/// Evaluates source that was skipped by `lazy_arg` in a subparser
+fn eval_lazy(parser: &Parser<'_>, source: &str) -> TResult<String> {
+    if parser.depth() >= MAX_DEPTH {
+        return err_res(ErrorKind::DepthLimit { span: parser.span() });
+    }
+    crate::parse_with_parent(source, parser, true).map_err(|error| {
+        err(ErrorKind::Nested {
+            source: source.to_owned(),
+            error,
+        })
+    })
+}
+
+/// Evaluates one iteration of a loop body and appends the result to `output`
+fn eval_loop_body(parser: &Parser<'_>, body: &str, output: &mut String) -> TResult<()> {
+    if !parser.state().counter().try_iterate() {
+        return err_res(ErrorKind::IterLimit { pos: parser.pos() });
+    }
+
+    let result = eval_lazy(parser, body)?;
+
+    if output.len() + result.len() > MAX_STRING_LENGTH {
+        return err_res(ErrorKind::StringLengthLimit {
+            span: parser.span(),
+            attempted_size: output.len() + result.len(),
+        });
+    }
+
+    output.push_str(&result);
+    Ok(())
+}
+
+/// Calls `f` with `key` reserved as a loop variable, and restores its previous value afterwards
+fn with_loop_variable<F>(parser: &mut Parser<'_>, key: &str, f: F) -> TResult<String>
+where
+    F: FnOnce(&mut Parser<'_>) -> TResult<String>,
+{
+    if key.len() > MAX_VARIABLE_KEY_LENGTH {
+        return err_res(ErrorKind::VarKeyLengthLimit {
+            span: parser.span(),
+            length: key.len(),
+        });
+    }
+
+    let previous = parser.state().with_variables(|vars| vars.get(key).cloned());
+    let result = f(parser);
+    parser.state().with_variables_mut(|vars| match previous {
+        Some(value) => vars.insert(key.to_owned(), value),
+        None => vars.remove(key),
+    });
+
+    result
+}
+
+/// `{foreach:variable|list|body}`: evaluates `body` once per item of `list`, which is either a
+/// list value or whitespace separated words
+pub fn foreach(parser: &mut Parser<'_>) -> TResult<String> {
+    let key = lazy_arg(parser, "foreach", "variable", true)?;
+    let items = lazy_arg(parser, "foreach", "list", true)?;
+    let body = lazy_arg(parser, "foreach", "body", false)?;
+    try_eat_closing_brace(parser)?;
+
+    with_loop_variable(parser, &key, |parser| {
+        let mut output = String::new();
+        let items = match list::parse(&items) {
+            Some(list) => Either::Left(list.into_iter()),
+            None => Either::Right(items.split_ascii_whitespace().map(str::to_owned)),
+        };
+        for item in items {
+            store_variable(parser, &key, item)?;
+            eval_loop_body(parser, &body, &mut output)?;
+        }
+        Ok(output)
+    })
+}
+
+/// `{for:variable|start|end|body}`: evaluates `body` once per number from `start` to `end`
+/// (inclusive), counting down if `start` is greater than `end`
+pub fn r#for(parser: &mut Parser<'_>) -> TResult<String> {
+    let key = lazy_arg(parser, "for", "variable", true)?;
+    let start = lazy_arg(parser, "for", "start", true)?;
+    let end = lazy_arg(parser, "for", "end", true)?;
+    let body = lazy_arg(parser, "for", "body", false)?;
+    try_eat_closing_brace(parser)?;
+
+    let parse_bound = |bound: String| -> TResult<i64> {
+        bound.parse().map_err(|error| {
+            err(ErrorKind::ArgParseError {
+                span: parser.span(),
+                err: ParseError::I64FromStrError(error, bound),
+            })
+        })
+    };
+    let (start, end) = (parse_bound(start)?, parse_bound(end)?);
+
+    let values = if start <= end {
+        Either::Left(start..=end)
+    } else {
+        Either::Right((end..=start).rev())
+    };
+
+    with_loop_variable(parser, &key, |parser| {
+        let mut output = String::new();
+        for value in values {
+            store_variable(parser, &key, value.to_string())?;
+            eval_loop_body(parser, &body, &mut output)?;
+        }
+        Ok(output)
+    })
+}
+
+/// `{while:value|comparator|condition|body}`: evaluates `body` for as long as the comparison (see
+/// `{if}`) holds. The comparison is re-evaluated before every iteration
+pub fn r#while(parser: &mut Parser<'_>) -> TResult<String> {
+    let stmt = lazy_arg(parser, "while", "value", false)?;
+    let comparison = lazy_arg(parser, "while", "comparator", false)?;
+    let value = lazy_arg(parser, "while", "condition", false)?;
+    let body = lazy_arg(parser, "while", "body", false)?;
+    try_eat_closing_brace(parser)?;
+
+    let mut output = String::new();
+    loop {
+        let condition = compare(
+            parser,
+            eval_lazy(parser, &stmt)?,
+            &eval_lazy(parser, &comparison)?,
+            eval_lazy(parser, &value)?,
+        )?;
+
+        if !condition {
+            break;
+        }
+
+        eval_loop_body(parser, &body, &mut output)?;
+    }
+
+    Ok(output)
+}
+
 pub fn mention(parser: &mut Parser<'_>, id: Option<u64>) -> TResult<String> {
     Ok(format!(
         "<@{}>",
@@ -545,13 +1294,149 @@ pub fn javascript(parser: &mut Parser<'_>, code: String) -> TResult<String> {
 
     match result {
         FakeEvalImageResponse::Image(img, ty) => {
-            parser.state().set_attachment(img, ty);
+            add_attachment(parser, format!("attachment.{}", ty.as_str()), img)?;
             Ok(String::new())
         },
         FakeEvalImageResponse::Text(t) => Ok(t.message),
     }
 }
 
+fn add_attachment(parser: &Parser<'_>, name: String, data: Vec<u8>) -> TResult<()> {
+    if !parser.state().add_attachment(Attachment { name, data }) {
+        return err_res(ErrorKind::AttachmentLimit { span: parser.span() });
+    }
+    Ok(())
+}
+
+pub fn attach(parser: &mut Parser<'_>, (name, content): (String, String)) -> TResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return err_res(ErrorKind::ArgParseError {
+            span: parser.span(),
+            err: ParseError::Other("file name must not be empty".to_string()),
+        });
+    }
+
+    add_attachment(parser, name.to_owned(), content.into_bytes())?;
+    Ok(String::new())
+}
+
+/// Ensures that `text` fits in the embed part with the given maximum length
+fn ensure_embed_length(parser: &Parser<'_>, part: &'static str, text: &str, max: usize) -> TResult<()> {
+    let length = text.chars().count();
+    if length > max {
+        return err_res(ErrorKind::EmbedLengthLimit {
+            part,
+            length,
+            max,
+            span: parser.span(),
+        });
+    }
+    Ok(())
+}
+
+pub fn embedtitle(parser: &mut Parser<'_>, title: String) -> TResult<String> {
+    ensure_embed_length(parser, "title", &title, output::limits::MAX_TITLE_LENGTH)?;
+
+    parser.state().with_embed_mut(|embed| embed.title = Some(title));
+    Ok(String::new())
+}
+
+pub fn embeddescription(parser: &mut Parser<'_>, description: String) -> TResult<String> {
+    ensure_embed_length(
+        parser,
+        "description",
+        &description,
+        output::limits::MAX_DESCRIPTION_LENGTH,
+    )?;
+
+    parser
+        .state()
+        .with_embed_mut(|embed| embed.description = Some(description));
+    Ok(String::new())
+}
+
+/// Accepts hex colours, optionally prefixed with `#` or `0x`
+pub fn embedcolour(parser: &mut Parser<'_>, colour: String) -> TResult<String> {
+    let colour = colour.trim();
+    let hex = colour
+        .strip_prefix('#')
+        .or_else(|| colour.strip_prefix("0x"))
+        .unwrap_or(colour);
+
+    // from_str_radix also accepts a leading sign
+    let colour = Some(hex)
+        .filter(|hex| !hex.is_empty() && hex.len() <= 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| {
+            err(ErrorKind::ArgParseError {
+                span: parser.span(),
+                err: ParseError::Other(format!("'{colour}' is not a valid hex colour")),
+            })
+        })?;
+
+    parser.state().with_embed_mut(|embed| embed.colour = Some(colour));
+    Ok(String::new())
+}
+
+pub fn embedimage(parser: &mut Parser<'_>, url: String) -> TResult<String> {
+    parser
+        .state()
+        .with_embed_mut(|embed| embed.image = Some(url.trim().to_owned()));
+    Ok(String::new())
+}
+
+pub fn embedfield(
+    parser: &mut Parser<'_>,
+    (name, (value, inline)): (String, (String, Option<String>)),
+) -> TResult<String> {
+    ensure_embed_length(parser, "field name", &name, output::limits::MAX_FIELD_NAME_LENGTH)?;
+    ensure_embed_length(parser, "field value", &value, output::limits::MAX_FIELD_VALUE_LENGTH)?;
+
+    let field = EmbedField {
+        name,
+        value,
+        inline: inline.is_some_and(|inline| inline.trim() == "inline"),
+    };
+
+    let added = parser.state().with_embed_mut(|embed| {
+        if embed.fields.len() >= output::limits::MAX_FIELDS {
+            return false;
+        }
+        embed.fields.push(field);
+        true
+    });
+
+    if !added {
+        return err_res(ErrorKind::EmbedFieldLimit { span: parser.span() });
+    }
+    Ok(String::new())
+}
+
+pub fn embedfooter(parser: &mut Parser<'_>, footer: String) -> TResult<String> {
+    ensure_embed_length(parser, "footer", &footer, output::limits::MAX_FOOTER_LENGTH)?;
+
+    parser.state().with_embed_mut(|embed| embed.footer = Some(footer));
+    Ok(String::new())
+}
+
+pub fn image(
+    parser: &mut Parser<'_>,
+    (operation, (url, Rest(args))): (String, (String, Rest<String>)),
+) -> TResult<String> {
+    ensure_request_limit!(parser);
+
+    // the context knows which operations exist and which options they take
+    let output = parser
+        .context()
+        .run_flux(&operation.trim().to_ascii_lowercase(), args, url.trim())
+        .map_err(|err| wrap_anyhow(parser.span(), err))?;
+
+    let ty = get_sig(&output).unwrap_or(Type::PNG);
+    add_attachment(parser, format!("image.{}", ty.as_str()), output)?;
+    Ok(String::new())
+}
+
 pub fn attachment_last(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
     ensure_request_limit!(parser);
 
@@ -595,6 +1480,92 @@ pub fn userid(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
         .to_string())
 }
 
+pub fn servername(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    parser
+        .context()
+        .guild_name()
+        .map_err(|err| wrap_anyhow(parser.span(), err))
+}
+
+pub fn membercount(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    Ok(parser
+        .context()
+        .guild_member_count()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .to_string())
+}
+
+pub fn channelname(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    parser
+        .context()
+        .channel_name()
+        .map_err(|err| wrap_anyhow(parser.span(), err))
+}
+
+pub fn channeltopic(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    Ok(parser
+        .context()
+        .channel_topic()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .unwrap_or_default())
+}
+
+pub fn nickname(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    Ok(parser
+        .context()
+        .nickname()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .unwrap_or_default())
+}
+
+/// Returns a list of the role names of the invoker
+pub fn roles(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    let roles = parser
+        .context()
+        .role_names()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?;
+
+    Ok(list::serialize(&roles))
+}
+
+pub fn joindate(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    ensure_context_lookup_limit!(parser);
+
+    Ok(parser
+        .context()
+        .joined_at()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .to_string())
+}
+
+pub fn replycontent(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    Ok(parser
+        .context()
+        .replied_message_content()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .unwrap_or_default())
+}
+
+pub fn messageid(parser: &mut Parser<'_>, _: ()) -> TResult<String> {
+    Ok(parser
+        .context()
+        .message_id()
+        .map_err(|err| wrap_anyhow(parser.span(), err))?
+        .to_string())
+}
+
 pub fn idof(_: &mut Parser<'_>, mention: Mention) -> TResult<String> {
     Ok(mention.0.to_string())
 }
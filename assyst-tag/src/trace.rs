@@ -0,0 +1,159 @@
+//! Execution traces, used for debugging tags.
+//!
+//! When tracing is enabled, every subtag invocation (including those in subparsers, such as the
+//! body of an `{eval}` or a loop) is recorded along with its arguments and result, in the order in
+//! which the invocations started.
+//!
+//! Spans are byte offsets into the source that was being parsed. For invocations in subparsers,
+//! such as the body of a loop or an `{eval}`, that is the source of the subparser rather than the
+//! source of the tag.
+
+use std::ops::Range;
+
+use assyst_string_fmt::Ansi;
+
+use crate::errors::TResult;
+
+/// Maximum number of recorded invocations, anything after this is dropped
+pub const MAX_TRACE_EVENTS: usize = 1_000;
+/// Recorded sources, arguments and results are truncated to this many characters
+pub const MAX_TRACE_VALUE_LENGTH: usize = 100;
+
+#[derive(Debug, Clone)]
+pub enum TraceOutcome {
+    Ok(String),
+    Error,
+    /// The invocation never finished, e.g. because one of its arguments failed to evaluate
+    Unfinished,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    /// How many invocations this one is nested in
+    pub depth: usize,
+    pub name: String,
+    /// The invocation as written in the tag, e.g. `{arg:0}`
+    pub source: String,
+    /// Byte span of the invocation, see the module documentation. Unfinished invocations only
+    /// know where they start, so their span is empty
+    pub span: Range<usize>,
+    /// Evaluated arguments. Always empty for lazy tags, which evaluate their own arguments
+    pub args: Vec<String>,
+    pub outcome: TraceOutcome,
+}
+
+/// Identifies a started invocation, see [`Trace::start`]
+pub(crate) struct TraceToken {
+    /// Index of the event, or `None` if it was dropped
+    id: Option<usize>,
+    /// The depth before the invocation started
+    depth: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    events: Vec<TraceEvent>,
+    depth: usize,
+    /// Whether events were dropped because of [`MAX_TRACE_EVENTS`]
+    truncated: bool,
+}
+
+fn truncate(value: &str) -> String {
+    match value.char_indices().nth(MAX_TRACE_VALUE_LENGTH) {
+        Some((idx, _)) => format!("{}...", &value[..idx]),
+        None => value.to_owned(),
+    }
+}
+
+impl Trace {
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Records the start of an invocation at byte `start`
+    pub(crate) fn start(&mut self, name: &str, start: usize) -> TraceToken {
+        let depth = self.depth;
+        self.depth += 1;
+
+        if self.events.len() >= MAX_TRACE_EVENTS {
+            self.truncated = true;
+            return TraceToken { id: None, depth };
+        }
+
+        self.events.push(TraceEvent {
+            depth,
+            name: name.to_owned(),
+            source: String::new(),
+            span: start..start,
+            args: Vec::new(),
+            outcome: TraceOutcome::Unfinished,
+        });
+        TraceToken {
+            id: Some(self.events.len() - 1),
+            depth,
+        }
+    }
+
+    pub(crate) fn args(&mut self, token: &TraceToken, args: &[String]) {
+        if let Some(event) = token.id.and_then(|id| self.events.get_mut(id)) {
+            event.args = args.iter().map(|arg| truncate(arg)).collect();
+        }
+    }
+
+    /// Records the end of an invocation spanning `span` of `input`
+    pub(crate) fn end(&mut self, token: TraceToken, input: &[u8], span: Range<usize>, result: &TResult<String>) {
+        // restore the depth instead of decrementing it, as invocations that failed halfway never
+        // get to call this
+        self.depth = token.depth;
+
+        if let Some(event) = token.id.and_then(|id| self.events.get_mut(id)) {
+            event.source = truncate(&String::from_utf8_lossy(&input[span.clone()]));
+            event.span = span;
+            event.outcome = match result {
+                Ok(output) => TraceOutcome::Ok(truncate(output)),
+                Err(_) => TraceOutcome::Error,
+            };
+        }
+    }
+
+    /// Renders the trace as a tree, one invocation per block. `ansi` controls whether the output is
+    /// colored using ANSI escape codes
+    pub fn render(&self, ansi: bool) -> String {
+        let paint = |text: String, f: fn(&String) -> String| if ansi { f(&text) } else { text };
+
+        let mut out = String::new();
+        for event in &self.events {
+            out += &"  ".repeat(event.depth);
+            // unfinished events never recorded their source
+            let header = if event.source.is_empty() {
+                format!("{{{}...", event.name)
+            } else {
+                event.source.replace('\n', " ")
+            };
+            out += &paint(header, |s| s.fg_cyan());
+            let span = match event.outcome {
+                TraceOutcome::Unfinished => format!(" @ {}..", event.span.start),
+                _ => format!(" @ {}..{}", event.span.start, event.span.end),
+            };
+            out += &paint(span, |s| s.fg_blue());
+
+            for (index, arg) in event.args.iter().enumerate() {
+                out += &format!("\n{}  {index}: {:?}", "  ".repeat(event.depth), arg);
+            }
+
+            out += &format!("\n{}  ", "  ".repeat(event.depth));
+            out += &match &event.outcome {
+                TraceOutcome::Ok(output) => paint(format!("=> {output:?}"), |s| s.fg_green()),
+                TraceOutcome::Error => paint("=> error".to_owned(), |s| s.fg_red()),
+                TraceOutcome::Unfinished => paint("=> did not finish".to_owned(), |s| s.fg_yellow()),
+            };
+            out += "\n";
+        }
+
+        if self.truncated {
+            out += &format!("... trace truncated after {MAX_TRACE_EVENTS} invocations\n");
+        }
+
+        out
+    }
+}